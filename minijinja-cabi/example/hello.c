@@ -1,11 +1,18 @@
 #include <minijinja.h>
 #include <stdio.h>
+#include <string.h>
 #include <assert.h>
 
+static int write_to_file(void *userdata, const char *data, size_t len)
+{
+    return fwrite(data, 1, len, (FILE *)userdata) == len ? 0 : 1;
+}
+
 int main()
 {
     mj_env *env = mj_env_new();
     mj_env_set_debug(env, true);
+    mj_env_set_auto_escape(env, MJ_AUTO_ESCAPE_HTML);
 
     bool ok = mj_env_add_template(env, "hello", "\
 Hello {{ name }}!\n\
@@ -42,7 +49,7 @@ seq: {{ seq }}");
 
     // store the values in the struct
     mj_value_set_string_key(&ctx, "seq", seq);
-    mj_value_set_string_key(&ctx, "name", mj_value_new_string("C-Lang"));
+    mj_value_set_string_key(&ctx, "name", mj_value_new_string("<C-Lang>"));
 
     // render a template
     char *rv = mj_env_render_template(env, "hello", ctx);
@@ -53,6 +60,29 @@ seq: {{ seq }}");
         mj_str_free(rv);
     }
 
+    // render a template in chunks into a callback
+    mj_value stream_ctx = mj_value_new_object();
+    mj_value_set_string_key(&stream_ctx, "name", mj_value_new_string("Stream"));
+    if (!mj_env_render_template_to_cb(env, "hello", stream_ctx, write_to_file, stdout)) {
+        mj_err_print();
+    } else {
+        printf("\n");
+    }
+
+    // values can be created from and converted to JSON
+    const char *json = "{\"name\": \"JSON\", \"seq\": [1, 2]}";
+    mj_value json_ctx = mj_value_from_json(json, strlen(json));
+    char *json_out = mj_value_to_json(json_ctx);
+    fprintf(stderr, "json: %s\n", json_out);
+    mj_str_free(json_out);
+    rv = mj_env_render_template(env, "hello", json_ctx);
+    if (!rv) {
+        mj_err_print();
+    } else {
+        printf("%s\n", rv);
+        mj_str_free(rv);
+    }
+
     // eval an expression
     mj_value erv = mj_env_eval_expr(env, "1 + 2", mj_value_new_object());
     char *ervs = mj_value_to_str(erv);
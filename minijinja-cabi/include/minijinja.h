@@ -18,6 +18,26 @@
 #endif
 
 
+/*
+ Controls the auto escaping of the engine.
+
+ The values of this enum are stable.
+ */
+typedef enum mj_auto_escape {
+  /*
+   Do not auto escape.
+   */
+  MJ_AUTO_ESCAPE_NONE,
+  /*
+   Escape for HTML/XML.
+   */
+  MJ_AUTO_ESCAPE_HTML,
+  /*
+   Escape for JSON.  Requires the `json` feature.
+   */
+  MJ_AUTO_ESCAPE_JSON,
+} mj_auto_escape;
+
 /*
  The kind of error that occurred.
  */
@@ -45,6 +65,8 @@ typedef enum mj_err_kind {
 
 /*
  Controls the undefined behavior of the engine.
+
+ The values of this enum are stable.
  */
 typedef enum mj_undefined_behavior {
   /*
@@ -59,6 +81,10 @@ typedef enum mj_undefined_behavior {
    Like Lenient, but also allows chaining of undefined lookups.
    */
   MJ_UNDEFINED_BEHAVIOR_CHAINABLE,
+  /*
+   Like Lenient, but fails when undefined values are printed.
+   */
+  MJ_UNDEFINED_BEHAVIOR_STRICT_OUTPUT,
 } mj_undefined_behavior;
 
 /*
@@ -109,6 +135,11 @@ typedef struct mj_syntax_config {
   const char *line_comment_prefix;
 } mj_syntax_config;
 
+/*
+ Callback that receives rendered output in chunks.
+ */
+typedef int (*mj_write_cb)(void *userdata, const char *data, size_t len);
+
 #ifdef __cplusplus
 extern "C" {
 #endif // __cplusplus
@@ -163,6 +194,21 @@ char *mj_env_render_template(const struct mj_env *env,
                              const char *name,
                              struct mj_value ctx);
 
+/*
+ Renders a template registered on the environment into a callback.
+ */
+MINIJINJA_API
+bool mj_env_render_template_to_cb(const struct mj_env *env,
+                                  const char *name,
+                                  struct mj_value ctx,
+                                  mj_write_cb write_cb,
+                                  void *userdata);
+
+/*
+ Sets the auto escaping for all templates.
+ */
+MINIJINJA_API bool mj_env_set_auto_escape(struct mj_env *env, enum mj_auto_escape val);
+
 /*
  Enables or disables debug mode.
  */
@@ -272,6 +318,11 @@ MINIJINJA_API uint64_t mj_value_as_u64(struct mj_value value);
  */
 MINIJINJA_API void mj_value_dbg(struct mj_value value);
 
+/*
+ Creates a value by parsing a JSON string.
+ */
+MINIJINJA_API struct mj_value mj_value_from_json(const char *s, size_t len);
+
 /*
  Decrements the refcount
  */
@@ -403,6 +454,11 @@ bool mj_value_set_string_key(struct mj_value *slf,
                              const char *key,
                              struct mj_value value);
 
+/*
+ Serializes the value into a JSON string.
+ */
+MINIJINJA_API char *mj_value_to_json(struct mj_value value);
+
 /*
  Converts the value into a string.
  */
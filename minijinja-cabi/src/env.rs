@@ -1,8 +1,8 @@
-use std::ffi::{c_char, CString};
-use std::ptr;
+use std::ffi::{c_char, c_int, c_void, CString};
+use std::{io, ptr};
 
 use minijinja::syntax::SyntaxConfig;
-use minijinja::{Environment, Error, ErrorKind, UndefinedBehavior};
+use minijinja::{AutoEscape, Environment, Error, ErrorKind, UndefinedBehavior};
 
 use crate::mj_value;
 
@@ -86,6 +86,61 @@ ffi_fn! {
     }
 }
 
+/// Callback that receives rendered output in chunks.
+///
+/// The chunk is not null terminated.  Returning a non zero value aborts
+/// rendering with a write failure.
+pub type mj_write_cb =
+    unsafe extern "C" fn(userdata: *mut c_void, data: *const c_char, len: usize) -> c_int;
+
+struct CallbackWriter {
+    write_cb: mj_write_cb,
+    userdata: *mut c_void,
+}
+
+impl io::Write for CallbackWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        match unsafe { (self.write_cb)(self.userdata, buf.as_ptr() as *const _, buf.len()) } {
+            0 => Ok(buf.len()),
+            rv => Err(io::Error::other(format!(
+                "write callback aborted with code {rv}"
+            ))),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+ffi_fn! {
+    /// Renders a template registered on the environment into a callback.
+    ///
+    /// Instead of allocating the entire output, the rendered template is
+    /// passed to `write_cb` in chunks together with `userdata`.  If the
+    /// callback returns a non zero value rendering is aborted.  Returns
+    /// `false` on error in which case the error can be inspected with the
+    /// `mj_err_*` functions.
+    ///
+    /// Takes ownership of the given context.
+    unsafe fn mj_env_render_template_to_cb(
+        scope,
+        env: *const mj_env,
+        name: *const c_char,
+        ctx: mj_value,
+        write_cb: mj_write_cb,
+        userdata: *mut c_void
+    ) -> bool {
+        let ctx = ctx.into_value();
+        let t = (*env).0.get_template(scope.get_str(name)?)?;
+        t.render_to_write(ctx, CallbackWriter { write_cb, userdata })?;
+        true
+    }
+}
+
 ffi_fn! {
     /// Renders a template from a named string.
     ///
@@ -208,7 +263,46 @@ ffi_fn! {
     }
 }
 
+/// Controls the auto escaping of the engine.
+///
+/// The values of this enum are stable.
+#[repr(C)]
+pub enum mj_auto_escape {
+    /// Do not auto escape.
+    MJ_AUTO_ESCAPE_NONE,
+    /// Escape for HTML/XML.
+    MJ_AUTO_ESCAPE_HTML,
+    /// Escape for JSON.  Requires the `json` feature.
+    MJ_AUTO_ESCAPE_JSON,
+}
+
+ffi_fn! {
+    /// Sets the auto escaping for all templates.
+    ///
+    /// This replaces the default which enables HTML escaping based on the
+    /// file extension of the template.
+    unsafe fn mj_env_set_auto_escape(_scope, env: *mut mj_env, val: mj_auto_escape) -> bool {
+        let auto_escape = match val {
+            mj_auto_escape::MJ_AUTO_ESCAPE_NONE => AutoEscape::None,
+            mj_auto_escape::MJ_AUTO_ESCAPE_HTML => AutoEscape::Html,
+            #[cfg(feature = "json")]
+            mj_auto_escape::MJ_AUTO_ESCAPE_JSON => AutoEscape::Json,
+            #[cfg(not(feature = "json"))]
+            mj_auto_escape::MJ_AUTO_ESCAPE_JSON => {
+                return Err(Error::new(
+                    ErrorKind::InvalidOperation,
+                    "json auto escaping requires the json feature",
+                ));
+            }
+        };
+        (*env).0.set_auto_escape_callback(move |_| auto_escape);
+        true
+    }
+}
+
 /// Controls the undefined behavior of the engine.
+///
+/// The values of this enum are stable.
 #[repr(C)]
 pub enum mj_undefined_behavior {
     /// The default, somewhat lenient undefined behavior.
@@ -217,6 +311,8 @@ pub enum mj_undefined_behavior {
     MJ_UNDEFINED_BEHAVIOR_STRICT,
     /// Like Lenient, but also allows chaining of undefined lookups.
     MJ_UNDEFINED_BEHAVIOR_CHAINABLE,
+    /// Like Lenient, but fails when undefined values are printed.
+    MJ_UNDEFINED_BEHAVIOR_STRICT_OUTPUT,
 }
 
 ffi_fn! {
@@ -226,6 +322,9 @@ ffi_fn! {
             mj_undefined_behavior::MJ_UNDEFINED_BEHAVIOR_LENIENT => UndefinedBehavior::Lenient,
             mj_undefined_behavior::MJ_UNDEFINED_BEHAVIOR_STRICT => UndefinedBehavior::Strict,
             mj_undefined_behavior::MJ_UNDEFINED_BEHAVIOR_CHAINABLE => UndefinedBehavior::Chainable,
+            mj_undefined_behavior::MJ_UNDEFINED_BEHAVIOR_STRICT_OUTPUT => {
+                UndefinedBehavior::StrictOutput
+            }
         })
     }
 }
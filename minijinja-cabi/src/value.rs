@@ -273,6 +273,40 @@ ffi_fn! {
     }
 }
 
+#[cfg(feature = "json")]
+ffi_fn! {
+    /// Creates a value by parsing a JSON string.
+    ///
+    /// `len` is the length of the string in bytes, the string does not need
+    /// to be null terminated.  On invalid JSON an error is set and undefined
+    /// is returned.
+    unsafe fn mj_value_from_json(_scope, s: *const c_char, len: usize) -> mj_value {
+        let bytes = if s.is_null() {
+            &[][..]
+        } else {
+            std::slice::from_raw_parts(s as *const u8, len)
+        };
+        serde_json::from_slice::<Value>(bytes).map_err(|err| {
+            Error::new(ErrorKind::BadSerialization, "invalid JSON").with_source(err)
+        })?.into()
+    }
+}
+
+#[cfg(feature = "json")]
+ffi_fn! {
+    /// Serializes the value into a JSON string.
+    ///
+    /// The returned string needs to be freed with `mj_str_free`.
+    unsafe fn mj_value_to_json(_scope, value: mj_value) -> *mut c_char {
+        let rv = serde_json::to_string(&value as &Value).map_err(|err| {
+            Error::new(ErrorKind::BadSerialization, "unable to format to JSON").with_source(err)
+        })?;
+        CString::new(rv).map_err(|_| {
+            Error::new(ErrorKind::InvalidOperation, "string contains null bytes")
+        })?.into_raw()
+    }
+}
+
 ffi_fn! {
     /// Extracts an integer from the value
     unsafe fn mj_value_as_i64(_scope, value: mj_value) -> i64 {
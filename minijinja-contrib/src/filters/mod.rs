@@ -251,7 +251,8 @@ pub fn wordcount(value: &Value) -> Result<Value, Error> {
 ///
 /// By default this filter is not unicode aware (feature = `wordwrap`) but when the unicode
 /// feature is enabled (`unicode_wordwrap`) then it becomes so.  It's implemented on top of
-/// the `textwrap` crate.
+/// the `textwrap` crate.  When registered it replaces the basic `wordwrap` filter that
+/// comes with MiniJinja.
 ///
 /// **Keyword arguments:**
 ///
@@ -307,3 +308,186 @@ pub fn wordwrap(value: &Value, kwargs: Kwargs) -> Result<Value, Error> {
         },
     )))
 }
+
+/// Renders Markdown into HTML.
+///
+/// The output is marked as safe so it is not escaped again when auto escaping
+/// is enabled.  Values that are not strings are converted to strings first.
+/// It's implemented on top of the `pulldown-cmark` crate and requires the
+/// `markdown` feature.  Unlike the rest of the crate this feature requires
+/// Rust 1.71.1 or later.
+///
+/// ```jinja
+/// {{ post.body|markdown }}
+/// {{ title|markdown(inline=true) }}
+/// ```
+///
+/// **Keyword arguments:**
+///
+/// - `extensions`: list of extensions to enable on top of CommonMark.  Supported
+///   are `tables`, `strikethrough`, `footnotes`, `tasklists` and `smart_punctuation`
+///   (default: none)
+/// - `sanitize`: if set to `true`, raw HTML in the source is escaped instead of
+///   passed through and links or images with `javascript:`, `vbscript:` or `data:`
+///   URLs are removed (default: false)
+/// - `inline`: if set to `true`, paragraphs are not wrapped in `<p>` tags which
+///   is useful for rendering short snippets such as titles (default: false)
+#[cfg(feature = "markdown")]
+#[cfg_attr(docsrs, doc(cfg(feature = "markdown")))]
+pub fn markdown(value: &Value, kwargs: Kwargs) -> Result<Value, Error> {
+    use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag, TagEnd};
+
+    let s = value.to_string();
+    let extensions = kwargs
+        .get::<Option<Vec<String>>>("extensions")?
+        .unwrap_or_default();
+    let sanitize = kwargs.get::<Option<bool>>("sanitize")?.unwrap_or(false);
+    let inline = kwargs.get::<Option<bool>>("inline")?.unwrap_or(false);
+    kwargs.assert_all_used()?;
+
+    let mut options = Options::empty();
+    for ext in &extensions {
+        options.insert(match ext.as_str() {
+            "tables" => Options::ENABLE_TABLES,
+            "strikethrough" => Options::ENABLE_STRIKETHROUGH,
+            "footnotes" => Options::ENABLE_FOOTNOTES,
+            "tasklists" => Options::ENABLE_TASKLISTS,
+            "smart_punctuation" => Options::ENABLE_SMART_PUNCTUATION,
+            other => {
+                return Err(Error::new(
+                    ErrorKind::InvalidOperation,
+                    format!("unknown markdown extension '{}'", other),
+                ))
+            }
+        });
+    }
+
+    fn is_unsafe_url(url: &str) -> bool {
+        let url = url.trim_start().to_ascii_lowercase();
+        url.starts_with("javascript:") || url.starts_with("vbscript:") || url.starts_with("data:")
+    }
+
+    let events = Parser::new_ext(&s, options).filter_map(|mut event| match event {
+        Event::Start(Tag::Paragraph) | Event::End(TagEnd::Paragraph) if inline => None,
+        Event::Html(html) | Event::InlineHtml(html) if sanitize => Some(Event::Text(html)),
+        Event::Start(
+            Tag::Link {
+                ref mut dest_url, ..
+            }
+            | Tag::Image {
+                ref mut dest_url, ..
+            },
+        ) if sanitize && is_unsafe_url(dest_url) => {
+            *dest_url = CowStr::Borrowed("");
+            Some(event)
+        }
+        other => Some(other),
+    });
+
+    let mut rv = String::with_capacity(s.len() * 3 / 2);
+    html::push_html(&mut rv, events);
+    Ok(Value::from_safe_string(rv))
+}
+
+/// Converts a string into a URL slug.
+///
+/// The string is lowercased, runs of characters that are not ASCII letters or
+/// digits are replaced by a single separator and leading and trailing separators
+/// are removed.  Other non-ASCII characters are stripped unless the
+/// `unicode_slugify` feature is enabled in which case they are transliterated
+/// to ASCII first (via the `deunicode` crate).
+///
+/// ```jinja
+/// <a href="/posts/{{ post.title|slugify }}">{{ post.title }}</a>
+/// ```
+///
+/// **Keyword arguments:**
+///
+/// - `separator`: string used to join the words (default: `-`)
+/// - `max_length`: maximum length of the slug in characters.  The slug is cut
+///   at that length and a trailing separator is removed afterwards, even if it
+///   was only cut partially (default: unlimited)
+///
+/// Values that are not strings are converted to strings first.  If the input
+/// consists only of symbols the result is an empty string.
+pub fn slugify(value: &Value, kwargs: Kwargs) -> Result<String, Error> {
+    let s = value.to_string();
+    let separator = kwargs.get::<Option<&str>>("separator")?.unwrap_or("-");
+    let max_length = kwargs.get::<Option<usize>>("max_length")?;
+    kwargs.assert_all_used()?;
+
+    #[cfg(feature = "unicode_slugify")]
+    let s = deunicode::deunicode(&s);
+
+    let mut rv = String::with_capacity(s.len());
+    let mut separators = Vec::new();
+    let mut pending_separator = false;
+    for c in s.chars() {
+        if c.is_ascii_alphanumeric() {
+            if pending_separator && !rv.is_empty() {
+                separators.push(rv.len());
+                rv.push_str(separator);
+            }
+            pending_separator = false;
+            rv.push(c.to_ascii_lowercase());
+        } else if c.is_ascii() {
+            pending_separator = true;
+        }
+    }
+
+    if let Some(max_length) = max_length {
+        if let Some((idx, _)) = rv.char_indices().nth(max_length) {
+            // if the cut ends in a separator, drop all of it
+            let idx = match separators.iter().rev().find(|&&start| start < idx) {
+                Some(&start) if idx <= start + separator.len() => start,
+                _ => idx,
+            };
+            rv.truncate(idx);
+        }
+    }
+
+    Ok(rv)
+}
+
+/// Converts newlines into HTML line breaks.
+///
+/// The input is HTML escaped first unless it's already marked as safe and every
+/// newline (`\n`, `\r\n` or `\r`) is replaced by `<br>` followed by a newline.
+/// Values that are not strings are converted to strings first.  The result is
+/// marked as safe.
+///
+/// ```jinja
+/// <p>{{ comment.body|nl2br }}</p>
+/// ```
+///
+/// **Keyword arguments:**
+///
+/// - `xhtml`: if set to `true`, `<br />` is emitted instead of `<br>` (default: false)
+pub fn nl2br(value: &Value, kwargs: Kwargs) -> Result<Value, Error> {
+    use minijinja::HtmlEscape;
+    use std::fmt::Write;
+
+    let xhtml = kwargs.get::<Option<bool>>("xhtml")?.unwrap_or(false);
+    kwargs.assert_all_used()?;
+
+    let s = value.to_string();
+    let br = if xhtml { "<br />\n" } else { "<br>\n" };
+    let mut rv = String::with_capacity(s.len());
+    for (idx, line) in s.split('\n').enumerate() {
+        if idx > 0 {
+            rv.push_str(br);
+        }
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        for (idx, part) in line.split('\r').enumerate() {
+            if idx > 0 {
+                rv.push_str(br);
+            }
+            if value.is_safe() {
+                rv.push_str(part);
+            } else {
+                write!(rv, "{}", HtmlEscape(part)).ok();
+            }
+        }
+    }
+    Ok(Value::from_safe_string(rv))
+}
@@ -163,14 +163,22 @@ pub fn randrange(state: &State, n: i64, m: Option<i64>) -> i64 {
 /// Generates a random lorem ipsum.
 ///
 /// The random number generated can be seeded with the `RAND_SEED`
-/// global context variable.
+/// global context variable.  With a fixed seed the output is stable which
+/// makes it suitable for snapshot tests.
 ///
 /// The function accepts various keyword arguments:
 ///
-/// * `n`: number of paragraphs to generate.
-/// * `min`: minimum number of words to generate per paragraph.
-/// * `max`: maximum number of words to generate per paragraph.
-/// * `html`: set to `true` to generate HTML paragraphs instead.
+/// * `n`: number of paragraphs to generate (defaults to `5`).  Can also
+///   be passed as first positional argument.
+/// * `min`: minimum number of words to generate per paragraph (defaults to `20`).
+/// * `max`: maximum number of words to generate per paragraph (defaults to `100`).
+/// * `html`: set to `true` to generate HTML paragraphs instead.  The
+///   returned value is then marked as safe.
+///
+/// ```jinja
+/// {% set RAND_SEED = 42 %}
+/// {{ lipsum(2, html=true, min=10, max=20) }}
+/// ```
 #[cfg(feature = "rand")]
 #[cfg_attr(docsrs, doc(cfg(feature = "rand")))]
 pub fn lipsum(
@@ -205,12 +213,12 @@ pub fn lipsum(
         "posuere", "potenti", "praesent", "pretium", "primis", "proin",
         "pulvinar", "purus", "quam", "quis", "quisque", "rhoncus", "ridiculus",
         "risus", "rutrum", "sagittis", "sapien", "scelerisque", "sed", "sem",
-        "semper", "senectus", "sit", "sociis", "sociosqu", "ssociis",
-        "sociosqu", "ssociis", "sociosqu", "ssociis", "sociosqu", "ssociis",
-        "sociosqu", "ssoincidusociis", "sociosqu", "ssociis", "sociosqu",
-        "ssociis", "sociosqu", "ssociis", "sociosqu", "ssociis", "vsociis",
-        "sociosqu", "ssociis", "sociosqu", "ssociis", "sociosqu", "ssociis",
-        "sociosqu", "ssociis", "s", "vulputate",
+        "semper", "senectus", "sit", "sociis", "sociosqu", "sodales",
+        "sollicitudin", "suscipit", "suspendisse", "taciti", "tellus", "tempor",
+        "tempus", "tincidunt", "torquent", "tortor", "tristique", "turpis",
+        "ullamcorper", "ultrices", "ultricies", "urna", "ut", "varius",
+        "vehicula", "vel", "velit", "venenatis", "vestibulum", "vitae",
+        "vivamus", "viverra", "volutpat", "vulputate",
     ];
 
     let n_kwargs: Option<usize> = kwargs.get("n")?;
@@ -221,6 +229,12 @@ pub fn lipsum(
     let html: Option<bool> = kwargs.get("html")?;
     let html = html.unwrap_or(false);
     let n = n.or(n_kwargs).unwrap_or(5);
+    if min > max {
+        return Err(Error::new(
+            ErrorKind::InvalidOperation,
+            format!("lipsum min ({}) must not be larger than max ({})", min, max),
+        ));
+    }
     let mut rv = String::new();
 
     let mut rng = get_rng(state);
@@ -230,7 +244,7 @@ pub fn lipsum(
         let mut last_fullstop = 0;
         let mut last = "";
 
-        for idx in 0..rng.gen_range(min..max) {
+        for idx in 0..rng.gen_range(min..=max) {
             if idx > 0 {
                 rv.push(' ');
             } else if html {
@@ -35,6 +35,8 @@ pub fn add_to_environment(env: &mut Environment) {
     env.add_filter("pluralize", filters::pluralize);
     env.add_filter("filesizeformat", filters::filesizeformat);
     env.add_filter("truncate", filters::truncate);
+    env.add_filter("slugify", filters::slugify);
+    env.add_filter("nl2br", filters::nl2br);
     #[cfg(feature = "wordcount")]
     {
         env.add_filter("wordcount", filters::wordcount);
@@ -43,6 +45,10 @@ pub fn add_to_environment(env: &mut Environment) {
     {
         env.add_filter("wordwrap", filters::wordwrap);
     }
+    #[cfg(feature = "markdown")]
+    {
+        env.add_filter("markdown", filters::markdown);
+    }
     #[cfg(feature = "datetime")]
     {
         env.add_filter("datetimeformat", filters::datetimeformat);
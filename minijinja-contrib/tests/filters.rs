@@ -363,3 +363,207 @@ fn test_wordwrap() {
         "This-is-a-\nhyphenated\n-word"
     );
 }
+
+#[test]
+#[cfg(feature = "markdown")]
+fn test_markdown() {
+    use minijinja::AutoEscape;
+    use minijinja_contrib::filters::markdown;
+
+    let mut env = minijinja::Environment::new();
+    env.set_auto_escape_callback(|_| AutoEscape::Html);
+    env.add_filter("markdown", markdown);
+
+    // output is safe and not escaped again
+    assert_eq!(
+        env.render_str("{{ text|markdown }}", context! { text => "Hello *World*" })
+            .unwrap(),
+        "<p>Hello <em>World</em></p>\n"
+    );
+
+    // inline rendering
+    assert_eq!(
+        env.render_str(
+            "{{ text|markdown(inline=true) }}",
+            context! { text => "Hello **World**" }
+        )
+        .unwrap(),
+        "Hello <strong>World</strong>"
+    );
+
+    // extensions
+    assert_eq!(
+        env.render_str(
+            "{{ text|markdown(inline=true, extensions=['strikethrough']) }}",
+            context! { text => "~~gone~~" }
+        )
+        .unwrap(),
+        "<del>gone</del>"
+    );
+    assert_eq!(
+        env.render_str(
+            "{{ text|markdown(extensions=['tables']) }}",
+            context! { text => "| a |\n|---|\n| b |" }
+        )
+        .unwrap(),
+        "<table><thead><tr><th>a</th></tr></thead><tbody>\n<tr><td>b</td></tr>\n</tbody></table>\n"
+    );
+    assert!(env
+        .render_str("{{ 'x'|markdown(extensions=['nope']) }}", context! {})
+        .is_err());
+
+    // non-string values are converted to strings
+    assert_eq!(
+        env.render_str("{{ 42|markdown(inline=true) }}", context! {})
+            .unwrap(),
+        "42"
+    );
+
+    // sanitizing
+    assert_eq!(
+        env.render_str(
+            "{{ text|markdown(inline=true) }}",
+            context! { text => "a <b>b</b>" }
+        )
+        .unwrap(),
+        "a <b>b</b>"
+    );
+    assert_eq!(
+        env.render_str(
+            "{{ text|markdown(inline=true, sanitize=true) }}",
+            context! { text => "a <b>b</b> [x](javascript:alert(1))" }
+        )
+        .unwrap(),
+        "a &lt;b&gt;b&lt;/b&gt; <a href=\"\">x</a>"
+    );
+}
+
+#[test]
+fn test_slugify() {
+    use minijinja_contrib::filters::slugify;
+
+    let mut env = minijinja::Environment::new();
+    env.add_filter("slugify", slugify);
+
+    for (input, expected) in [
+        ("Hello World", "hello-world"),
+        ("  --Hello,   World!--  ", "hello-world"),
+        ("Rust 2021 Edition", "rust-2021-edition"),
+        ("!!!", ""),
+        ("", ""),
+    ] {
+        assert_eq!(
+            env.render_str("{{ text|slugify }}", context! { text => input })
+                .unwrap(),
+            expected
+        );
+    }
+
+    assert_eq!(
+        env.render_str(
+            "{{ text|slugify(separator='_') }}",
+            context! { text => "Hello  World" }
+        )
+        .unwrap(),
+        "hello_world"
+    );
+    assert_eq!(
+        env.render_str(
+            "{{ text|slugify(max_length=6) }}",
+            context! { text => "Hello World" }
+        )
+        .unwrap(),
+        "hello"
+    );
+    assert_eq!(
+        env.render_str(
+            "{{ text|slugify(separator='--', max_length=6) }}",
+            context! { text => "Hello World" }
+        )
+        .unwrap(),
+        "hello"
+    );
+    assert_eq!(
+        env.render_str(
+            "{{ text|slugify(separator='--', max_length=7) }}",
+            context! { text => "Hello World" }
+        )
+        .unwrap(),
+        "hello"
+    );
+    assert_eq!(
+        env.render_str("{{ 42|slugify }}", context! {}).unwrap(),
+        "42"
+    );
+    assert_eq!(
+        env.render_str(
+            "{{ text|slugify(max_length=8) }}",
+            context! { text => "Hello World" }
+        )
+        .unwrap(),
+        "hello-wo"
+    );
+}
+
+#[test]
+#[cfg(not(feature = "unicode_slugify"))]
+fn test_slugify_strips_non_ascii() {
+    use minijinja_contrib::filters::slugify;
+
+    let mut env = minijinja::Environment::new();
+    env.add_filter("slugify", slugify);
+    assert_eq!(
+        env.render_str("{{ text|slugify }}", context! { text => "Café über" })
+            .unwrap(),
+        "caf-ber"
+    );
+}
+
+#[test]
+#[cfg(feature = "unicode_slugify")]
+fn test_slugify_transliterates() {
+    use minijinja_contrib::filters::slugify;
+
+    let mut env = minijinja::Environment::new();
+    env.add_filter("slugify", slugify);
+    assert_eq!(
+        env.render_str("{{ text|slugify }}", context! { text => "Café über" })
+            .unwrap(),
+        "cafe-uber"
+    );
+}
+
+#[test]
+fn test_nl2br() {
+    use minijinja::AutoEscape;
+    use minijinja_contrib::filters::nl2br;
+
+    let mut env = minijinja::Environment::new();
+    env.set_auto_escape_callback(|_| AutoEscape::Html);
+    env.add_filter("nl2br", nl2br);
+
+    assert_eq!(
+        env.render_str("{{ text|nl2br }}", context! { text => "a <b>\nc" })
+            .unwrap(),
+        "a &lt;b&gt;<br>\nc"
+    );
+    assert_eq!(
+        env.render_str("{{ text|nl2br }}", context! { text => "a\r\nb\rc\n" })
+            .unwrap(),
+        "a<br>\nb<br>\nc<br>\n"
+    );
+    assert_eq!(
+        env.render_str("{{ text|nl2br(xhtml=true) }}", context! { text => "a\nb" })
+            .unwrap(),
+        "a<br />\nb"
+    );
+    assert_eq!(
+        env.render_str(
+            "{{ text|safe|nl2br }}",
+            context! { text => "<em>a</em>\nb" }
+        )
+        .unwrap(),
+        "<em>a</em><br>\nb"
+    );
+    assert_eq!(env.render_str("{{ 42|nl2br }}", context! {}).unwrap(), "42");
+}
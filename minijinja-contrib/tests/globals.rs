@@ -54,23 +54,21 @@ fn test_lispum() {
     env.add_function("lipsum", lipsum);
 
     assert_snapshot!(render!(in env, r"{% set RAND_SEED = 42 %}{{ lipsum(5) }}"), @r###"
-    Facilisi accumsan class rutrum integer euismod gravida cras vsociis arcu lobortis sociosqu elementum lacus nulla. Leo imperdiet penatibus id quam malesuada pretium sociosqu scelerisque diam sociosqu penatibus imperdiet et nisl. Ante s vulputate nulla porta ssociis per gravida primis porta penatibus nostra congue dui.
+    Montes fermentum accumsan elit sagittis tristique torquent gravida cras varius arcu lobortis torquent suscipit pharetra. Lacus nullam mus lectus imperdiet per molestie nunc ipsum primis tincidunt sed diam velit per. Venenatis mi etiam ante volutpat sagittis nibh dapibus porttitor suspendisse pharetra. Gravida in proin porttitor penatibus justo congue dui semper cras integer nostra venenatis etiam. Eu sagittis ac hymenaeos proin nisl massa hendrerit sit semper neque risus aliquet duis. Non facilisis quis luctus ad purus magna sagittis mauris lectus.
 
-    Ipsum cras integer magna ssociis etiam eu rutrum ac praesent ssociis primis nisl malesuada sociosqu. Senectus sem neque ridiculus aliquet duis nisl facilisis quam diam nibh ad eget. Rutrum mauris aliquam faucibus magna eu phasellus ssociis libero neque convallis magna. Ante aliquet proin montes nibh sociosqu vulputate auctor.
+    Magnis venenatis ligula netus convallis magnis ante aliquet pulvinar integer fames ultricies vulputate auctor. Natoque per aliquam dictumst magna nascetur condimentum risus habitasse nibh hac mollis quam molestie fames tincidunt. Et venenatis laoreet turpis pharetra sed nonummy taciti natoque id hymenaeos feugiat. Sagittis luctus porttitor magnis etiam placerat pulvinar vivamus pulvinar lacinia ridiculus parturient lacinia. Vivamus sociosqu pede dis tellus nonummy felis class magna fermentum magna dictum diam mattis dapibus commodo facilisis. Hendrerit morbi curabitur diam viverra risus nascetur.
 
-    Lacinia aliquam dictumst pellentesque nibh sociosqu sagittis leo ad dictum elementum sapien mi sociosqu. Et ssociis laoreet dolor egestas scelerisque potenti duis natoque ssociis feugiat. Proin luctus porta rhoncus quis phasellus netus non proin sociosqu nonummy ornare lacinia. Leo sociis inceptos cum leo non elit class sed sapien dictum diam mattis dapibus netus facilisis. Hendrerit montes aliquam ssociis ridiculus a cras sociosqu nisi ssociis curabitur.
+    Congue lectus suscipit nisi viverra curabitur lacinia nonummy ullamcorper varius rhoncus quisque praesent at senectus porta. Eros sociosqu scelerisque ipsum.
 
-    Justo nonummy pulvinar potenti in potenti at facilisi platea sagittis scelerisque quis sapien semper dictum in ipsum. Nunc nonummy ornare etiam elementum nullam curae eu nullam ad nascetur ssociis nullam mus. Nisi ssociis gravida dapibus non sociosqu laoreet adipiscing potenti ipsum parturient potenti mollis odio. Leo eget felis pretium libero consectetuer hymenaeos sociosqu ssociis in posuere.
-
-    S commodo fames ridiculus luctus proin non aptent nullam mi eleifend consectetuer aliquam ad. Scelerisque nisl blandit sociis euismod curae semper nunc nec litora condimentum fames habitasse. Inceptos augue sociosqu hendrerit justo montes orci proin mus molestie id iaculis nostra lacus. Cum facilisis potenti facilisis nonummy sem.
+    Laoreet in ipsum nisl elit ullamcorper etiam commodo accumsan orci mauris conubia suspendisse nunc magna. Nisl tempus in mi laoreet orci molestie rhoncus platea suscipit fusce mollis orci dui. Sem lorem libero consectetuer iaculis urna sollicitudin proin potenti gravida commodo vivamus luctus. Pulvinar risus aptent nunc dolor tempus sed consectetuer faucibus sem eros non blandit sociosqu commodo curae. Gravida suscipit neque litora nascetur commodo fames habitasse inceptos augue urna hymenaeos justo. Montes ornare hendrerit aliquet nam potenti primis iaculis purus curae a fermentum nascetur justo potenti.
 
+    Dui eu semper imperdiet erat rhoncus sapien quis metus curabitur iaculis fames consectetuer fringilla. Habitasse auctor integer tellus risus eget dis hendrerit mattis nostra scelerisque litora ligula elit. Class taciti vitae ultricies purus eros vitae dolor eu facilisis nullam metus gravida. Sociis ipsum convallis.
     "###);
 
     assert_snapshot!(render!(in env, r"{% set RAND_SEED = 42 %}{{ lipsum(2, html=true) }}"), @r###"
-    <p>Facilisi accumsan class rutrum integer euismod gravida cras vsociis arcu lobortis sociosqu elementum lacus nulla. Leo imperdiet penatibus id quam malesuada pretium sociosqu scelerisque diam sociosqu penatibus imperdiet et nisl. Ante s vulputate nulla porta ssociis per gravida primis porta penatibus nostra congue dui.</p>
-
-    <p>Ipsum cras integer magna ssociis etiam eu rutrum ac praesent ssociis primis nisl malesuada sociosqu. Senectus sem neque ridiculus aliquet duis nisl facilisis quam diam nibh ad eget. Rutrum mauris aliquam faucibus magna eu phasellus ssociis libero neque convallis magna. Ante aliquet proin montes nibh sociosqu vulputate auctor.</p>
+    <p>Montes fermentum accumsan elit sagittis tristique torquent gravida cras varius arcu lobortis torquent suscipit pharetra. Lacus nullam mus lectus imperdiet per molestie nunc ipsum primis tincidunt sed diam velit per. Venenatis mi etiam ante volutpat sagittis nibh dapibus porttitor suspendisse pharetra. Gravida in proin porttitor penatibus justo congue dui semper cras integer nostra venenatis etiam. Eu sagittis ac hymenaeos proin nisl massa hendrerit sit semper neque risus aliquet duis. Non facilisis quis luctus ad purus magna sagittis mauris lectus.</p>
 
+    <p>Magnis venenatis ligula netus convallis magnis ante aliquet pulvinar integer fames ultricies vulputate auctor. Natoque per aliquam dictumst magna nascetur condimentum risus habitasse nibh hac mollis quam molestie fames tincidunt. Et venenatis laoreet turpis pharetra sed nonummy taciti natoque id hymenaeos feugiat. Sagittis luctus porttitor magnis etiam placerat pulvinar vivamus pulvinar lacinia ridiculus parturient lacinia. Vivamus sociosqu pede dis tellus nonummy felis class magna fermentum magna dictum diam mattis dapibus commodo facilisis. Hendrerit morbi curabitur diam viverra risus nascetur.</p>
     "###);
 }
 
@@ -85,3 +83,36 @@ fn test_randrange() {
     assert_snapshot!(render!(in env, r"{% set RAND_SEED = 42 %}{{ randrange(10) }}"), @"1");
     assert_snapshot!(render!(in env, r"{% set RAND_SEED = 42 %}{{ randrange(-50, 50) }}"), @"-20");
 }
+
+#[test]
+#[cfg(feature = "rand")]
+fn test_lipsum_params() {
+    use minijinja_contrib::globals::lipsum;
+
+    let mut env = Environment::new();
+    env.add_function("lipsum", lipsum);
+
+    let rv = render!(in env, r"{% set RAND_SEED = 1 %}{{ lipsum(n=3, min=4, max=4) }}");
+    let paragraphs = rv.trim_end().split("\n\n").collect::<Vec<_>>();
+    assert_eq!(paragraphs.len(), 3);
+    for p in paragraphs {
+        assert_eq!(p.split(' ').count(), 4);
+    }
+
+    // seeded output is deterministic
+    assert_eq!(
+        render!(in env, r"{% set RAND_SEED = 7 %}{{ lipsum(2) }}"),
+        render!(in env, r"{% set RAND_SEED = 7 %}{{ lipsum(2) }}"),
+    );
+
+    // html output is safe and not escaped again
+    env.set_auto_escape_callback(|_| minijinja::AutoEscape::Html);
+    let rv = render!(in env, r"{% set RAND_SEED = 1 %}{{ lipsum(1, html=true) }}");
+    assert!(rv.starts_with("<p>"));
+    assert!(rv.trim_end().ends_with("</p>"));
+
+    let err = env
+        .render_str("{{ lipsum(min=10, max=5) }}", ())
+        .unwrap_err();
+    assert_eq!(err.kind(), minijinja::ErrorKind::InvalidOperation);
+}
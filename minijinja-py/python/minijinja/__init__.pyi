@@ -8,7 +8,7 @@ from typing import (
     overload,
 )
 from typing_extensions import Final, TypeAlias, Self
-from minijinja._lowlevel import State
+from minijinja._lowlevel import Expression, State
 from collections.abc import Mapping
 
 __all__ = [
@@ -30,7 +30,7 @@ class _PassesState(Protocol[_A_contra, _R_co]):
     __minijinja_pass_state__: Literal[True]
 
 _StrPath: TypeAlias = PurePath | str
-_Behavior = Literal["strict", "lenient", "chainable"]
+_Behavior = Literal["strict", "lenient", "chainable", "strict_output"]
 
 DEFAULT_ENVIRONMENT: Final[Environment]
 
@@ -122,6 +122,7 @@ class Environment:
         self, source: str, name: str | None = None, /, **context: Any
     ) -> str: ...
     def eval_expr(self, expression: str, /, **context: Any) -> Any: ...
+    def compile_expression(self, expression: str) -> Expression: ...
 
 class TemplateError(RuntimeError):
     def __init__(self, message: str) -> None: ...
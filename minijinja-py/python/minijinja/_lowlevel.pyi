@@ -10,3 +10,10 @@ class State:
     auto_escape: bool
 
     def lookup(self, name: str) -> Any: ...
+
+@final
+class Expression:
+    source: str
+    env: Environment
+
+    def eval(self, **context: Any) -> Any: ...
@@ -10,6 +10,7 @@ use pyo3::exceptions::PyRuntimeError;
 use pyo3::prelude::*;
 use pyo3::pybacked::PyBackedStr;
 use pyo3::types::{PyDict, PyTuple};
+use self_cell::self_cell;
 
 use crate::error_support::{report_unraisable, to_minijinja_error, to_py_error};
 use crate::state::bind_state;
@@ -147,6 +148,7 @@ impl Environment {
             "strict" => UndefinedBehavior::Strict,
             "lenient" => UndefinedBehavior::Lenient,
             "chainable" => UndefinedBehavior::Chainable,
+            "strict_output" => UndefinedBehavior::StrictOutput,
             _ => {
                 return Err(PyRuntimeError::new_err(
                     "invalid value for undefined behavior",
@@ -164,6 +166,7 @@ impl Environment {
             UndefinedBehavior::Lenient => "lenient",
             UndefinedBehavior::Chainable => "chainable",
             UndefinedBehavior::Strict => "strict",
+            UndefinedBehavior::StrictOutput => "strict_output",
             _ => {
                 return Err(PyRuntimeError::new_err(
                     "invalid value for undefined behavior",
@@ -721,6 +724,79 @@ impl Environment {
             to_python_value(expr.eval(ctx).map_err(to_py_error)?)
         })
     }
+
+    /// Compiles an expression for repeated evaluation.
+    ///
+    /// Syntax errors are reported here rather than when the expression is
+    /// evaluated.  The expression is compiled against a snapshot of the
+    /// environment so later changes to filters, tests or globals are not
+    /// visible to it.
+    #[pyo3(text_signature = "(self, expression)")]
+    pub fn compile_expression(slf: PyRef<'_, Self>, expression: String) -> PyResult<Expression> {
+        let env = slf.inner.lock().unwrap().env.clone();
+        let compiled = CompiledExpression::try_new(env, |env| {
+            env.compile_expression_owned(expression.clone())
+        })
+        .map_err(to_py_error)?;
+        Ok(Expression {
+            env: slf.into(),
+            source: expression,
+            compiled,
+        })
+    }
+}
+
+type BoundExpression<'env> = minijinja::Expression<'env, 'static>;
+
+self_cell! {
+    struct CompiledExpression {
+        owner: minijinja::Environment<'static>,
+        #[covariant]
+        dependent: BoundExpression,
+    }
+}
+
+/// An expression compiled for an environment.
+#[pyclass(module = "minijinja._lowlevel")]
+pub struct Expression {
+    env: Py<Environment>,
+    source: String,
+    compiled: CompiledExpression,
+}
+
+#[pymethods]
+impl Expression {
+    /// Returns the environment the expression was compiled for.
+    #[getter]
+    pub fn get_env(&self, py: Python<'_>) -> Py<Environment> {
+        self.env.clone_ref(py)
+    }
+
+    /// Returns the source of the expression.
+    #[getter]
+    pub fn get_source(&self) -> &str {
+        &self.source
+    }
+
+    /// Evaluates the expression with a given context.
+    #[pyo3(signature = (**ctx))]
+    pub fn eval(&self, ctx: Option<&Bound<'_, PyDict>>) -> PyResult<Py<PyAny>> {
+        bind_environment(self.env.as_ptr(), || {
+            let ctx = ctx
+                .map(|ctx| Value::from_object(DynamicObject::new(ctx.as_any().clone().unbind())))
+                .unwrap_or_else(|| context!());
+            to_python_value(
+                self.compiled
+                    .borrow_dependent()
+                    .eval(ctx)
+                    .map_err(to_py_error)?,
+            )
+        })
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<Expression {:?}>", self.source)
+    }
 }
 
 pub fn with_environment<R, F: FnOnce(Py<Environment>) -> PyResult<R>>(f: F) -> PyResult<R> {
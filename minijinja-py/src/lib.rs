@@ -8,6 +8,7 @@ mod typeconv;
 #[pymodule]
 fn _lowlevel(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<environment::Environment>()?;
+    m.add_class::<environment::Expression>()?;
     m.add_class::<state::StateRef>()?;
     m.add_class::<error_support::ErrorInfo>()?;
     Ok(())
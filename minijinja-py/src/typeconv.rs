@@ -8,9 +8,9 @@ use minijinja::{AutoEscape, Error, State};
 use once_cell::sync::OnceCell;
 use pyo3::prelude::*;
 use pyo3::pybacked::PyBackedStr;
-use pyo3::types::{PyDict, PyList, PySequence, PyTuple};
+use pyo3::types::{PyDict, PyIterator, PyList, PySequence, PyTuple};
 
-use crate::error_support::{to_minijinja_error, to_py_error};
+use crate::error_support::{report_unraisable, to_minijinja_error, to_py_error};
 use crate::state::{bind_state, StateRef};
 
 static AUTO_ESCAPE_CACHE: Mutex<BTreeMap<String, AutoEscape>> = Mutex::new(BTreeMap::new());
@@ -128,6 +128,12 @@ impl Object for DynamicObject {
             let inner = self.inner.bind(py);
             if inner.downcast::<PySequence>().is_ok() {
                 Enumerator::Seq(inner.len().unwrap_or(0))
+            } else if let Ok(iter) = inner.downcast::<PyIterator>() {
+                // only real iterators (and generators) are consumed lazily,
+                // containers such as dicts and sets keep their length.
+                Enumerator::Iter(Box::new(LazyIter {
+                    iter: Some(iter.clone().unbind()),
+                }))
             } else if let Ok(iter) = inner.iter() {
                 Enumerator::Values(
                     iter.filter_map(|x| match x {
@@ -143,6 +149,34 @@ impl Object for DynamicObject {
     }
 }
 
+/// Lazily pulls values from a Python iterator.
+///
+/// The GIL is acquired for every item, so nothing is materialized upfront.
+/// If the Python iterator raises, the error is reported as unraisable and
+/// iteration ends.
+struct LazyIter {
+    iter: Option<Py<PyIterator>>,
+}
+
+impl Iterator for LazyIter {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Value> {
+        let iter = self.iter.as_ref()?;
+        let rv = Python::with_gil(|py| match iter.bind(py).clone().next()? {
+            Ok(value) => Some(to_minijinja_value(&value)),
+            Err(err) => {
+                report_unraisable(py, err);
+                None
+            }
+        });
+        if rv.is_none() {
+            self.iter = None;
+        }
+        rv
+    }
+}
+
 pub fn to_minijinja_value(value: &Bound<'_, PyAny>) -> Value {
     if value.is_none() {
         Value::from(())
@@ -22,6 +22,27 @@ def test_expression():
     assert rv == list(range(10))
 
 
+def test_compiled_expression():
+    env = Environment()
+    expr = env.compile_expression("x is even and y > 2")
+    assert expr.source == "x is even and y > 2"
+    assert expr.env is env
+    assert expr.eval(x=2, y=3) == True
+    assert expr.eval(x=1, y=3) == False
+
+    env.add_filter("double", lambda x: x * 2)
+    expr = env.compile_expression("x|double")
+    assert expr.eval(x=21) == 42
+
+    try:
+        env.compile_expression("1 +")
+    except TemplateError as e:
+        assert e.kind == "SyntaxError"
+        assert e.line == 1
+    else:
+        assert False, "expected error"
+
+
 def test_pass_callable():
     def magic():
         return [1, 2, 3]
@@ -56,6 +77,38 @@ def test_generator():
     assert rv == [1, 2, 3]
 
 
+def test_generator_is_lazy():
+    consumed = []
+
+    def hmm():
+        for x in range(5):
+            consumed.append(x)
+            yield x
+
+    env = Environment()
+    values = hmm()
+    rv = env.render_str("{{ values|first }}", values=values)
+    assert rv == "0"
+    assert consumed == [0]
+
+    # generators are single pass, the rest continues where we left off
+    rv = env.render_str("{{ values|list }}|{{ values|list }}", values=values)
+    assert rv == "[1, 2, 3, 4]|[]"
+
+
+def test_containers_are_not_lazy():
+    env = Environment()
+    rv = env.render_str(
+        "{{ d|length }}|{{ s|length }}|{{ not e and not es }}|"
+        "{% for x in s %}{{ loop.length }}{% endfor %}",
+        d={"a": 1, "b": 2},
+        s={1},
+        e={},
+        es=frozenset(),
+    )
+    assert rv == "2|1|true|1"
+
+
 def test_method_calling():
     class MyClass(object):
         def my_method(self):
@@ -0,0 +1,833 @@
+//! Serialization of compiled templates into a binary artifact.
+//!
+//! The artifact starts with a magic header, a format version and the version
+//! of MiniJinja that created it.  Artifacts are only loaded by the exact same
+//! version of MiniJinja as the instruction set is not stable across releases.
+//!
+//! After the header follows a string blob which is the template source followed
+//! by a table of extra strings.  All strings referenced by instructions are
+//! stored as offset and length into that blob so that the loaded template can
+//! borrow from it just like a template compiled from source borrows from the
+//! source.
+use std::collections::{BTreeMap, HashMap};
+
+#[cfg(feature = "debug")]
+use crate::compiler::instructions::SpanInfo;
+use crate::compiler::instructions::{Instruction, Instructions, LineInfo, LocalId, MAX_LOCALS};
+use crate::compiler::tokens::Span;
+use crate::error::{Error, ErrorKind};
+use crate::output::CaptureMode;
+use crate::template::{CompiledTemplate, TemplateComplexity, TemplateConfig};
+use crate::value::{value_map_with_capacity, StringType, Value, ValueKind, ValueRepr};
+
+const MAGIC: &[u8; 4] = b"MJCT";
+const FORMAT_VERSION: u32 = 1;
+
+fn invalid(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidBytecode, msg.to_string())
+}
+
+/// Serializes a compiled template into a binary artifact.
+pub fn dump(compiled: &CompiledTemplate<'_>) -> Result<Vec<u8>, Error> {
+    let source = compiled.instructions.source();
+    let mut w = Writer {
+        buf: Vec::new(),
+        source,
+        strtab: String::new(),
+        strtab_offsets: HashMap::new(),
+    };
+    w.write_uint(compiled.buffer_size_hint as u64);
+    w.write_uint(compiled.body_offset as u64);
+    match compiled.front_matter {
+        Some(ref value) => {
+            w.write_u8(1);
+            ok!(w.write_value(value));
+        }
+        None => w.write_u8(0),
+    }
+    ok!(w.write_instructions(&compiled.instructions));
+    w.write_uint(compiled.blocks.len() as u64);
+    for (name, instructions) in &compiled.blocks {
+        w.write_str(name);
+        ok!(w.write_instructions(instructions));
+    }
+
+    let mut rv = Vec::with_capacity(source.len() + w.strtab.len() + w.buf.len() + 32);
+    rv.extend_from_slice(MAGIC);
+    rv.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    write_uint(&mut rv, env!("CARGO_PKG_VERSION").len() as u64);
+    rv.extend_from_slice(env!("CARGO_PKG_VERSION").as_bytes());
+    write_uint(&mut rv, source.len() as u64);
+    write_uint(&mut rv, w.strtab.len() as u64);
+    rv.extend_from_slice(source.as_bytes());
+    rv.extend_from_slice(w.strtab.as_bytes());
+    rv.extend_from_slice(&w.buf);
+    Ok(rv)
+}
+
+/// A parsed artifact header.
+pub struct Artifact<'a> {
+    blob: String,
+    source_len: usize,
+    code: &'a [u8],
+}
+
+impl<'a> Artifact<'a> {
+    /// Validates the header and extracts the string blob.
+    pub fn parse(data: &'a [u8]) -> Result<Artifact<'a>, Error> {
+        let mut r = Reader { data, pos: 0 };
+        if ok!(r.read_bytes(MAGIC.len())) != MAGIC {
+            return Err(invalid("not a compiled template"));
+        }
+        let format_version = ok!(r.read_bytes(4));
+        let version_len = ok!(r.read_usize());
+        let version = ok!(r.read_bytes(version_len));
+        if format_version != FORMAT_VERSION.to_le_bytes()
+            || version != env!("CARGO_PKG_VERSION").as_bytes()
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidBytecode,
+                format!(
+                    "compiled template was created by MiniJinja {}, expected {}",
+                    String::from_utf8_lossy(version),
+                    env!("CARGO_PKG_VERSION"),
+                ),
+            ));
+        }
+        let source_len = ok!(r.read_usize());
+        let strtab_len = ok!(r.read_usize());
+        let blob = ok!(r.read_bytes(source_len.saturating_add(strtab_len)));
+        let blob = ok!(String::from_utf8(blob.to_vec())
+            .ok()
+            .filter(|x| x.is_char_boundary(source_len))
+            .ok_or_else(|| invalid("compiled template contains invalid strings")));
+        Ok(Artifact {
+            blob,
+            source_len,
+            code: &data[r.pos..],
+        })
+    }
+
+    /// Splits the artifact into the string blob and a loader for the code.
+    pub fn into_parts(self) -> (String, ArtifactCode<'a>) {
+        (
+            self.blob,
+            ArtifactCode {
+                source_len: self.source_len,
+                code: self.code,
+            },
+        )
+    }
+}
+
+/// The code section of an artifact.
+pub struct ArtifactCode<'a> {
+    source_len: usize,
+    code: &'a [u8],
+}
+
+impl ArtifactCode<'_> {
+    /// Loads the compiled template borrowing strings from the blob.
+    pub fn load<'source>(
+        &self,
+        name: &'source str,
+        blob: &'source str,
+        config: &TemplateConfig,
+    ) -> Result<CompiledTemplate<'source>, Error> {
+        let mut r = BlobReader {
+            r: Reader {
+                data: self.code,
+                pos: 0,
+            },
+            name,
+            blob,
+            source: &blob[..self.source_len],
+        };
+        let buffer_size_hint = ok!(r.r.read_usize());
+        let body_offset = ok!(r.r.read_usize());
+        let front_matter = match ok!(r.r.read_u8()) {
+            0 => None,
+            _ => Some(ok!(r.r.read_value())),
+        };
+        let instructions = ok!(r.read_instructions());
+        let block_count = ok!(r.r.read_usize());
+        let mut blocks = BTreeMap::new();
+        for _ in 0..block_count {
+            let block_name = ok!(r.read_str());
+            blocks.insert(block_name, ok!(r.read_instructions()));
+        }
+        if r.r.pos != self.code.len() {
+            return Err(invalid("trailing data in compiled template"));
+        }
+        let complexity = TemplateComplexity::compute(&instructions, &blocks);
+        Ok(CompiledTemplate {
+            instructions,
+            blocks,
+            buffer_size_hint,
+            syntax_config: config.syntax_config.clone(),
+            initial_auto_escape: (config.default_auto_escape)(name),
+            front_matter,
+            body_offset,
+            complexity,
+        })
+    }
+}
+
+fn write_uint(buf: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf.push(byte);
+            break;
+        }
+        buf.push(byte | 0x80);
+    }
+}
+
+struct Writer<'source> {
+    buf: Vec<u8>,
+    source: &'source str,
+    strtab: String,
+    strtab_offsets: HashMap<&'source str, usize>,
+}
+
+impl<'source> Writer<'source> {
+    fn write_u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    fn write_uint(&mut self, value: u64) {
+        write_uint(&mut self.buf, value);
+    }
+
+    fn write_opt_u16(&mut self, value: Option<u16>) {
+        match value {
+            Some(value) => self.write_uint(value as u64 + 1),
+            None => self.write_uint(0),
+        }
+    }
+
+    fn write_bytes(&mut self, value: &[u8]) {
+        self.write_uint(value.len() as u64);
+        self.buf.extend_from_slice(value);
+    }
+
+    /// Writes a reference to a string in the blob.
+    ///
+    /// Strings that are slices of the source are referenced by their
+    /// position in the source, all others are added to the string table.
+    fn write_str(&mut self, value: &'source str) {
+        let source_start = self.source.as_ptr() as usize;
+        let value_start = value.as_ptr() as usize;
+        let offset = if value_start >= source_start
+            && value_start + value.len() <= source_start + self.source.len()
+        {
+            value_start - source_start
+        } else {
+            let source_len = self.source.len();
+            let strtab = &mut self.strtab;
+            *self.strtab_offsets.entry(value).or_insert_with(|| {
+                let offset = source_len + strtab.len();
+                strtab.push_str(value);
+                offset
+            })
+        };
+        self.write_uint(offset as u64);
+        self.write_uint(value.len() as u64);
+    }
+
+    fn write_value(&mut self, value: &Value) -> Result<(), Error> {
+        match value.0 {
+            ValueRepr::Undefined => self.write_u8(0),
+            ValueRepr::None => self.write_u8(1),
+            ValueRepr::Bool(val) => self.write_u8(if val { 3 } else { 2 }),
+            ValueRepr::U64(val) => {
+                self.write_u8(4);
+                self.write_uint(val);
+            }
+            ValueRepr::I64(val) => {
+                self.write_u8(5);
+                self.buf.extend_from_slice(&val.to_le_bytes());
+            }
+            ValueRepr::F64(val) => {
+                self.write_u8(6);
+                self.buf.extend_from_slice(&val.to_le_bytes());
+            }
+            ValueRepr::U128(val) => {
+                self.write_u8(7);
+                self.buf.extend_from_slice(&{ val.0 }.to_le_bytes());
+            }
+            ValueRepr::I128(val) => {
+                self.write_u8(8);
+                self.buf.extend_from_slice(&{ val.0 }.to_le_bytes());
+            }
+            ValueRepr::String(ref s, StringType::Safe) => {
+                self.write_u8(10);
+                self.write_bytes(s.as_bytes());
+            }
+            ValueRepr::String(ref s, StringType::Normal) => {
+                self.write_u8(9);
+                self.write_bytes(s.as_bytes());
+            }
+            ValueRepr::SmallStr(ref s) => {
+                self.write_u8(9);
+                self.write_bytes(s.as_str().as_bytes());
+            }
+            ValueRepr::Bytes(ref b) => {
+                self.write_u8(11);
+                self.write_bytes(b);
+            }
+            ValueRepr::Object(_) if value.kind() == ValueKind::Seq => {
+                let items = ok!(value.try_iter()).collect::<Vec<_>>();
+                self.write_u8(12);
+                self.write_uint(items.len() as u64);
+                for item in &items {
+                    ok!(self.write_value(item));
+                }
+            }
+            ValueRepr::Object(_) if value.kind() == ValueKind::Map => {
+                let keys = ok!(value.try_iter()).collect::<Vec<_>>();
+                self.write_u8(13);
+                self.write_uint(keys.len() as u64);
+                for key in &keys {
+                    ok!(self.write_value(key));
+                    ok!(self.write_value(&ok!(value.get_item(key))));
+                }
+            }
+            ValueRepr::Invalid(_) | ValueRepr::Object(_) => {
+                return Err(Error::new(
+                    ErrorKind::BadSerialization,
+                    format!(
+                        "cannot store constant of type {} in compiled template",
+                        value.kind()
+                    ),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    fn write_instructions(&mut self, instructions: &Instructions<'source>) -> Result<(), Error> {
+        self.write_uint(instructions.instructions.len() as u64);
+        for instr in &instructions.instructions {
+            ok!(self.write_instruction(instr));
+        }
+        self.write_uint(instructions.line_infos.len() as u64);
+        for info in &instructions.line_infos {
+            self.write_uint(info.first_instruction as u64);
+            self.write_uint(info.line as u64);
+        }
+        #[cfg(feature = "debug")]
+        {
+            self.write_uint(instructions.span_infos.len() as u64);
+            for info in &instructions.span_infos {
+                self.write_uint(info.first_instruction as u64);
+                match info.span {
+                    Some(span) => {
+                        self.write_u8(1);
+                        for value in [
+                            span.start_line,
+                            span.start_col,
+                            span.start_offset,
+                            span.end_line,
+                            span.end_col,
+                            span.end_offset,
+                        ] {
+                            self.write_uint(value as u64);
+                        }
+                    }
+                    None => self.write_u8(0),
+                }
+            }
+        }
+        #[cfg(not(feature = "debug"))]
+        {
+            self.write_uint(0);
+        }
+        Ok(())
+    }
+
+    fn write_instruction(&mut self, instr: &Instruction<'source>) -> Result<(), Error> {
+        match *instr {
+            Instruction::EmitRaw(s) => {
+                self.write_u8(0);
+                self.write_str(s);
+            }
+            Instruction::StoreLocal(s) => {
+                self.write_u8(1);
+                self.write_str(s);
+            }
+            Instruction::Lookup(s) => {
+                self.write_u8(2);
+                self.write_str(s);
+            }
+            Instruction::GetAttr(s) => {
+                self.write_u8(3);
+                self.write_str(s);
+            }
+            Instruction::SetAttr(s) => {
+                self.write_u8(4);
+                self.write_str(s);
+            }
+            Instruction::GetItem => self.write_u8(5),
+            Instruction::Slice => self.write_u8(6),
+            Instruction::LoadConst(ref value) => {
+                self.write_u8(7);
+                ok!(self.write_value(value));
+            }
+            Instruction::BuildMap(n) => {
+                self.write_u8(8);
+                self.write_uint(n as u64);
+            }
+            Instruction::BuildKwargs(n) => {
+                self.write_u8(9);
+                self.write_uint(n as u64);
+            }
+            Instruction::MergeKwargs(n) => {
+                self.write_u8(10);
+                self.write_uint(n as u64);
+            }
+            Instruction::BuildList(n) => {
+                self.write_u8(11);
+                self.write_uint(n.map_or(0, |n| n as u64 + 1));
+            }
+            Instruction::UnpackList(n) => {
+                self.write_u8(12);
+                self.write_uint(n as u64);
+            }
+            Instruction::UnpackLists(n) => {
+                self.write_u8(13);
+                self.write_uint(n as u64);
+            }
+            Instruction::Add => self.write_u8(14),
+            Instruction::Sub => self.write_u8(15),
+            Instruction::Mul => self.write_u8(16),
+            Instruction::Div => self.write_u8(17),
+            Instruction::IntDiv => self.write_u8(18),
+            Instruction::Rem => self.write_u8(19),
+            Instruction::Pow => self.write_u8(20),
+            Instruction::Neg => self.write_u8(21),
+            Instruction::Eq => self.write_u8(22),
+            Instruction::Ne => self.write_u8(23),
+            Instruction::Gt => self.write_u8(24),
+            Instruction::Gte => self.write_u8(25),
+            Instruction::Lt => self.write_u8(26),
+            Instruction::Lte => self.write_u8(27),
+            Instruction::Not => self.write_u8(28),
+            Instruction::StringConcat => self.write_u8(29),
+            Instruction::In => self.write_u8(30),
+            Instruction::ApplyFilter(s, args, local) => {
+                self.write_u8(31);
+                self.write_str(s);
+                self.write_opt_u16(args);
+                self.write_u8(local);
+            }
+            Instruction::PerformTest(s, args, local) => {
+                self.write_u8(32);
+                self.write_str(s);
+                self.write_opt_u16(args);
+                self.write_u8(local);
+            }
+            Instruction::Emit => self.write_u8(33),
+            Instruction::PushLoop(flags) => {
+                self.write_u8(34);
+                self.write_u8(flags);
+            }
+            Instruction::PushWith => self.write_u8(35),
+            Instruction::Iterate(target) => {
+                self.write_u8(36);
+                self.write_uint(target as u64);
+            }
+            Instruction::PushDidNotIterate => self.write_u8(37),
+            Instruction::PopFrame => self.write_u8(38),
+            Instruction::Jump(target) => {
+                self.write_u8(39);
+                self.write_uint(target as u64);
+            }
+            Instruction::JumpIfFalse(target) => {
+                self.write_u8(40);
+                self.write_uint(target as u64);
+            }
+            Instruction::JumpIfFalseOrPop(target) => {
+                self.write_u8(41);
+                self.write_uint(target as u64);
+            }
+            Instruction::JumpIfTrueOrPop(target) => {
+                self.write_u8(42);
+                self.write_uint(target as u64);
+            }
+            Instruction::PushAutoEscape => self.write_u8(43),
+            Instruction::PopAutoEscape => self.write_u8(44),
+            Instruction::BeginCapture(mode) => {
+                self.write_u8(45);
+                self.write_u8(match mode {
+                    CaptureMode::Capture => 0,
+                    CaptureMode::Discard => 1,
+                });
+            }
+            Instruction::EndCapture => self.write_u8(46),
+            Instruction::CallFunction(s, args) => {
+                self.write_u8(47);
+                self.write_str(s);
+                self.write_opt_u16(args);
+            }
+            Instruction::CallMethod(s, args) => {
+                self.write_u8(48);
+                self.write_str(s);
+                self.write_opt_u16(args);
+            }
+            Instruction::CallObject(args) => {
+                self.write_u8(49);
+                self.write_opt_u16(args);
+            }
+            Instruction::DupTop => self.write_u8(50),
+            Instruction::DiscardTop => self.write_u8(51),
+            Instruction::FastSuper => self.write_u8(52),
+            Instruction::FastRecurse => self.write_u8(53),
+            Instruction::Swap => self.write_u8(54),
+            #[cfg(feature = "multi_template")]
+            Instruction::CallBlock(s) => {
+                self.write_u8(55);
+                self.write_str(s);
+            }
+            #[cfg(feature = "multi_template")]
+            Instruction::RequiredBlock(s) => {
+                self.write_u8(56);
+                self.write_str(s);
+            }
+            #[cfg(feature = "multi_template")]
+            Instruction::LoadBlocks => self.write_u8(57),
+            #[cfg(feature = "multi_template")]
+            Instruction::Include(ignore_missing) => {
+                self.write_u8(58);
+                self.write_u8(ignore_missing as u8);
+            }
+            #[cfg(feature = "multi_template")]
+            Instruction::ExportLocals => self.write_u8(59),
+            #[cfg(feature = "macros")]
+            Instruction::BuildMacro(s, offset, flags) => {
+                self.write_u8(60);
+                self.write_str(s);
+                self.write_uint(offset as u64);
+                self.write_u8(flags);
+            }
+            #[cfg(feature = "macros")]
+            Instruction::Return => self.write_u8(61),
+            #[cfg(feature = "macros")]
+            Instruction::IsUndefined => self.write_u8(62),
+            #[cfg(feature = "macros")]
+            Instruction::Enclose(s) => {
+                self.write_u8(63);
+                self.write_str(s);
+            }
+            #[cfg(feature = "macros")]
+            Instruction::GetClosure => self.write_u8(64),
+        }
+        Ok(())
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let rv = ok!(self
+            .pos
+            .checked_add(len)
+            .and_then(|end| self.data.get(self.pos..end))
+            .ok_or_else(|| invalid("compiled template is truncated")));
+        self.pos += len;
+        Ok(rv)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        let mut rv = [0u8; N];
+        rv.copy_from_slice(ok!(self.read_bytes(N)));
+        Ok(rv)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, Error> {
+        self.read_bytes(1).map(|x| x[0])
+    }
+
+    fn read_uint(&mut self) -> Result<u64, Error> {
+        let mut rv = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = ok!(self.read_u8());
+            if shift >= 64 {
+                return Err(invalid("integer overflow in compiled template"));
+            }
+            rv |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(rv);
+            }
+            shift += 7;
+        }
+    }
+
+    fn read_usize(&mut self) -> Result<usize, Error> {
+        self.read_uint()
+            .and_then(|x| usize::try_from(x).map_err(|_| invalid("integer overflow")))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, Error> {
+        self.read_uint()
+            .and_then(|x| u32::try_from(x).map_err(|_| invalid("integer overflow")))
+    }
+
+    fn read_opt_u16(&mut self) -> Result<Option<u16>, Error> {
+        match ok!(self.read_uint()) {
+            0 => Ok(None),
+            x => u16::try_from(x - 1)
+                .map(Some)
+                .map_err(|_| invalid("integer overflow")),
+        }
+    }
+
+    fn read_string(&mut self) -> Result<String, Error> {
+        let len = ok!(self.read_usize());
+        String::from_utf8(ok!(self.read_bytes(len)).to_vec())
+            .map_err(|_| invalid("compiled template contains invalid strings"))
+    }
+
+    fn read_value(&mut self) -> Result<Value, Error> {
+        Ok(match ok!(self.read_u8()) {
+            0 => Value::UNDEFINED,
+            1 => Value::from(()),
+            2 => Value::from(false),
+            3 => Value::from(true),
+            4 => Value::from(ok!(self.read_uint())),
+            5 => Value::from(i64::from_le_bytes(ok!(self.read_array()))),
+            6 => Value::from(f64::from_le_bytes(ok!(self.read_array()))),
+            7 => Value::from(u128::from_le_bytes(ok!(self.read_array()))),
+            8 => Value::from(i128::from_le_bytes(ok!(self.read_array()))),
+            9 => Value::from(ok!(self.read_string())),
+            10 => Value::from_safe_string(ok!(self.read_string())),
+            11 => {
+                let len = ok!(self.read_usize());
+                Value::from_bytes(ok!(self.read_bytes(len)).to_vec())
+            }
+            12 => {
+                let len = ok!(self.read_usize());
+                let mut items = Vec::new();
+                for _ in 0..len {
+                    items.push(ok!(self.read_value()));
+                }
+                Value::from(items)
+            }
+            13 => {
+                let len = ok!(self.read_usize());
+                let mut map = value_map_with_capacity(len);
+                for _ in 0..len {
+                    let key = ok!(self.read_value());
+                    map.insert(key, ok!(self.read_value()));
+                }
+                Value::from_object(map)
+            }
+            _ => return Err(invalid("invalid constant in compiled template")),
+        })
+    }
+}
+
+struct BlobReader<'a, 'source> {
+    r: Reader<'a>,
+    name: &'source str,
+    blob: &'source str,
+    source: &'source str,
+}
+
+impl<'source> BlobReader<'_, 'source> {
+    fn read_str(&mut self) -> Result<&'source str, Error> {
+        let offset = ok!(self.r.read_usize());
+        let len = ok!(self.r.read_usize());
+        offset
+            .checked_add(len)
+            .and_then(|end| self.blob.get(offset..end))
+            .ok_or_else(|| invalid("invalid string reference in compiled template"))
+    }
+
+    fn read_local_id(&mut self) -> Result<LocalId, Error> {
+        match ok!(self.r.read_u8()) {
+            x if (x as usize) < MAX_LOCALS || x == !0 => Ok(x),
+            _ => Err(invalid("invalid local in compiled template")),
+        }
+    }
+
+    fn read_instructions(&mut self) -> Result<Instructions<'source>, Error> {
+        let count = ok!(self.r.read_usize());
+        let mut instructions = Vec::new();
+        for _ in 0..count {
+            instructions.push(ok!(self.read_instruction()));
+        }
+        // jumps and macro bodies must stay within the instructions so that a
+        // malformed artifact cannot send the VM to arbitrary positions.
+        for instr in &instructions {
+            let target = match *instr {
+                Instruction::Iterate(target)
+                | Instruction::Jump(target)
+                | Instruction::JumpIfFalse(target)
+                | Instruction::JumpIfFalseOrPop(target)
+                | Instruction::JumpIfTrueOrPop(target) => target,
+                #[cfg(feature = "macros")]
+                Instruction::BuildMacro(_, target, _) => target,
+                _ => continue,
+            };
+            if target > instructions.len() {
+                return Err(invalid("jump target out of bounds in compiled template"));
+            }
+        }
+        let count = ok!(self.r.read_usize());
+        let mut line_infos = Vec::new();
+        for _ in 0..count {
+            line_infos.push(LineInfo {
+                first_instruction: ok!(self.r.read_u32()),
+                line: ok!(self.r.read_u32()),
+            });
+        }
+        let count = ok!(self.r.read_usize());
+        #[cfg(feature = "debug")]
+        let mut span_infos = Vec::new();
+        for _ in 0..count {
+            let first_instruction = ok!(self.r.read_u32());
+            let span = match ok!(self.r.read_u8()) {
+                0 => None,
+                _ => Some(Span {
+                    start_line: ok!(self.r.read_u32()),
+                    start_col: ok!(self.r.read_u32()),
+                    start_offset: ok!(self.r.read_u32()),
+                    end_line: ok!(self.r.read_u32()),
+                    end_col: ok!(self.r.read_u32()),
+                    end_offset: ok!(self.r.read_u32()),
+                }),
+            };
+            // span information is dropped if the debug feature is disabled
+            #[cfg(feature = "debug")]
+            {
+                span_infos.push(SpanInfo {
+                    first_instruction,
+                    span,
+                });
+            }
+            #[cfg(not(feature = "debug"))]
+            {
+                let _ = (first_instruction, span);
+            }
+        }
+        Ok(Instructions {
+            instructions,
+            line_infos,
+            #[cfg(feature = "debug")]
+            span_infos,
+            name: self.name,
+            source: self.source,
+        })
+    }
+
+    fn read_instruction(&mut self) -> Result<Instruction<'source>, Error> {
+        Ok(match ok!(self.r.read_u8()) {
+            0 => Instruction::EmitRaw(ok!(self.read_str())),
+            1 => Instruction::StoreLocal(ok!(self.read_str())),
+            2 => Instruction::Lookup(ok!(self.read_str())),
+            3 => Instruction::GetAttr(ok!(self.read_str())),
+            4 => Instruction::SetAttr(ok!(self.read_str())),
+            5 => Instruction::GetItem,
+            6 => Instruction::Slice,
+            7 => Instruction::LoadConst(ok!(self.r.read_value())),
+            8 => Instruction::BuildMap(ok!(self.r.read_usize())),
+            9 => Instruction::BuildKwargs(ok!(self.r.read_usize())),
+            10 => Instruction::MergeKwargs(ok!(self.r.read_usize())),
+            11 => Instruction::BuildList(match ok!(self.r.read_usize()) {
+                0 => None,
+                n => Some(n - 1),
+            }),
+            12 => Instruction::UnpackList(ok!(self.r.read_usize())),
+            13 => Instruction::UnpackLists(ok!(self.r.read_usize())),
+            14 => Instruction::Add,
+            15 => Instruction::Sub,
+            16 => Instruction::Mul,
+            17 => Instruction::Div,
+            18 => Instruction::IntDiv,
+            19 => Instruction::Rem,
+            20 => Instruction::Pow,
+            21 => Instruction::Neg,
+            22 => Instruction::Eq,
+            23 => Instruction::Ne,
+            24 => Instruction::Gt,
+            25 => Instruction::Gte,
+            26 => Instruction::Lt,
+            27 => Instruction::Lte,
+            28 => Instruction::Not,
+            29 => Instruction::StringConcat,
+            30 => Instruction::In,
+            31 => Instruction::ApplyFilter(
+                ok!(self.read_str()),
+                ok!(self.r.read_opt_u16()),
+                ok!(self.read_local_id()),
+            ),
+            32 => Instruction::PerformTest(
+                ok!(self.read_str()),
+                ok!(self.r.read_opt_u16()),
+                ok!(self.read_local_id()),
+            ),
+            33 => Instruction::Emit,
+            34 => Instruction::PushLoop(ok!(self.r.read_u8())),
+            35 => Instruction::PushWith,
+            36 => Instruction::Iterate(ok!(self.r.read_usize())),
+            37 => Instruction::PushDidNotIterate,
+            38 => Instruction::PopFrame,
+            39 => Instruction::Jump(ok!(self.r.read_usize())),
+            40 => Instruction::JumpIfFalse(ok!(self.r.read_usize())),
+            41 => Instruction::JumpIfFalseOrPop(ok!(self.r.read_usize())),
+            42 => Instruction::JumpIfTrueOrPop(ok!(self.r.read_usize())),
+            43 => Instruction::PushAutoEscape,
+            44 => Instruction::PopAutoEscape,
+            45 => Instruction::BeginCapture(match ok!(self.r.read_u8()) {
+                0 => CaptureMode::Capture,
+                _ => CaptureMode::Discard,
+            }),
+            46 => Instruction::EndCapture,
+            47 => Instruction::CallFunction(ok!(self.read_str()), ok!(self.r.read_opt_u16())),
+            48 => Instruction::CallMethod(ok!(self.read_str()), ok!(self.r.read_opt_u16())),
+            49 => Instruction::CallObject(ok!(self.r.read_opt_u16())),
+            50 => Instruction::DupTop,
+            51 => Instruction::DiscardTop,
+            52 => Instruction::FastSuper,
+            53 => Instruction::FastRecurse,
+            54 => Instruction::Swap,
+            #[cfg(feature = "multi_template")]
+            55 => Instruction::CallBlock(ok!(self.read_str())),
+            #[cfg(feature = "multi_template")]
+            56 => Instruction::RequiredBlock(ok!(self.read_str())),
+            #[cfg(feature = "multi_template")]
+            57 => Instruction::LoadBlocks,
+            #[cfg(feature = "multi_template")]
+            58 => Instruction::Include(ok!(self.r.read_u8()) != 0),
+            #[cfg(feature = "multi_template")]
+            59 => Instruction::ExportLocals,
+            #[cfg(feature = "macros")]
+            60 => Instruction::BuildMacro(
+                ok!(self.read_str()),
+                ok!(self.r.read_usize()),
+                ok!(self.r.read_u8()),
+            ),
+            #[cfg(feature = "macros")]
+            61 => Instruction::Return,
+            #[cfg(feature = "macros")]
+            62 => Instruction::IsUndefined,
+            #[cfg(feature = "macros")]
+            63 => Instruction::Enclose(ok!(self.read_str())),
+            #[cfg(feature = "macros")]
+            64 => Instruction::GetClosure,
+            _ => {
+                return Err(invalid(
+                    "invalid or unsupported instruction in compiled template",
+                ))
+            }
+        })
+    }
+}
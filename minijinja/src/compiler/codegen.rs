@@ -2,12 +2,13 @@ use std::collections::BTreeMap;
 
 use crate::compiler::ast;
 use crate::compiler::instructions::{
-    Instruction, Instructions, LocalId, LOOP_FLAG_RECURSIVE, LOOP_FLAG_WITH_LOOP_VAR, MAX_LOCALS,
+    Instruction, Instructions, LocalId, LOOP_FLAG_FILTERED, LOOP_FLAG_RECURSIVE,
+    LOOP_FLAG_WITH_LOOP_VAR, MAX_LOCALS,
 };
 use crate::compiler::tokens::Span;
 use crate::output::CaptureMode;
 use crate::value::ops::neg;
-use crate::value::{Kwargs, Value, ValueMap};
+use crate::value::{Kwargs, Value};
 
 #[cfg(test)]
 use similar_asserts::assert_eq;
@@ -141,6 +142,10 @@ impl<'source> CodeGenerator<'source> {
         if recursive {
             flags |= LOOP_FLAG_RECURSIVE;
         }
+        self.start_for_loop_with_flags(flags);
+    }
+
+    fn start_for_loop_with_flags(&mut self, flags: u8) {
         self.add(Instruction::PushLoop(flags));
         let instr = self.add(Instruction::Iterate(!0));
         self.pending_block.push(PendingBlock::Loop {
@@ -404,8 +409,12 @@ impl<'source> CodeGenerator<'source> {
     fn compile_block(&mut self, block: &ast::Spanned<ast::Block<'source>>) {
         self.set_line_from_span(block.span());
         let mut sub = self.new_subgenerator();
-        for node in &block.body {
-            sub.compile_stmt(node);
+        if block.required {
+            sub.add(Instruction::RequiredBlock(block.name));
+        } else {
+            for node in &block.body {
+                sub.compile_stmt(node);
+            }
         }
         let instructions = self.finish_subgenerator(sub);
         self.blocks.insert(block.name, instructions);
@@ -526,10 +535,14 @@ impl<'source> CodeGenerator<'source> {
         // filter expressions work like a nested for loop without
         // the special loop variable. in one loop, the condition is checked and
         // passing items accumulated into a list. in the second, that list is
-        // iterated over normally
+        // iterated over normally.  For recursive loops the recursion has to
+        // pass through the filter as well, so a trampoline that jumps back to
+        // the filter is placed right before the loop is pushed.
+        self.compile_expr(&for_loop.iter);
         if let Some(ref filter_expr) = for_loop.filter_expr {
+            let filter_start = self.next_instruction();
             self.add(Instruction::LoadConst(Value::from(0usize)));
-            self.compile_expr(&for_loop.iter);
+            self.add(Instruction::Swap);
             self.start_for_loop(false, false);
             self.add(Instruction::DupTop);
             self.compile_assignment(&for_loop.target);
@@ -543,11 +556,24 @@ impl<'source> CodeGenerator<'source> {
             self.end_if();
             self.end_for_loop(false);
             self.add(Instruction::BuildList(None));
-        } else {
-            self.compile_expr(&for_loop.iter);
+            if for_loop.recursive {
+                let skip = self.add(Instruction::Jump(!0));
+                self.add(Instruction::Jump(filter_start));
+                let loop_start = self.next_instruction();
+                if let Some(Instruction::Jump(ref mut target)) = self.instructions.get_mut(skip) {
+                    *target = loop_start;
+                }
+            }
         }
 
-        self.start_for_loop(true, for_loop.recursive);
+        let mut flags = LOOP_FLAG_WITH_LOOP_VAR;
+        if for_loop.recursive {
+            flags |= LOOP_FLAG_RECURSIVE;
+            if for_loop.filter_expr.is_some() {
+                flags |= LOOP_FLAG_FILTERED;
+            }
+        }
+        self.start_for_loop_with_flags(flags);
         self.compile_assignment(&for_loop.target);
         for node in &for_loop.body {
             self.compile_stmt(node);
@@ -789,13 +815,13 @@ impl<'source> CodeGenerator<'source> {
         if has_kwargs {
             let mut pending_kwargs = 0;
             let mut num_kwargs_batches = 0;
-            let mut collected_kwargs = ValueMap::new();
+            let mut collected_kwargs = Vec::new();
             for arg in args {
                 match arg {
                     ast::CallArg::Kwarg(key, value) => {
                         if static_kwargs {
                             if let ast::Expr::Const(c) = value {
-                                collected_kwargs.insert(Value::from(*key), c.value.clone());
+                                collected_kwargs.push((Value::from(*key), c.value.clone()));
                             } else {
                                 unreachable!();
                             }
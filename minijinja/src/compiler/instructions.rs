@@ -11,6 +11,10 @@ pub const LOOP_FLAG_WITH_LOOP_VAR: u8 = 1;
 /// This loop is recursive.
 pub const LOOP_FLAG_RECURSIVE: u8 = 2;
 
+/// The iterable of this recursive loop is filtered.  Recursing re-enters
+/// through the instruction preceding the loop push which jumps to the filter.
+pub const LOOP_FLAG_FILTERED: u8 = 4;
+
 /// This macro uses the caller var.
 #[cfg(feature = "macros")]
 pub const MACRO_CALLER: u8 = 2;
@@ -208,6 +212,10 @@ pub enum Instruction<'source> {
     #[cfg(feature = "multi_template")]
     CallBlock(&'source str),
 
+    /// Fails because a required block was not overridden.
+    #[cfg(feature = "multi_template")]
+    RequiredBlock(&'source str),
+
     /// Loads block from a template with name on stack ("extends")
     #[cfg(feature = "multi_template")]
     LoadBlocks,
@@ -242,26 +250,26 @@ pub enum Instruction<'source> {
 }
 
 #[derive(Copy, Clone)]
-struct LineInfo {
-    first_instruction: u32,
-    line: u32,
+pub(crate) struct LineInfo {
+    pub(crate) first_instruction: u32,
+    pub(crate) line: u32,
 }
 
 #[cfg(feature = "debug")]
 #[derive(Copy, Clone)]
-struct SpanInfo {
-    first_instruction: u32,
-    span: Option<Span>,
+pub(crate) struct SpanInfo {
+    pub(crate) first_instruction: u32,
+    pub(crate) span: Option<Span>,
 }
 
 /// Wrapper around instructions to help with location management.
 pub struct Instructions<'source> {
     pub(crate) instructions: Vec<Instruction<'source>>,
-    line_infos: Vec<LineInfo>,
+    pub(crate) line_infos: Vec<LineInfo>,
     #[cfg(feature = "debug")]
-    span_infos: Vec<SpanInfo>,
-    name: &'source str,
-    source: &'source str,
+    pub(crate) span_infos: Vec<SpanInfo>,
+    pub(crate) name: &'source str,
+    pub(crate) source: &'source str,
 }
 
 pub(crate) static EMPTY_INSTRUCTIONS: Instructions<'static> = Instructions {
@@ -6,12 +6,13 @@ use crate::error::{Error, ErrorKind};
 use crate::syntax::SyntaxConfig;
 use crate::utils::{memchr, memstr, unescape};
 
-/// Internal config struct to control whitespace in the engine.
+/// Internal config struct to control whitespace and comments in the engine.
 #[derive(Copy, Clone, Debug, Default)]
 pub struct WhitespaceConfig {
     pub keep_trailing_newline: bool,
     pub lstrip_blocks: bool,
     pub trim_blocks: bool,
+    pub keep_comments: bool,
 }
 
 /// Tokenizes jinja templates.
@@ -328,6 +329,11 @@ impl<'s> Tokenizer<'s> {
         }
     }
 
+    /// Skips over the given number of bytes without tokenizing them.
+    pub fn skip(&mut self, bytes: usize) {
+        self.advance(bytes.min(self.rest_bytes().len()));
+    }
+
     /// Returns the current filename.
     pub fn filename(&self) -> &str {
         self.filename
@@ -657,9 +663,15 @@ impl<'s> Tokenizer<'s> {
                     let ws = Whitespace::from_byte(
                         self.rest_bytes().get(end.saturating_sub(1) + skip).copied(),
                     );
-                    self.advance(end + skip + self.comment_end().len());
+                    let old_loc = self.loc();
+                    let comment = self.advance(end + skip + self.comment_end().len());
+                    let span = self.span(old_loc);
                     self.handle_tail_ws(ws);
-                    Ok(ControlFlow::Continue(()))
+                    if self.ws_config.keep_comments {
+                        Ok(ControlFlow::Break((Token::TemplateData(comment), span)))
+                    } else {
+                        Ok(ControlFlow::Continue(()))
+                    }
                 } else {
                     self.advance(self.rest_bytes().len());
                     Err(self.syntax_error("unexpected end of comment"))
@@ -1,6 +1,8 @@
 #![allow(missing_docs)]
 /// This module contains the internals of the compiler.
 pub mod ast;
+#[cfg(feature = "bytecode")]
+pub mod bytecode;
 pub mod codegen;
 pub mod instructions;
 pub mod lexer;
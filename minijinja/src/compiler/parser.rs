@@ -107,9 +107,11 @@ impl<'a> TokenStream<'a> {
         in_expr: bool,
         syntax_config: SyntaxConfig,
         whitespace_config: WhitespaceConfig,
+        offset: usize,
     ) -> TokenStream<'a> {
         let mut tokenizer =
             Tokenizer::new(source, filename, in_expr, syntax_config, whitespace_config);
+        tokenizer.skip(offset);
         let current = tokenizer.next_token().transpose();
         TokenStream {
             tokenizer,
@@ -244,9 +246,17 @@ impl<'a> Parser<'a> {
         in_expr: bool,
         syntax_config: SyntaxConfig,
         whitespace_config: WhitespaceConfig,
+        offset: usize,
     ) -> Parser<'a> {
         Parser {
-            stream: TokenStream::new(source, filename, in_expr, syntax_config, whitespace_config),
+            stream: TokenStream::new(
+                source,
+                filename,
+                in_expr,
+                syntax_config,
+                whitespace_config,
+                offset,
+            ),
             in_macro: false,
             in_loop: false,
             blocks: BTreeSet::new(),
@@ -988,10 +998,35 @@ impl<'a> Parser<'a> {
             syntax_error!("block '{}' defined twice", name);
         }
 
+        // blocks in MiniJinja always see the enclosing scope, so `scoped`
+        // is accepted for compatibility with Jinja2 but has no effect.
+        if matches!(
+            ok!(self.stream.current()),
+            Some((Token::Ident("scoped"), _))
+        ) {
+            ok!(self.stream.next());
+        }
+        let required = matches!(
+            ok!(self.stream.current()),
+            Some((Token::Ident("required"), _))
+        );
+        if required {
+            ok!(self.stream.next());
+        }
+
         expect_token!(self, Token::BlockEnd, "end of block");
         let body = ok!(self.subparse(&|tok| matches!(tok, Token::Ident("endblock"))));
         ok!(self.stream.next());
 
+        if required
+            && !body.iter().all(|node| match node {
+                ast::Stmt::EmitRaw(raw) => raw.raw.trim().is_empty(),
+                _ => false,
+            })
+        {
+            syntax_error!("required blocks can only contain comments or whitespace");
+        }
+
         if let Some((Token::Ident(trailing_name), _)) = ok!(self.stream.current()) {
             if *trailing_name != name {
                 syntax_error!(
@@ -1004,7 +1039,11 @@ impl<'a> Parser<'a> {
         }
         self.in_loop = old_in_loop;
 
-        Ok(ast::Block { name, body })
+        Ok(ast::Block {
+            name,
+            required,
+            body,
+        })
     }
     fn parse_auto_escape(&mut self) -> Result<ast::AutoEscape<'a>, Error> {
         let enabled = ok!(self.parse_expr());
@@ -1253,13 +1292,36 @@ impl<'a> Parser<'a> {
 }
 
 /// Parses a template.
+#[cfg(feature = "unstable_machinery")]
 pub fn parse<'source>(
     source: &'source str,
     filename: &'source str,
     syntax_config: SyntaxConfig,
     whitespace_config: WhitespaceConfig,
 ) -> Result<ast::Stmt<'source>, Error> {
-    Parser::new(source, filename, false, syntax_config, whitespace_config).parse()
+    parse_from_offset(source, filename, 0, syntax_config, whitespace_config)
+}
+
+/// Parses a template starting at the given byte offset.
+///
+/// The skipped part of the source is not parsed but still counts towards
+/// the line numbers and spans of the template.
+pub fn parse_from_offset<'source>(
+    source: &'source str,
+    filename: &'source str,
+    offset: usize,
+    syntax_config: SyntaxConfig,
+    whitespace_config: WhitespaceConfig,
+) -> Result<ast::Stmt<'source>, Error> {
+    Parser::new(
+        source,
+        filename,
+        false,
+        syntax_config,
+        whitespace_config,
+        offset,
+    )
+    .parse()
 }
 
 /// Parses a standalone expression.
@@ -1270,6 +1332,7 @@ pub fn parse_expr(source: &str) -> Result<ast::Expr<'_>, Error> {
         true,
         Default::default(),
         Default::default(),
+        0,
     )
     .parse_standalone_expr()
 }
@@ -2,7 +2,7 @@ use std::collections::BTreeMap;
 use std::fmt;
 
 use crate::compiler::tokens::Span;
-use crate::error::ErrorKind;
+use crate::error::{ErrorKind, ReportOptions};
 use crate::value::Value;
 
 /// This is a snapshot of the debug information.
@@ -37,14 +37,15 @@ impl DebugInfo {
 }
 
 pub(super) fn render_debug_info(
-    f: &mut fmt::Formatter,
+    f: &mut dyn fmt::Write,
     name: Option<&str>,
     kind: ErrorKind,
     line: Option<usize>,
     span: Option<Span>,
     info: &DebugInfo,
+    options: &ReportOptions,
 ) -> fmt::Result {
-    if let Some(source) = info.source() {
+    if let Some(source) = info.source().filter(|_| options.source_snippet) {
         let title = format!(
             " {} ",
             name.unwrap_or_default()
@@ -53,24 +54,42 @@ pub(super) fn render_debug_info(
                 .unwrap_or("Template Source")
         );
         ok!(writeln!(f));
-        writeln!(f, "{:-^1$}", title, 79).unwrap();
+        ok!(writeln!(f, "{:-^1$}", title, 79));
         let lines: Vec<_> = source.lines().enumerate().collect();
         let idx = line.unwrap_or(1).saturating_sub(1);
         let skip = idx.saturating_sub(3);
         let pre = lines.iter().skip(skip).take(3.min(idx)).collect::<Vec<_>>();
         let post = lines.iter().skip(idx + 1).take(3).collect::<Vec<_>>();
+        let gutter = |f: &mut dyn fmt::Write, lineno: usize, marker: char| {
+            if options.line_numbers {
+                write!(f, "{:>4} {} ", lineno, marker)
+            } else {
+                write!(f, "{} ", marker)
+            }
+        };
         for (idx, line) in pre {
-            writeln!(f, "{:>4} | {}", idx + 1, line).unwrap();
+            ok!(gutter(f, idx + 1, '|'));
+            ok!(writeln!(f, "{}", line));
         }
 
         if let Some(line) = lines.get(idx) {
-            writeln!(f, "{:>4} > {}", idx + 1, line.1).unwrap();
+            ok!(gutter(f, idx + 1, '>'));
+            ok!(writeln!(f, "{}", line.1));
         }
         if let Some(span) = span {
             if span.start_line == span.end_line {
+                ok!(write!(
+                    f,
+                    "{}",
+                    if options.line_numbers {
+                        "     i "
+                    } else {
+                        "i "
+                    }
+                ));
                 ok!(writeln!(
                     f,
-                    "     i {}{} {}",
+                    "{}{} {}",
                     " ".repeat(span.start_col as usize),
                     "^".repeat(span.end_col as usize - span.start_col as usize),
                     kind,
@@ -79,12 +98,15 @@ pub(super) fn render_debug_info(
         }
 
         for (idx, line) in post {
-            writeln!(f, "{:>4} | {}", idx + 1, line).unwrap();
+            ok!(gutter(f, idx + 1, '|'));
+            ok!(writeln!(f, "{}", line));
         }
-        write!(f, "{:~^1$}", "", 79).unwrap();
+        ok!(write!(f, "{:~^1$}", "", 79));
+    }
+    if options.referenced_variables {
+        ok!(writeln!(f));
+        ok!(writeln!(f, "{:#?}", VarPrinter(&info.referenced_locals)));
+        ok!(write!(f, "{:-^1$}", "", 79));
     }
-    ok!(writeln!(f));
-    ok!(writeln!(f, "{:#?}", VarPrinter(&info.referenced_locals)));
-    write!(f, "{:-^1$}", "", 79).unwrap();
     Ok(())
 }
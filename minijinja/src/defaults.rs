@@ -5,7 +5,7 @@ use crate::error::Error;
 use crate::filters::{self, BoxedFilter};
 use crate::output::Output;
 use crate::tests::{self, BoxedTest};
-use crate::utils::{write_escaped, AutoEscape};
+use crate::utils::AutoEscape;
 use crate::value::Value;
 use crate::vm::State;
 
@@ -46,9 +46,10 @@ pub fn default_auto_escape_callback(name: &str) -> AutoEscape {
     doc = r" * [`Json`](AutoEscape::Json): serializes values to JSON"
 )]
 /// * [`None`](AutoEscape::None): no escaping
-/// * [`Custom(..)`](AutoEscape::Custom): results in an error
+/// * [`Custom(..)`](AutoEscape::Custom): uses the escaper registered with
+///   [`add_escaper`](crate::Environment::add_escaper) or errors if there is none
 pub fn escape_formatter(out: &mut Output, state: &State, value: &Value) -> Result<(), Error> {
-    write_escaped(out, state.auto_escape(), value)
+    state.env().write_escaped(out, state.auto_escape(), value)
 }
 
 pub(crate) fn get_builtin_filters() -> BTreeMap<Cow<'static, str>, filters::BoxedFilter> {
@@ -59,40 +60,47 @@ pub(crate) fn get_builtin_filters() -> BTreeMap<Cow<'static, str>, filters::Boxe
     rv.insert("e".into(), escape);
     #[cfg(feature = "builtins")]
     {
-        rv.insert("lower".into(), BoxedFilter::new(filters::lower));
-        rv.insert("upper".into(), BoxedFilter::new(filters::upper));
+        rv.insert("lower".into(), BoxedFilter::new(filters::lower_keep_safe));
+        rv.insert("forceescape".into(), BoxedFilter::new(filters::forceescape));
+        rv.insert("upper".into(), BoxedFilter::new(filters::upper_keep_safe));
         rv.insert("title".into(), BoxedFilter::new(filters::title));
         rv.insert("capitalize".into(), BoxedFilter::new(filters::capitalize));
-        rv.insert("replace".into(), BoxedFilter::new(filters::replace));
+        rv.insert("replace".into(), BoxedFilter::new(filters::replace_kwargs));
+        rv.insert("format".into(), BoxedFilter::new(filters::format));
         let length = BoxedFilter::new(filters::length);
         rv.insert("length".into(), length.clone());
         rv.insert("count".into(), length);
         rv.insert("dictsort".into(), BoxedFilter::new(filters::dictsort));
         rv.insert("items".into(), BoxedFilter::new(filters::items));
+        rv.insert("combine".into(), BoxedFilter::new(filters::combine));
         rv.insert("reverse".into(), BoxedFilter::new(filters::reverse));
-        rv.insert("trim".into(), BoxedFilter::new(filters::trim));
+        rv.insert("trim".into(), BoxedFilter::new(filters::trim_keep_safe));
+        rv.insert("lstrip".into(), BoxedFilter::new(filters::lstrip));
+        rv.insert("rstrip".into(), BoxedFilter::new(filters::rstrip));
         rv.insert("join".into(), BoxedFilter::new(filters::join));
         rv.insert("split".into(), BoxedFilter::new(filters::split));
         rv.insert("lines".into(), BoxedFilter::new(filters::lines));
-        rv.insert("default".into(), BoxedFilter::new(filters::default));
-        rv.insert("round".into(), BoxedFilter::new(filters::round));
+        let default = BoxedFilter::new(filters::default);
+        rv.insert("default".into(), default.clone());
+        rv.insert("d".into(), default);
+        rv.insert("round".into(), BoxedFilter::new(filters::round_kwargs));
         rv.insert("abs".into(), BoxedFilter::new(filters::abs));
-        rv.insert("int".into(), BoxedFilter::new(filters::int));
-        rv.insert("float".into(), BoxedFilter::new(filters::float));
+        rv.insert("int".into(), BoxedFilter::new(filters::int_kwargs));
+        rv.insert("float".into(), BoxedFilter::new(filters::float_kwargs));
         rv.insert("attr".into(), BoxedFilter::new(filters::attr));
-        rv.insert("first".into(), BoxedFilter::new(filters::first));
-        rv.insert("last".into(), BoxedFilter::new(filters::last));
-        rv.insert("min".into(), BoxedFilter::new(filters::min));
-        rv.insert("max".into(), BoxedFilter::new(filters::max));
+        rv.insert("first".into(), BoxedFilter::new(filters::first_with_state));
+        rv.insert("last".into(), BoxedFilter::new(filters::last_with_state));
+        rv.insert("min".into(), BoxedFilter::new(filters::min_kwargs));
+        rv.insert("max".into(), BoxedFilter::new(filters::max_kwargs));
         rv.insert("sort".into(), BoxedFilter::new(filters::sort));
-        rv.insert("d".into(), BoxedFilter::new(filters::default));
         rv.insert("list".into(), BoxedFilter::new(filters::list));
         rv.insert("string".into(), BoxedFilter::new(filters::string));
         rv.insert("bool".into(), BoxedFilter::new(filters::bool));
         rv.insert("batch".into(), BoxedFilter::new(filters::batch));
         rv.insert("slice".into(), BoxedFilter::new(filters::slice));
-        rv.insert("sum".into(), BoxedFilter::new(filters::sum));
+        rv.insert("sum".into(), BoxedFilter::new(filters::sum_kwargs));
         rv.insert("indent".into(), BoxedFilter::new(filters::indent));
+        rv.insert("wordwrap".into(), BoxedFilter::new(filters::wordwrap));
         rv.insert("select".into(), BoxedFilter::new(filters::select));
         rv.insert("reject".into(), BoxedFilter::new(filters::reject));
         rv.insert("selectattr".into(), BoxedFilter::new(filters::selectattr));
@@ -128,7 +136,10 @@ pub(crate) fn get_builtin_tests() -> BTreeMap<Cow<'static, str>, BoxedTest> {
         rv.insert("boolean".into(), BoxedTest::new(tests::is_boolean));
         rv.insert("odd".into(), BoxedTest::new(tests::is_odd));
         rv.insert("even".into(), BoxedTest::new(tests::is_even));
-        rv.insert("divisibleby".into(), BoxedTest::new(tests::is_divisibleby));
+        rv.insert(
+            "divisibleby".into(),
+            BoxedTest::new(tests::is_divisibleby_checked),
+        );
         rv.insert("number".into(), BoxedTest::new(tests::is_number));
         rv.insert("integer".into(), BoxedTest::new(tests::is_integer));
         rv.insert("int".into(), BoxedTest::new(tests::is_integer));
@@ -139,9 +150,12 @@ pub(crate) fn get_builtin_tests() -> BTreeMap<Cow<'static, str>, BoxedTest> {
         rv.insert("mapping".into(), BoxedTest::new(tests::is_mapping));
         rv.insert(
             "startingwith".into(),
-            BoxedTest::new(tests::is_startingwith),
+            BoxedTest::new(tests::is_startingwith_any),
+        );
+        rv.insert(
+            "endingwith".into(),
+            BoxedTest::new(tests::is_endingwith_any),
         );
-        rv.insert("endingwith".into(), BoxedTest::new(tests::is_endingwith));
         rv.insert("lower".into(), BoxedTest::new(tests::is_lower));
         rv.insert("upper".into(), BoxedTest::new(tests::is_upper));
         rv.insert("sameas".into(), BoxedTest::new(tests::is_sameas));
@@ -185,8 +199,13 @@ pub(crate) fn get_globals() -> BTreeMap<Cow<'static, str>, Value> {
         use crate::functions::{self, BoxedFunction};
         rv.insert(
             "range".into(),
-            BoxedFunction::new(functions::range).to_value(),
+            BoxedFunction::new(functions::range_any).to_value(),
+        );
+        rv.insert(
+            "enumerate".into(),
+            BoxedFunction::new(functions::enumerate).to_value(),
         );
+        rv.insert("zip".into(), BoxedFunction::new(functions::zip).to_value());
         rv.insert(
             "dict".into(),
             BoxedFunction::new(functions::dict).to_value(),
@@ -12,15 +12,21 @@ use crate::error::{attach_basic_debug_info, Error, ErrorKind};
 use crate::expression::Expression;
 use crate::output::Output;
 use crate::template::{CompiledTemplate, CompiledTemplateRef, Template, TemplateConfig};
-use crate::utils::{AutoEscape, BTreeMapKeysDebug, UndefinedBehavior};
+use crate::utils::{
+    write_escaped, write_with_custom_escaping, AutoEscape, BTreeMapKeysDebug, UndefinedBehavior,
+    WhitespacePolicy,
+};
 use crate::value::{FunctionArgs, FunctionResult, Value};
-use crate::vm::State;
+use crate::vm::{State, TraceEvent};
 use crate::{defaults, filters, functions, tests};
 
 type FormatterFunc = dyn Fn(&mut Output, &State, &Value) -> Result<(), Error> + Sync + Send;
+pub(crate) type EscaperFunc = dyn Fn(&mut Output, &str) -> fmt::Result + Sync + Send;
 type PathJoinFunc = dyn for<'s> Fn(&'s str, &'s str) -> Cow<'s, str> + Sync + Send;
 type UnknownMethodFunc =
     dyn Fn(&State, &Value, &str, &[Value]) -> Result<Value, Error> + Sync + Send;
+type AttributeAccessFunc = dyn Fn(&Value, &str) -> bool + Sync + Send;
+pub(crate) type TraceFunc = dyn Fn(TraceEvent<'_>) + Sync + Send;
 
 /// The maximum recursion in the VM.  Normally each stack frame
 /// adds one to this counter (eg: every time a frame is added).
@@ -44,21 +50,32 @@ const MAX_RECURSION: usize = 500;
 ///   defaults.  It will contain all built-in filters, tests and globals as well
 ///   as a callback for auto escaping based on file extension.
 /// * [`Environment::empty`] creates a completely blank environment.
+///
+/// Cloning an environment is cheap.  Compiled templates as well as the
+/// registered filters, tests and globals are shared between the clones and
+/// only copied when one of the clones is modified.  Modifications to a clone
+/// are never visible to the original environment.
 #[derive(Clone)]
 pub struct Environment<'source> {
     templates: TemplateStore<'source>,
-    filters: BTreeMap<Cow<'source, str>, filters::BoxedFilter>,
-    tests: BTreeMap<Cow<'source, str>, tests::BoxedTest>,
-    globals: BTreeMap<Cow<'source, str>, Value>,
+    filters: Arc<BTreeMap<Cow<'source, str>, filters::BoxedFilter>>,
+    tests: Arc<BTreeMap<Cow<'source, str>, tests::BoxedTest>>,
+    globals: Arc<BTreeMap<Cow<'source, str>, Value>>,
     path_join_callback: Option<Arc<PathJoinFunc>>,
     pub(crate) unknown_method_callback: Option<Arc<UnknownMethodFunc>>,
+    attribute_access_policy: Option<Arc<AttributeAccessFunc>>,
+    pub(crate) trace_callback: Option<Arc<TraceFunc>>,
     undefined_behavior: UndefinedBehavior,
+    output_whitespace: WhitespacePolicy,
     formatter: Arc<FormatterFunc>,
+    escapers: Arc<BTreeMap<Cow<'source, str>, Arc<EscaperFunc>>>,
     #[cfg(feature = "debug")]
     debug: bool,
     #[cfg(feature = "fuel")]
     fuel: Option<u64>,
     recursion_limit: usize,
+    #[cfg(feature = "loader")]
+    inline_template_cache: Option<Arc<crate::loader::InlineTemplateCache>>,
 }
 
 impl Default for Environment<'_> {
@@ -71,8 +88,8 @@ impl fmt::Debug for Environment<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Environment")
             .field("globals", &self.globals)
-            .field("tests", &BTreeMapKeysDebug(&self.tests))
-            .field("filters", &BTreeMapKeysDebug(&self.filters))
+            .field("tests", &BTreeMapKeysDebug(&*self.tests))
+            .field("filters", &BTreeMapKeysDebug(&*self.filters))
             .field("templates", &self.templates)
             .finish()
     }
@@ -90,18 +107,24 @@ impl<'source> Environment<'source> {
             templates: TemplateStore::new(TemplateConfig::new(Arc::new(
                 defaults::default_auto_escape_callback,
             ))),
-            filters: defaults::get_builtin_filters(),
-            tests: defaults::get_builtin_tests(),
-            globals: defaults::get_globals(),
+            filters: Arc::new(defaults::get_builtin_filters()),
+            tests: Arc::new(defaults::get_builtin_tests()),
+            globals: Arc::new(defaults::get_globals()),
             path_join_callback: None,
             unknown_method_callback: None,
+            attribute_access_policy: None,
+            trace_callback: None,
             undefined_behavior: UndefinedBehavior::default(),
+            output_whitespace: WhitespacePolicy::default(),
             formatter: Arc::new(defaults::escape_formatter),
+            escapers: Default::default(),
             #[cfg(feature = "debug")]
             debug: cfg!(debug_assertions),
             #[cfg(feature = "fuel")]
             fuel: None,
             recursion_limit: MAX_RECURSION,
+            #[cfg(feature = "loader")]
+            inline_template_cache: None,
         }
     }
 
@@ -117,13 +140,19 @@ impl<'source> Environment<'source> {
             globals: Default::default(),
             path_join_callback: None,
             unknown_method_callback: None,
+            attribute_access_policy: None,
+            trace_callback: None,
             undefined_behavior: UndefinedBehavior::default(),
+            output_whitespace: WhitespacePolicy::default(),
             formatter: Arc::new(defaults::escape_formatter),
+            escapers: Default::default(),
             #[cfg(feature = "debug")]
             debug: cfg!(debug_assertions),
             #[cfg(feature = "fuel")]
             fuel: None,
             recursion_limit: MAX_RECURSION,
+            #[cfg(feature = "loader")]
+            inline_template_cache: None,
         }
     }
 
@@ -149,6 +178,28 @@ impl<'source> Environment<'source> {
         self.templates.insert(name, source)
     }
 
+    /// Loads a template from a string and returns it.
+    ///
+    /// This works like [`add_template`](Self::add_template) but returns the
+    /// loaded template right away which saves a separate call to
+    /// [`get_template`](Self::get_template).  The environment stays borrowed
+    /// for as long as the template is held.
+    ///
+    /// ```
+    /// # use minijinja::{Environment, context};
+    /// let mut env = Environment::new();
+    /// let tmpl = env.add_and_get_template("hello.txt", "Hello {{ name }}!").unwrap();
+    /// assert_eq!(tmpl.render(context!(name => "World")).unwrap(), "Hello World!");
+    /// ```
+    pub fn add_and_get_template(
+        &mut self,
+        name: &'source str,
+        source: &'source str,
+    ) -> Result<Template<'_, '_>, Error> {
+        ok!(self.templates.insert(name, source));
+        self.get_template(name)
+    }
+
     /// Adds a template without borrowing.
     ///
     /// This lets you place an owned [`String`] in the environment rather than the
@@ -172,6 +223,42 @@ impl<'source> Environment<'source> {
         self.templates.insert_cow(name.into(), source.into())
     }
 
+    /// Adds a template that was compiled ahead of time.
+    ///
+    /// The data has to be created with [`Template::to_compiled_bytes`] by the
+    /// same version of MiniJinja.  Loading such a template skips parsing and
+    /// compiling entirely which can speed up the start of applications with a
+    /// large number of templates.  If the data was created by a different
+    /// version or is corrupted, an error of kind
+    /// [`InvalidBytecode`](crate::ErrorKind::InvalidBytecode) is returned.
+    ///
+    /// ```
+    /// # use minijinja::{Environment, context};
+    /// let mut env = Environment::new();
+    /// env.add_template("hello.txt", "Hello {{ name }}!").unwrap();
+    /// let data = env.get_template("hello.txt").unwrap().to_compiled_bytes().unwrap();
+    ///
+    /// let mut other_env = Environment::new();
+    /// other_env.add_compiled_template("hello.txt", &data).unwrap();
+    /// let tmpl = other_env.get_template("hello.txt").unwrap();
+    /// assert_eq!(tmpl.render(context!(name => "World")).unwrap(), "Hello World!");
+    /// ```
+    ///
+    /// The initial auto escaping is determined by the environment that loads
+    /// the template, all syntax and whitespace settings are however already
+    /// applied when the template was compiled.  Loading validates the structure
+    /// of the data including string references and jump targets, but it does
+    /// not verify that the instructions form a program the compiler would
+    /// have emitted.  The data should therefore come from a trusted source.
+    #[cfg(feature = "bytecode")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "bytecode")))]
+    pub fn add_compiled_template<N>(&mut self, name: N, data: &[u8]) -> Result<(), Error>
+    where
+        N: Into<Cow<'source, str>>,
+    {
+        self.templates.insert_compiled(name.into(), data)
+    }
+
     /// Register a template loader as source of templates.
     ///
     /// When a template loader is registered, the environment gains the ability
@@ -179,7 +266,16 @@ impl<'source> Environment<'source> {
     /// the template.  If this template exists `Ok(Some(template_source))` has
     /// to be returned, otherwise `Ok(None)`.  Once a template has been loaded
     /// it's stored on the environment.  This means the loader is only invoked
-    /// once per template name.
+    /// once per template name and the template is only compiled once, no matter
+    /// how often it's rendered, included or extended.
+    ///
+    /// The cache is not bounded.  Templates handed out by the environment
+    /// borrow from it, so loaded templates can only be released through
+    /// [`remove_template`](Self::remove_template) or
+    /// [`clear_templates`](Self::clear_templates) which require mutable access.
+    /// Servers with an unbounded set of dynamic template names should clear
+    /// the templates periodically or compile such templates with
+    /// [`template_from_str`](Self::template_from_str) instead.
     ///
     /// For loading templates from the file system, you can use the
     /// [`path_loader`](crate::path_loader) function.
@@ -257,6 +353,33 @@ impl<'source> Environment<'source> {
         self.templates.template_config.ws_config.lstrip_blocks
     }
 
+    /// Keep comments in the output.
+    ///
+    /// By default `{# ... #}` comments are removed from templates.  If this is
+    /// set to `true` comments are instead emitted as is, including their
+    /// delimiters.  Whitespace control on the comment tags is still honored.
+    /// This is useful when generating source files where license headers or
+    /// markers written as template comments should be retained.
+    ///
+    /// ```
+    /// # use minijinja::{Environment, context};
+    /// let mut env = Environment::new();
+    /// env.set_keep_comments(true);
+    /// let rv = env.render_str("{# SPDX-License-Identifier: MIT #}\nx", context!()).unwrap();
+    /// assert_eq!(rv, "{# SPDX-License-Identifier: MIT #}\nx");
+    /// ```
+    ///
+    /// This setting is used whenever a template is loaded into the environment.
+    /// Changing it at a later point only affects future templates loaded.
+    pub fn set_keep_comments(&mut self, yes: bool) {
+        self.templates.template_config.ws_config.keep_comments = yes;
+    }
+
+    /// Returns the value of the keep comments flag.
+    pub fn keep_comments(&self) -> bool {
+        self.templates.template_config.ws_config.keep_comments
+    }
+
     /// Removes a template by name.
     pub fn remove_template(&mut self, name: &str) {
         self.templates.remove(name);
@@ -292,6 +415,33 @@ impl<'source> Environment<'source> {
         self.path_join_callback = Some(Arc::new(f));
     }
 
+    /// Enables or disables resolving template names relative to the current template.
+    ///
+    /// When enabled, names passed to `include`, `extends`, `import` and similar tags
+    /// that start with `./` or `../` are resolved against the directory of the
+    /// template that references them.  All other names continue to be resolved
+    /// from the root of the loader.
+    ///
+    /// ```
+    /// # let mut env = minijinja::Environment::new();
+    /// env.set_relative_includes(true);
+    /// env.add_template("pages/blog/index.html", "{% extends '../base.html' %}").unwrap();
+    /// env.add_template("pages/base.html", "base").unwrap();
+    /// let tmpl = env.get_template("pages/blog/index.html").unwrap();
+    /// assert_eq!(tmpl.render(()).unwrap(), "base");
+    /// ```
+    ///
+    /// This is implemented as a path join callback, so it replaces a callback
+    /// registered with [`set_path_join_callback`](Self::set_path_join_callback)
+    /// and disabling it removes any such callback.
+    pub fn set_relative_includes(&mut self, yes: bool) {
+        self.path_join_callback = if yes {
+            Some(Arc::new(join_relative_path))
+        } else {
+            None
+        };
+    }
+
     /// Sets a callback invoked for unknown methods on objects.
     ///
     /// This registers a function with the environment that is invoked when invoking a method
@@ -327,6 +477,86 @@ impl<'source> Environment<'source> {
         self.unknown_method_callback = Some(Arc::new(f));
     }
 
+    /// Sets a policy that controls attribute access and method calls.
+    ///
+    /// The policy is invoked with the value and the name of the attribute or
+    /// method before an attribute is looked up (`value.name` or
+    /// `value["name"]`) or a method is called (`value.name()`) in a template.
+    /// If the policy returns `false`, the operation fails with a
+    /// [`SecurityError`](crate::ErrorKind::SecurityError).  By default all
+    /// access is permitted.
+    ///
+    /// This is useful when rendering untrusted templates against trusted data,
+    /// similar to the sandboxed environment of Jinja2:
+    ///
+    /// ```
+    /// # use minijinja::{Environment, context};
+    /// let mut env = Environment::new();
+    /// env.set_attribute_access_policy(|_value, name| !name.starts_with('_'));
+    /// let rv = env.render_str("{{ user.name }}", context! {
+    ///     user => context! { name => "John", _password => "secret" }
+    /// }).unwrap();
+    /// assert_eq!(rv, "John");
+    /// assert!(env.render_str("{{ user._password }}", context! {
+    ///     user => context! { name => "John", _password => "secret" }
+    /// }).is_err());
+    /// ```
+    ///
+    /// Note that the policy only applies to attribute and method syntax in
+    /// templates.  Filters that look up attributes (such as `attr` or
+    /// `map(attribute=...)`) and Rust code are not restricted by it.  If that
+    /// is a concern, these filters should be removed from the environment.
+    pub fn set_attribute_access_policy<F>(&mut self, f: F)
+    where
+        F: Fn(&Value, &str) -> bool + Sync + Send + 'static,
+    {
+        self.attribute_access_policy = Some(Arc::new(f));
+    }
+
+    /// Sets a callback that is invoked to trace the execution of templates.
+    ///
+    /// When set, the engine measures how long filters, tests, functions,
+    /// methods, blocks and macros take and invokes the callback with a
+    /// [`TraceEvent`] once they finish.  The events carry the name of the
+    /// operation, the template and line it was invoked from as well as the
+    /// duration.  Because nested operations finish first, the events can be
+    /// used to build a flame graph like view of the template execution.
+    ///
+    /// When no callback is set, no timing information is collected.  On
+    /// `wasm32-unknown-unknown` the events are still emitted but all
+    /// durations are zero as there is no clock to measure them with.
+    ///
+    /// ```
+    /// # use minijinja::{Environment, context};
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let mut env = Environment::new();
+    /// let calls = Arc::new(Mutex::new(Vec::new()));
+    /// let calls_ref = calls.clone();
+    /// env.set_trace_callback(move |event| {
+    ///     calls_ref.lock().unwrap().push(event.name().to_string());
+    /// });
+    /// env.render_str("{{ 'x'|upper }}", context!()).unwrap();
+    /// assert_eq!(*calls.lock().unwrap(), vec!["upper".to_string()]);
+    /// ```
+    pub fn set_trace_callback<F>(&mut self, f: F)
+    where
+        F: Fn(TraceEvent<'_>) + Sync + Send + 'static,
+    {
+        self.trace_callback = Some(Arc::new(f));
+    }
+
+    /// Checks the attribute access policy for a value and name.
+    pub(crate) fn check_attribute_access(&self, value: &Value, name: &str) -> Result<(), Error> {
+        match self.attribute_access_policy {
+            Some(ref policy) if !policy(value, name) => Err(Error::new(
+                ErrorKind::SecurityError,
+                format!("access to '{}' on {} is not permitted", name, value.kind()),
+            )),
+            _ => Ok(()),
+        }
+    }
+
     /// Removes all stored templates.
     ///
     /// This method is mainly useful when combined with a loader as it causes
@@ -438,6 +668,18 @@ impl<'source> Environment<'source> {
         source: &str,
         ctx: S,
     ) -> Result<String, Error> {
+        #[cfg(feature = "loader")]
+        {
+            if let Some(ref cache) = self.inline_template_cache {
+                let loaded =
+                    ok!(cache.get_or_compile(name, source, &self.templates.template_config));
+                return Template::new(
+                    self,
+                    CompiledTemplateRef::Borrowed(loaded.borrow_dependent()),
+                )
+                .render(ctx);
+            }
+        }
         ok!(self.template_from_named_str(name, source)).render(ctx)
     }
 
@@ -452,9 +694,54 @@ impl<'source> Environment<'source> {
     /// **Note on values:** The [`Value`] type implements `Serialize` and can be
     /// efficiently passed to render.  It does not undergo actual serialization.
     pub fn render_str<S: Serialize>(&self, source: &str, ctx: S) -> Result<String, Error> {
-        // reduce total amount of code falling under mono morphization into
-        // this function, and share the rest in _eval.
-        ok!(self.template_from_str(source)).render(ctx)
+        self.render_named_str("<string>", source, ctx)
+    }
+
+    /// Enables a cache for templates rendered from strings.
+    ///
+    /// By default [`render_str`](Self::render_str) and
+    /// [`render_named_str`](Self::render_named_str) parse and compile the
+    /// template source on every call.  When the same ad-hoc templates are
+    /// rendered over and over (for instance templates stored in a database)
+    /// this cache can be enabled to skip that work for repeated sources.
+    ///
+    /// The cache holds at most `size` templates and evicts the least recently
+    /// used one once it's full.  A size of `0` disables the cache which is the
+    /// default.  Templates are cached by their exact source and name and are only
+    /// reused if the syntax, whitespace and auto escaping settings they were
+    /// compiled with still match the environment.  Templates that fail to
+    /// compile are not cached.
+    ///
+    /// ```
+    /// # let mut env = minijinja::Environment::new();
+    /// env.set_inline_template_cache_size(32);
+    /// for _ in 0..3 {
+    ///     // only compiled once
+    ///     env.render_str("Hello {{ name }}!", minijinja::context!{ name => "World" }).unwrap();
+    /// }
+    /// ```
+    ///
+    /// Clones of the environment share the cache.  Changing the size creates a
+    /// new empty cache.
+    #[cfg(feature = "loader")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "loader")))]
+    pub fn set_inline_template_cache_size(&mut self, size: usize) {
+        self.inline_template_cache = if size == 0 {
+            None
+        } else {
+            Some(Arc::new(crate::loader::InlineTemplateCache::new(size)))
+        };
+    }
+
+    /// Returns the size of the cache for templates rendered from strings.
+    ///
+    /// See [`set_inline_template_cache_size`](Self::set_inline_template_cache_size).
+    #[cfg(feature = "loader")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "loader")))]
+    pub fn inline_template_cache_size(&self) -> usize {
+        self.inline_template_cache
+            .as_ref()
+            .map_or(0, |x| x.capacity())
     }
 
     /// Sets a new function to select the default auto escaping.
@@ -484,6 +771,52 @@ impl<'source> Environment<'source> {
         self.templates.template_config.default_auto_escape = Arc::new(f);
     }
 
+    /// Enables front matter extraction with the given parser.
+    ///
+    /// Once set, templates that start with a front matter block have that
+    /// block removed before compilation.  The block is opened by a line that
+    /// only holds the delimiter (`---` by default, see
+    /// [`set_front_matter_delimiter`](Self::set_front_matter_delimiter)) and
+    /// closed by the next such line.  The text in between is handed to the
+    /// parser and the returned value can be retrieved with
+    /// [`Template::front_matter`].  This makes it possible to plug in a YAML,
+    /// TOML or JSON parser.  Line numbers of the template body continue to
+    /// refer to the original source.  By default front matter is not
+    /// extracted.
+    ///
+    /// ```
+    /// # use minijinja::{Environment, Value};
+    /// # let mut env = Environment::new();
+    /// env.set_front_matter_parser(|front_matter| {
+    ///     Ok(Value::from_iter(front_matter.lines().filter_map(|line| {
+    ///         let (key, value) = line.split_once(':')?;
+    ///         Some((key.trim().to_string(), value.trim().to_string()))
+    ///     })))
+    /// });
+    /// env.add_template("post.html", "---\ntitle: Hello\n---\n<h1>Hello!</h1>").unwrap();
+    /// let tmpl = env.get_template("post.html").unwrap();
+    /// let front_matter = tmpl.front_matter().unwrap();
+    /// assert_eq!(front_matter.get_attr("title").unwrap().as_str(), Some("Hello"));
+    /// assert_eq!(tmpl.render(()).unwrap(), "<h1>Hello!</h1>");
+    /// ```
+    pub fn set_front_matter_parser<F>(&mut self, f: F)
+    where
+        F: Fn(&str) -> Result<Value, Error> + 'static + Sync + Send,
+    {
+        self.templates.template_config.front_matter_parser = Some(Arc::new(f));
+    }
+
+    /// Sets the delimiter line of front matter blocks.
+    ///
+    /// The default is `---`.  This only has an effect if a parser was set with
+    /// [`set_front_matter_parser`](Self::set_front_matter_parser).
+    pub fn set_front_matter_delimiter<D>(&mut self, delimiter: D)
+    where
+        D: Into<Cow<'static, str>>,
+    {
+        self.templates.template_config.front_matter_delimiter = delimiter.into();
+    }
+
     /// Changes the undefined behavior.
     ///
     /// This changes the runtime behavior of [`undefined`](Value::UNDEFINED) values in
@@ -502,6 +835,31 @@ impl<'source> Environment<'source> {
         self.undefined_behavior
     }
 
+    /// Changes how whitespace in the rendered output is handled.
+    ///
+    /// By default whitespace is written as is.  With
+    /// [`WhitespacePolicy::Collapse`] runs of whitespace are collapsed as the
+    /// output is written which can considerably reduce the size of rendered
+    /// HTML without a separate minification pass.
+    /// [`WhitespacePolicy::CollapseHtml`] additionally leaves whitespace in
+    /// `<pre>` and `<textarea>` elements alone.
+    ///
+    /// ```
+    /// # use minijinja::{Environment, WhitespacePolicy, context};
+    /// let mut env = Environment::new();
+    /// env.set_output_whitespace(WhitespacePolicy::Collapse);
+    /// let rv = env.render_str("<ul>\n  <li>{{ x }}</li>\n</ul>", context!(x => 42)).unwrap();
+    /// assert_eq!(rv, "<ul>\n<li>42</li>\n</ul>");
+    /// ```
+    pub fn set_output_whitespace(&mut self, policy: WhitespacePolicy) {
+        self.output_whitespace = policy;
+    }
+
+    /// Returns the current output whitespace policy.
+    pub fn output_whitespace(&self) -> WhitespacePolicy {
+        self.output_whitespace
+    }
+
     /// Sets a different formatter function.
     ///
     /// The formatter is invoked to format the given value into the provided
@@ -515,7 +873,7 @@ impl<'source> Environment<'source> {
     /// `Undefined` which renders as an empty string instead.
     ///
     /// The current value of the auto escape flag can be retrieved directly
-    /// from the [`State`].
+    /// from the [`State`] or the [`Output`].
     ///
     /// ```
     /// # use minijinja::Environment;
@@ -543,6 +901,56 @@ impl<'source> Environment<'source> {
         self.formatter = Arc::new(f);
     }
 
+    /// Registers an escaper for a custom auto escape format.
+    ///
+    /// Whenever [`AutoEscape::Custom`] with the given name is active, the
+    /// default [`escape_formatter`](defaults::escape_formatter) and the
+    /// `escape` filter hand the stringified value to this function to write
+    /// it into the output.  Values marked as safe are written unchanged.
+    /// This makes it possible to render formats such as LaTeX, CSV or shell
+    /// scripts with proper escaping.
+    ///
+    /// ```
+    /// # use minijinja::{Environment, AutoEscape, context};
+    /// # let mut env = Environment::new();
+    /// use std::fmt::Write;
+    ///
+    /// env.set_auto_escape_callback(|name| {
+    ///     if name.ends_with(".tex") {
+    ///         AutoEscape::Custom("latex")
+    ///     } else {
+    ///         AutoEscape::None
+    ///     }
+    /// });
+    /// env.add_escaper("latex", |out, value| {
+    ///     for c in value.chars() {
+    ///         match c {
+    ///             '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+    ///                 out.write_char('\\')?;
+    ///                 out.write_char(c)?;
+    ///             }
+    ///             c => out.write_char(c)?,
+    ///         }
+    ///     }
+    ///     Ok(())
+    /// });
+    /// env.add_template("doc.tex", "{{ price }}").unwrap();
+    /// let tmpl = env.get_template("doc.tex").unwrap();
+    /// assert_eq!(tmpl.render(context!(price => "5$ & up")).unwrap(), "5\\$ \\& up");
+    /// ```
+    pub fn add_escaper<N, F>(&mut self, name: N, f: F)
+    where
+        N: Into<Cow<'source, str>>,
+        F: Fn(&mut Output, &str) -> fmt::Result + 'static + Sync + Send,
+    {
+        Arc::make_mut(&mut self.escapers).insert(name.into(), Arc::new(f));
+    }
+
+    /// Removes a custom escaper by name.
+    pub fn remove_escaper(&mut self, name: &str) {
+        Arc::make_mut(&mut self.escapers).remove(name);
+    }
+
     /// Enable or disable the debug mode.
     ///
     /// When the debug mode is enabled the engine will dump out some of the
@@ -605,6 +1013,19 @@ impl<'source> Environment<'source> {
     }
 
     /// Returns the current syntax config.
+    ///
+    /// ```
+    /// # use minijinja::{Environment, syntax::SyntaxConfig};
+    /// let mut env = Environment::new();
+    /// env.set_syntax(
+    ///     SyntaxConfig::builder()
+    ///         .variable_delimiters("${", "}")
+    ///         .build()
+    ///         .unwrap()
+    /// );
+    /// assert_eq!(env.syntax().variable_delimiters(), ("${", "}"));
+    /// assert_eq!(env.syntax().block_delimiters(), ("{%", "%}"));
+    /// ```
     #[cfg(feature = "custom_syntax")]
     #[cfg_attr(docsrs, doc(cfg(feature = "custom_syntax")))]
     pub fn syntax(&self) -> &crate::syntax::SyntaxConfig {
@@ -623,7 +1044,9 @@ impl<'source> Environment<'source> {
     /// recursion counter.  The actual cost attributed to that recursion depends on
     /// the cost of the operation.  If statements and for loops for instance only
     /// increase the counter by 1, whereas template includes and macros might increase
-    /// it to 10 or more.
+    /// it to 10 or more.  When the limit is exceeded rendering fails with an
+    /// error of kind [`RecursionLimitExceeded`](crate::ErrorKind::RecursionLimitExceeded).
+    /// The limit also applies when the `stacker` feature is enabled.
     ///
     /// **Note on stack growth:** even if the stacker feature is enabled it does not
     /// mean that in all cases stack can grow to the limits desired.  For instance in
@@ -650,6 +1073,17 @@ impl<'source> Environment<'source> {
     /// receive the output.  This lets one use the expressions of the language
     /// be used as a minimal scripting language.  For more information and an
     /// example see [`Expression`].
+    ///
+    /// Expressions are evaluated by the same engine as templates and have
+    /// access to all filters, tests and globals registered on the environment:
+    ///
+    /// ```
+    /// # use minijinja::{Environment, context};
+    /// let mut env = Environment::new();
+    /// env.add_filter("double", |x: i64| x * 2);
+    /// let expr = env.compile_expression("x|double is even and x|double > 5").unwrap();
+    /// assert!(expr.eval(context!(x => 3)).unwrap().is_true());
+    /// ```
     pub fn compile_expression(&self, expr: &'source str) -> Result<Expression<'_, 'source>, Error> {
         self._compile_expression(expr)
             .map(|instr| Expression::new(self, instr))
@@ -696,13 +1130,75 @@ impl<'source> Environment<'source> {
         Rv: FunctionResult,
         Args: for<'a> FunctionArgs<'a>,
     {
-        self.filters
-            .insert(name.into(), filters::BoxedFilter::new(f));
+        Arc::make_mut(&mut self.filters).insert(name.into(), filters::BoxedFilter::new(f));
+    }
+
+    /// Adds a new filter function that is passed shared data.
+    ///
+    /// Filters need to be `'static` which means that closures cannot borrow
+    /// external data.  Rather than cloning an [`Arc`] into every closure, this
+    /// method holds on to the [`Arc`] and passes a reference to the data as
+    /// first parameter to the filter.  For details see
+    /// [`FilterWithData`](crate::filters::FilterWithData).
+    ///
+    /// ```
+    /// # use minijinja::Environment;
+    /// use std::sync::Arc;
+    ///
+    /// struct Config {
+    ///     currency: String,
+    /// }
+    ///
+    /// let mut env = Environment::new();
+    /// let config = Arc::new(Config { currency: "EUR".into() });
+    /// env.add_filter_with_data("money", config, |config: &Config, value: f64| {
+    ///     format!("{:.2} {}", value, config.currency)
+    /// });
+    /// let rv = env.render_str("{{ 42|money }}", ()).unwrap();
+    /// assert_eq!(rv, "42.00 EUR");
+    /// ```
+    pub fn add_filter_with_data<N, D, F, Rv, Args>(&mut self, name: N, data: Arc<D>, f: F)
+    where
+        N: Into<Cow<'source, str>>,
+        D: Send + Sync + ?Sized + 'static,
+        // the crazy bounds here exist to enable borrowing in closures
+        F: filters::FilterWithData<D, Rv, Args>
+            + for<'a> filters::FilterWithData<D, Rv, <Args as FunctionArgs<'a>>::Output>,
+        Rv: FunctionResult,
+        Args: for<'a> FunctionArgs<'a>,
+    {
+        Arc::make_mut(&mut self.filters)
+            .insert(name.into(), filters::BoxedFilter::new_with_data(data, f));
+    }
+
+    /// Registers an already registered filter under an additional name.
+    ///
+    /// Both names refer to the same filter implementation afterwards.  If no
+    /// filter is registered under `existing` an error is returned.
+    ///
+    /// ```
+    /// # use minijinja::Environment;
+    /// let mut env = Environment::new();
+    /// env.add_filter("slugify", |v: String| v.to_lowercase().replace(' ', "-"));
+    /// env.add_filter_alias("slug", "slugify").unwrap();
+    /// ```
+    pub fn add_filter_alias<N>(&mut self, name: N, existing: &str) -> Result<(), Error>
+    where
+        N: Into<Cow<'source, str>>,
+    {
+        let filter = ok!(self.filters.get(existing).cloned().ok_or_else(|| {
+            Error::new(
+                ErrorKind::UnknownFilter,
+                format!("cannot alias unknown filter '{}'", existing),
+            )
+        }));
+        Arc::make_mut(&mut self.filters).insert(name.into(), filter);
+        Ok(())
     }
 
     /// Removes a filter by name.
     pub fn remove_filter(&mut self, name: &str) {
-        self.filters.remove(name);
+        Arc::make_mut(&mut self.filters).remove(name);
     }
 
     /// Adds a new test function.
@@ -718,12 +1214,30 @@ impl<'source> Environment<'source> {
         Rv: tests::TestResult,
         Args: for<'a> FunctionArgs<'a>,
     {
-        self.tests.insert(name.into(), tests::BoxedTest::new(f));
+        Arc::make_mut(&mut self.tests).insert(name.into(), tests::BoxedTest::new(f));
+    }
+
+    /// Registers an already registered test under an additional name.
+    ///
+    /// This works like [`add_filter_alias`](Self::add_filter_alias) but for
+    /// tests.
+    pub fn add_test_alias<N>(&mut self, name: N, existing: &str) -> Result<(), Error>
+    where
+        N: Into<Cow<'source, str>>,
+    {
+        let test = ok!(self.tests.get(existing).cloned().ok_or_else(|| {
+            Error::new(
+                ErrorKind::UnknownTest,
+                format!("cannot alias unknown test '{}'", existing),
+            )
+        }));
+        Arc::make_mut(&mut self.tests).insert(name.into(), test);
+        Ok(())
     }
 
     /// Removes a test by name.
     pub fn remove_test(&mut self, name: &str) {
-        self.tests.remove(name);
+        Arc::make_mut(&mut self.tests).remove(name);
     }
 
     /// Adds a new global function.
@@ -747,21 +1261,114 @@ impl<'source> Environment<'source> {
         self.add_global(name.into(), Value::from_function(f))
     }
 
+    /// Registers an already registered function under an additional name.
+    ///
+    /// This works like [`add_filter_alias`](Self::add_filter_alias) but for
+    /// functions.  As functions are stored as globals, any global can be
+    /// aliased this way.
+    pub fn add_function_alias<N>(&mut self, name: N, existing: &str) -> Result<(), Error>
+    where
+        N: Into<Cow<'source, str>>,
+    {
+        let func = ok!(self.globals.get(existing).cloned().ok_or_else(|| {
+            Error::new(
+                ErrorKind::UnknownFunction,
+                format!("cannot alias unknown function '{}'", existing),
+            )
+        }));
+        Arc::make_mut(&mut self.globals).insert(name.into(), func);
+        Ok(())
+    }
+
     /// Adds a global variable.
     pub fn add_global<N, V>(&mut self, name: N, value: V)
     where
         N: Into<Cow<'source, str>>,
         V: Into<Value>,
     {
-        self.globals.insert(name.into(), value.into());
+        Arc::make_mut(&mut self.globals).insert(name.into(), value.into());
     }
 
     /// Removes a global function or variable by name.
     pub fn remove_global(&mut self, name: &str) {
-        self.globals.remove(name);
+        Arc::make_mut(&mut self.globals).remove(name);
+    }
+
+    /// Returns an iterator over the names of all registered filters.
+    ///
+    /// This is useful for introspection, for instance to offer completions
+    /// in an editor or to review which filters are reachable from templates.
+    ///
+    /// ```
+    /// # use minijinja::Environment;
+    /// let env = Environment::new();
+    /// assert!(env.filters().any(|name| name == "upper"));
+    /// ```
+    pub fn filters(&self) -> impl Iterator<Item = &str> {
+        self.filters.keys().map(|x| x as &str)
+    }
+
+    /// Checks if a filter with the given name is registered.
+    pub fn has_filter(&self, name: &str) -> bool {
+        self.filters.contains_key(name)
+    }
+
+    /// Returns an iterator over the names of all registered tests.
+    pub fn tests(&self) -> impl Iterator<Item = &str> {
+        self.tests.keys().map(|x| x as &str)
+    }
+
+    /// Checks if a test with the given name is registered.
+    pub fn has_test(&self, name: &str) -> bool {
+        self.tests.contains_key(name)
+    }
+
+    /// Returns an iterator over all globals and their values.
+    ///
+    /// As functions are registered as globals, this also includes all
+    /// functions.
+    ///
+    /// ```
+    /// # use minijinja::Environment;
+    /// let env = Environment::new();
+    /// let functions = env
+    ///     .globals()
+    ///     .filter(|(_, value)| value.as_object().is_some())
+    ///     .map(|(name, _)| name)
+    ///     .collect::<Vec<_>>();
+    /// # assert!(functions.contains(&"range"));
+    /// ```
+    pub fn globals(&self) -> impl Iterator<Item = (&str, Value)> {
+        self.globals
+            .iter()
+            .map(|(name, value)| (name as &str, value.clone()))
+    }
+
+    /// Checks if a global (or function) with the given name is registered.
+    pub fn has_global(&self, name: &str) -> bool {
+        self.globals.contains_key(name)
     }
 
     /// Returns an empty [`State`] for testing purposes and similar.
+    ///
+    /// The state is detached from any template and can be used to invoke
+    /// callables outside of template rendering, for instance to unit test a
+    /// registered function or to call a method on an object.  Globals of the
+    /// environment can be looked up through it.
+    ///
+    /// ```
+    /// # use minijinja::{Environment, args};
+    /// let mut env = Environment::new();
+    /// env.add_function("add", |a: i64, b: i64| a + b);
+    /// let state = env.empty_state();
+    /// let add = state.lookup("add").unwrap();
+    /// assert_eq!(add.call(&state, args!(1, 2)).unwrap().as_i64(), Some(3));
+    /// ```
+    ///
+    /// Note that macros are bound to the state of the template that declared
+    /// them.  To call a macro after rendering use the state returned from
+    /// [`Template::render_and_return_state`] or [`Template::eval_to_state`]
+    /// instead.
     pub fn empty_state(&self) -> State<'_, '_> {
         State::new_for_env(self)
     }
@@ -796,11 +1403,26 @@ impl<'source> Environment<'source> {
         state: &State,
         out: &mut Output,
     ) -> Result<(), Error> {
-        if value.is_undefined() && matches!(self.undefined_behavior, UndefinedBehavior::Strict) {
-            Err(Error::from(ErrorKind::UndefinedError))
-        } else {
-            (self.formatter)(out, state, value)
+        ok!(state.undefined_behavior().assert_printable(value));
+        out.set_auto_escape(state.auto_escape());
+        (self.formatter)(out, state, value)
+    }
+
+    /// Writes a value with the given auto escaping into the output.
+    ///
+    /// Custom auto escape formats are dispatched to the registered escapers.
+    pub(crate) fn write_escaped(
+        &self,
+        out: &mut Output,
+        auto_escape: AutoEscape,
+        value: &Value,
+    ) -> Result<(), Error> {
+        if let AutoEscape::Custom(name) = auto_escape {
+            if let Some(escaper) = self.escapers.get(name) {
+                return write_with_custom_escaping(out, &**escaper, value);
+            }
         }
+        write_escaped(out, auto_escape, value)
     }
 
     /// Performs a template path join.
@@ -812,6 +1434,24 @@ impl<'source> Environment<'source> {
     }
 }
 
+fn join_relative_path<'s>(name: &'s str, parent: &'s str) -> Cow<'s, str> {
+    if !name.starts_with("./") && !name.starts_with("../") {
+        return Cow::Borrowed(name);
+    }
+    let mut rv = parent.split('/').collect::<Vec<_>>();
+    rv.pop();
+    for segment in name.split('/') {
+        match segment {
+            "." | "" => {}
+            ".." => {
+                rv.pop();
+            }
+            other => rv.push(other),
+        }
+    }
+    Cow::Owned(rv.join("/"))
+}
+
 #[cfg(not(feature = "loader"))]
 mod basic_store {
     use super::*;
@@ -819,12 +1459,12 @@ mod basic_store {
     #[derive(Clone)]
     pub(crate) struct BasicStore<'source> {
         pub template_config: TemplateConfig,
-        map: BTreeMap<&'source str, Arc<CompiledTemplate<'source>>>,
+        map: Arc<BTreeMap<&'source str, Arc<CompiledTemplate<'source>>>>,
     }
 
     impl fmt::Debug for BasicStore<'_> {
         fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-            BTreeMapKeysDebug(&self.map).fmt(f)
+            BTreeMapKeysDebug(&*self.map).fmt(f)
         }
     }
 
@@ -832,12 +1472,12 @@ mod basic_store {
         pub fn new(template_config: TemplateConfig) -> BasicStore<'source> {
             BasicStore {
                 template_config,
-                map: BTreeMap::default(),
+                map: Default::default(),
             }
         }
 
         pub fn insert(&mut self, name: &'source str, source: &'source str) -> Result<(), Error> {
-            self.map.insert(
+            Arc::make_mut(&mut self.map).insert(
                 name,
                 Arc::new(ok!(CompiledTemplate::new(
                     name,
@@ -849,11 +1489,11 @@ mod basic_store {
         }
 
         pub fn remove(&mut self, name: &str) {
-            self.map.remove(name);
+            Arc::make_mut(&mut self.map).remove(name);
         }
 
         pub fn clear(&mut self) {
-            self.map.clear();
+            Arc::make_mut(&mut self.map).clear();
         }
 
         pub fn get(&self, name: &str) -> Result<&CompiledTemplate<'source>, Error> {
@@ -139,6 +139,13 @@ pub enum ErrorKind {
     /// An unknown block was called
     #[cfg(feature = "multi_template")]
     UnknownBlock,
+    /// Access was denied by the attribute access policy.
+    SecurityError,
+    /// The configured recursion limit was exceeded.
+    RecursionLimitExceeded,
+    /// A compiled template could not be loaded.
+    #[cfg(feature = "bytecode")]
+    InvalidBytecode,
 }
 
 impl ErrorKind {
@@ -170,10 +177,65 @@ impl ErrorKind {
             ErrorKind::InvalidDelimiter => "invalid custom delimiters",
             #[cfg(feature = "multi_template")]
             ErrorKind::UnknownBlock => "unknown block",
+            ErrorKind::SecurityError => "security error",
+            ErrorKind::RecursionLimitExceeded => "recursion limit exceeded",
+            #[cfg(feature = "bytecode")]
+            ErrorKind::InvalidBytecode => "invalid compiled template",
         }
     }
 }
 
+/// Controls what [`Error::render_report`] renders.
+///
+/// All parts of the report are enabled by default.  The source snippet and
+/// the referenced variables are only available if the error carries debug
+/// information which requires the `debug` feature and debug mode to be
+/// enabled.
+#[derive(Debug, Clone, Copy)]
+pub struct ReportOptions {
+    pub(crate) source_snippet: bool,
+    pub(crate) line_numbers: bool,
+    pub(crate) referenced_variables: bool,
+    pub(crate) causes: bool,
+}
+
+impl Default for ReportOptions {
+    fn default() -> ReportOptions {
+        ReportOptions {
+            source_snippet: true,
+            line_numbers: true,
+            referenced_variables: true,
+            causes: true,
+        }
+    }
+}
+
+impl ReportOptions {
+    /// Include the snippet of the template source around the error.
+    pub fn with_source_snippet(mut self, yes: bool) -> ReportOptions {
+        self.source_snippet = yes;
+        self
+    }
+
+    /// Prefix the lines of the source snippet with line numbers.
+    pub fn with_line_numbers(mut self, yes: bool) -> ReportOptions {
+        self.line_numbers = yes;
+        self
+    }
+
+    /// Include the variables referenced by the failing template code.
+    pub fn with_referenced_variables(mut self, yes: bool) -> ReportOptions {
+        self.referenced_variables = yes;
+        self
+    }
+
+    /// Include the chain of errors that caused this error.
+    pub fn with_causes(mut self, yes: bool) -> ReportOptions {
+        self.causes = yes;
+        self
+    }
+}
+
 impl fmt::Display for ErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.description())
@@ -285,10 +347,13 @@ impl Error {
     /// used to better visualize where the error is coming from.  By indexing into
     /// the template source one ends up with the source of the failing expression.
     ///
-    /// Note that debug mode ([`Environment::set_debug`](crate::Environment::set_debug))
-    /// needs to be enabled, and the `debug` feature must be turned on.  The engine
-    /// usually keeps track of spans in all cases, but there is no absolute guarantee
-    /// that it is able to provide a range in all error cases.
+    /// The range is available whenever the engine recorded span information
+    /// for the failing instruction, which does not require the `debug` feature.
+    /// If the template source is not attached to the error (which requires debug
+    /// mode) it can also be retrieved with [`Template::source`](crate::Template::source).
+    /// There is however no absolute guarantee that a range can be provided in all
+    /// error cases.  For the range of the entire line see
+    /// [`Template::line_range`](crate::Template::line_range).
     ///
     /// ```
     /// # use minijinja::{Error, Environment, context};
@@ -299,8 +364,6 @@ impl Error {
     /// let src = err.template_source().unwrap();
     /// assert_eq!(&src[err.range().unwrap()], "foo + bar");
     /// ```
-    #[cfg(feature = "debug")]
-    #[cfg_attr(docsrs, doc(cfg(feature = "debug")))]
     pub fn range(&self) -> Option<std::ops::Range<usize>> {
         self.repr
             .span
@@ -328,6 +391,7 @@ impl Error {
                         self.0.line(),
                         self.0.span(),
                         info,
+                        &ReportOptions::default(),
                     )
                 } else {
                     Ok(())
@@ -338,6 +402,59 @@ impl Error {
         Proxy(self)
     }
 
+    /// Renders a report of the error into a writer.
+    ///
+    /// This is the programmatic version of the alternative formatting
+    /// (``format!("{:#}", err)``).  The [`ReportOptions`] control which parts
+    /// of the report are rendered.  By default the report contains the error
+    /// message, the source snippet with line numbers and the referenced
+    /// variables (both only if debug information is available) as well as the
+    /// chain of causes.
+    ///
+    /// ```
+    /// # use minijinja::{Environment, ReportOptions, context};
+    /// # let mut env = Environment::new();
+    /// # env.set_debug(true);
+    /// let tmpl = env.template_from_named_str("hello.txt", "Hello {{ foo + 1 }}!").unwrap();
+    /// let err = tmpl.render(context!(foo => "a string")).unwrap_err();
+    /// let mut report = String::new();
+    /// err.render_report(
+    ///     &mut report,
+    ///     ReportOptions::default().with_referenced_variables(false),
+    /// ).unwrap();
+    /// assert!(report.contains("   1 > Hello {{ foo + 1 }}!"));
+    /// assert!(!report.contains("Referenced variables"));
+    /// ```
+    pub fn render_report(&self, w: &mut dyn fmt::Write, options: ReportOptions) -> fmt::Result {
+        ok!(write!(w, "{}", self));
+        #[cfg(feature = "debug")]
+        {
+            if let Some(info) = self.debug_info() {
+                ok!(crate::debug::render_debug_info(
+                    w,
+                    self.name(),
+                    self.kind(),
+                    self.line(),
+                    self.span(),
+                    info,
+                    &options,
+                ));
+            }
+        }
+        if options.causes {
+            let mut err = self as &dyn std::error::Error;
+            while let Some(next_err) = err.source() {
+                ok!(write!(w, "\n\ncaused by: "));
+                match next_err.downcast_ref::<Error>() {
+                    Some(next_err) => ok!(next_err.render_report(w, options.with_causes(false))),
+                    None => ok!(write!(w, "{}", next_err)),
+                }
+                err = next_err;
+            }
+        }
+        Ok(())
+    }
+
     /// Returns the template source if available.
     #[cfg(feature = "debug")]
     #[cfg_attr(docsrs, doc(cfg(feature = "debug")))]
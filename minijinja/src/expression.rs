@@ -115,6 +115,7 @@ impl<'env, 'source> Expression<'env, 'source> {
             &BTreeMap::new(),
             &mut Output::null(),
             crate::AutoEscape::None,
+            self.env.undefined_behavior(),
         ))
         .0
         .expect("expression evaluation did not leave value on stack"))
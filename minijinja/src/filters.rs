@@ -115,7 +115,7 @@
 use std::sync::Arc;
 
 use crate::error::Error;
-use crate::utils::{write_escaped, SealedMarker};
+use crate::utils::SealedMarker;
 use crate::value::{ArgType, FunctionArgs, FunctionResult, Value};
 use crate::vm::State;
 use crate::{AutoEscape, Output};
@@ -231,6 +231,64 @@ tuple_impls! { A B C }
 tuple_impls! { A B C D }
 tuple_impls! { A B C D E }
 
+/// A utility trait that represents filters with access to shared data.
+///
+/// This trait is used by the
+/// [`add_filter_with_data`](crate::Environment::add_filter_with_data) method.
+/// It works like [`Filter`] but the function is passed a reference to the
+/// shared data as first parameter, followed by the regular filter arguments.
+/// This avoids having to clone an [`Arc`] into every closure that needs
+/// access to a shared configuration or lookup table.
+///
+/// ```
+/// # use minijinja::Environment;
+/// # let mut env = Environment::new();
+/// use std::collections::HashMap;
+/// use std::sync::Arc;
+///
+/// let labels = Arc::new(HashMap::from([("de", "German"), ("fr", "French")]));
+///
+/// fn language_label(labels: &HashMap<&str, &str>, code: &str) -> String {
+///     labels.get(code).copied().unwrap_or(code).to_string()
+/// }
+///
+/// env.add_filter_with_data("language_label", labels, language_label);
+/// ```
+///
+/// ```jinja
+/// {{ "de"|language_label }} -> German
+/// ```
+pub trait FilterWithData<D: ?Sized, Rv, Args>: Send + Sync + 'static {
+    /// Applies a filter to value with the given data and arguments.
+    #[doc(hidden)]
+    fn apply_with_data(&self, data: &D, args: Args, _: SealedMarker) -> Rv;
+}
+
+macro_rules! tuple_impls_with_data {
+    ( $( $name:ident )* ) => {
+        impl<Func, Data, Rv, $($name),*> FilterWithData<Data, Rv, ($($name,)*)> for Func
+        where
+            Func: Fn(&Data, $($name),*) -> Rv + Send + Sync + 'static,
+            Data: ?Sized,
+            Rv: FunctionResult,
+            $($name: for<'a> ArgType<'a>,)*
+        {
+            fn apply_with_data(&self, data: &Data, args: ($($name,)*), _: SealedMarker) -> Rv {
+                #[allow(non_snake_case)]
+                let ($($name,)*) = args;
+                (self)(data, $($name,)*)
+            }
+        }
+    };
+}
+
+tuple_impls_with_data! {}
+tuple_impls_with_data! { A }
+tuple_impls_with_data! { A B }
+tuple_impls_with_data! { A B C }
+tuple_impls_with_data! { A B C D }
+tuple_impls_with_data! { A B C D E }
+
 impl BoxedFilter {
     /// Creates a new boxed filter.
     pub fn new<F, Rv, Args>(f: F) -> BoxedFilter
@@ -245,6 +303,25 @@ impl BoxedFilter {
         }))
     }
 
+    /// Creates a new boxed filter that is passed shared data.
+    pub fn new_with_data<D, F, Rv, Args>(data: Arc<D>, f: F) -> BoxedFilter
+    where
+        D: Send + Sync + ?Sized + 'static,
+        F: FilterWithData<D, Rv, Args>
+            + for<'a> FilterWithData<D, Rv, <Args as FunctionArgs<'a>>::Output>,
+        Rv: FunctionResult,
+        Args: for<'a> FunctionArgs<'a>,
+    {
+        BoxedFilter(Arc::new(move |state, args| -> Result<Value, Error> {
+            f.apply_with_data(
+                &*data,
+                ok!(Args::from_values(Some(state), args)),
+                SealedMarker,
+            )
+            .into_result()
+        }))
+    }
+
     /// Applies the filter to a value and argument.
     pub fn apply_to(&self, state: &State, args: &[Value]) -> Result<Value, Error> {
         (self.0)(state, args)
@@ -254,6 +331,13 @@ impl BoxedFilter {
 /// Marks a value as safe.  This converts it into a string.
 ///
 /// When a value is marked as safe, no further auto escaping will take place.
+/// Marking an already safe value as safe again has no effect.
+///
+/// The safe flag belongs to the current string value.  Filters that produce
+/// a new string from it decide whether the flag carries over: filters that
+/// cannot introduce characters that need escaping (such as `upper`, `lower`
+/// or `trim`) preserve it, all others return an unsafe string.  The
+/// documentation of the individual filters states which behavior applies.
 pub fn safe(v: String) -> Value {
     Value::from_safe_string(v)
 }
@@ -284,7 +368,7 @@ pub fn escape(state: &State, v: &Value) -> Result<Value, Error> {
         None => String::new(),
     };
     let mut out = Output::with_string(&mut rv);
-    ok!(write_escaped(&mut out, auto_escape, v));
+    ok!(state.env().write_escaped(&mut out, auto_escape, v));
     Ok(Value::from_safe_string(rv))
 }
 
@@ -301,8 +385,25 @@ mod builtins {
     use std::fmt::Write;
     use std::mem;
 
+    /// Converts a value into a string the same way a string argument would.
+    fn str_arg(v: &Value) -> Result<Cow<'_, str>, Error> {
+        <Cow<'_, str> as ArgType>::from_value(Some(v))
+    }
+
+    /// Creates a new string that is safe if the original value was safe.
+    fn preserve_safety(original: &Value, rv: String) -> Value {
+        if original.is_safe() {
+            Value::from_safe_string(rv)
+        } else {
+            Value::from(rv)
+        }
+    }
+
     /// Converts a value to uppercase.
     ///
+    /// If the value was marked as safe, the result of the `upper` filter
+    /// stays safe.  This function itself always returns a plain string.
+    ///
     /// ```jinja
     /// <h1>{{ chapter.title|upper }}</h1>
     /// ```
@@ -311,8 +412,17 @@ mod builtins {
         v.to_uppercase()
     }
 
+    /// The `upper` filter which preserves the safe flag.
+    pub(crate) fn upper_keep_safe(v: &Value) -> Result<Value, Error> {
+        let s = ok!(str_arg(v));
+        Ok(preserve_safety(v, s.to_uppercase()))
+    }
+
     /// Converts a value to lowercase.
     ///
+    /// If the value was marked as safe, the result of the `lower` filter
+    /// stays safe.  This function itself always returns a plain string.
+    ///
     /// ```jinja
     /// <h1>{{ chapter.title|lower }}</h1>
     /// ```
@@ -321,8 +431,17 @@ mod builtins {
         v.to_lowercase()
     }
 
+    /// The `lower` filter which preserves the safe flag.
+    pub(crate) fn lower_keep_safe(v: &Value) -> Result<Value, Error> {
+        let s = ok!(str_arg(v));
+        Ok(preserve_safety(v, s.to_lowercase()))
+    }
+
     /// Converts a value to title case.
     ///
+    /// The result is never marked as safe as changing the case of the first
+    /// letter of a word can corrupt HTML entities.
+    ///
     /// ```jinja
     /// <h1>{{ chapter.title|title }}</h1>
     /// ```
@@ -347,6 +466,8 @@ mod builtins {
     /// Convert the string with all its characters lowercased
     /// apart from the first char which is uppercased.
     ///
+    /// The result is never marked as safe.
+    ///
     /// ```jinja
     /// <h1>{{ chapter.title|capitalize }}</h1>
     /// ```
@@ -362,10 +483,27 @@ mod builtins {
     /// Does a string replace.
     ///
     /// It replaces all occurrences of the first parameter with the second.
+    /// If the optional `count` parameter is given, only the first `count`
+    /// occurrences are replaced.  A `count` of zero replaces nothing, a
+    /// negative count replaces all occurrences.  Matches are found from left
+    /// to right and never overlap.
+    ///
+    /// If the `regex` feature is enabled, passing `regex=true` treats the
+    /// first parameter as a regular expression.  The replacement can then
+    /// refer to capture groups with `$1` or `${name}`.
+    ///
+    /// As the replacement can introduce arbitrary characters the result of
+    /// the `replace` filter is only marked as safe if both the value and the
+    /// replacement are safe.
+    ///
+    /// This function itself only supports replacing all occurrences and
+    /// always returns a plain string.
     ///
     /// ```jinja
     /// {{ "Hello World"|replace("Hello", "Goodbye") }}
     ///   -> Goodbye World
+    /// {{ "aaaa"|replace("a", "b", 2) }}
+    ///   -> bbaa
     /// ```
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
     pub fn replace(
@@ -377,6 +515,279 @@ mod builtins {
         v.replace(&from as &str, &to as &str)
     }
 
+    /// The `replace` filter with support for `count` and `regex`.
+    pub(crate) fn replace_kwargs(
+        v: &Value,
+        from: Cow<'_, str>,
+        to: &Value,
+        count: Option<i64>,
+        kwargs: Kwargs,
+    ) -> Result<Value, Error> {
+        let count = match count {
+            Some(count) => Some(count),
+            None => ok!(kwargs.get::<Option<i64>>("count")),
+        };
+        #[cfg(feature = "regex")]
+        let use_regex = ok!(kwargs.get::<Option<bool>>("regex")).unwrap_or(false);
+        ok!(kwargs.assert_all_used());
+
+        let s = ok!(str_arg(v));
+        let replacement = ok!(str_arg(to));
+        // a negative count means "replace everything" like a missing count
+        let count = count.and_then(|x| usize::try_from(x).ok());
+
+        #[cfg(feature = "regex")]
+        let rv = if use_regex {
+            let re = ok!(regex::Regex::new(&from).map_err(|err| {
+                Error::new(
+                    ErrorKind::InvalidOperation,
+                    format!("invalid regular expression: {err}"),
+                )
+            }));
+            match count {
+                Some(0) => s.to_string(),
+                Some(count) => re.replacen(&s, count, &replacement as &str).into_owned(),
+                None => re.replace_all(&s, &replacement as &str).into_owned(),
+            }
+        } else {
+            replace_str(&s, &from, &replacement, count)
+        };
+        #[cfg(not(feature = "regex"))]
+        let rv = replace_str(&s, &from, &replacement, count);
+
+        Ok(if to.is_safe() {
+            preserve_safety(v, rv)
+        } else {
+            Value::from(rv)
+        })
+    }
+
+    fn replace_str(s: &str, from: &str, to: &str, count: Option<usize>) -> String {
+        match count {
+            Some(count) => s.replacen(from, to, count),
+            None => s.replace(from, to),
+        }
+    }
+
+    /// Formats the arguments with a printf-style format string.
+    ///
+    /// The value is the format string, the arguments of the filter are
+    /// substituted for the placeholders in order.  The following conversions
+    /// are supported:
+    ///
+    /// * `%s`: the value converted to a string
+    /// * `%d` / `%i`: the value as integer (floats are truncated)
+    /// * `%f` / `%F`: the value as float, 6 digits after the decimal point
+    ///   unless a precision is given
+    /// * `%x` / `%X` / `%o`: the value as lower or upper case hexadecimal or
+    ///   octal integer
+    /// * `%%`: a literal percent sign
+    ///
+    /// Placeholders can have a width and a precision (`%5.2f`) and the flags
+    /// `-` (left align), `0` (pad with zeroes), `+` (always show the sign)
+    /// and ` ` (space in front of positive numbers).  For `%s` the precision
+    /// limits the number of characters.  It's an error if the number of
+    /// arguments does not match the number of placeholders.  The result is
+    /// never marked as safe.
+    ///
+    /// ```jinja
+    /// {{ "%s, %s!"|format("Hello", "World") }} -> Hello, World!
+    /// {{ "%05.1f%%"|format(42.25) }} -> 042.2%
+    /// {{ "#%06x"|format(4259839) }} -> #40ffff
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
+    pub fn format(format_str: &str, args: crate::value::Rest<Value>) -> Result<String, Error> {
+        let mut rv = String::with_capacity(format_str.len());
+        let mut args = args.iter();
+        let mut rest = format_str;
+
+        while let Some(pos) = rest.find('%') {
+            rv.push_str(&rest[..pos]);
+            let (spec, after) = ok!(parse_format_spec(&rest[pos + 1..]));
+            rest = after;
+            if spec.conversion == '%' {
+                rv.push('%');
+                continue;
+            }
+            let arg = ok!(args.next().ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidOperation,
+                    "not enough arguments for format string",
+                )
+            }));
+            ok!(spec.apply(&mut rv, arg));
+        }
+        rv.push_str(rest);
+
+        if args.next().is_some() {
+            return Err(Error::new(
+                ErrorKind::InvalidOperation,
+                "not all arguments converted during string formatting",
+            ));
+        }
+        Ok(rv)
+    }
+
+    struct FormatSpec {
+        left_align: bool,
+        zero_pad: bool,
+        sign: Option<char>,
+        width: usize,
+        precision: Option<usize>,
+        conversion: char,
+    }
+
+    fn parse_format_spec(s: &str) -> Result<(FormatSpec, &str), Error> {
+        let mut spec = FormatSpec {
+            left_align: false,
+            zero_pad: false,
+            sign: None,
+            width: 0,
+            precision: None,
+            conversion: '%',
+        };
+        let mut chars = s.char_indices().peekable();
+
+        while let Some(&(_, c)) = chars.peek() {
+            match c {
+                '-' => spec.left_align = true,
+                '0' => spec.zero_pad = true,
+                '+' => spec.sign = Some('+'),
+                ' ' => spec.sign = spec.sign.or(Some(' ')),
+                _ => break,
+            }
+            chars.next();
+        }
+
+        let read_number = |chars: &mut std::iter::Peekable<std::str::CharIndices>| {
+            let mut num = 0usize;
+            while let Some(digit) = chars.peek().and_then(|x| x.1.to_digit(10)) {
+                num = num.saturating_mul(10).saturating_add(digit as usize);
+                chars.next();
+            }
+            num
+        };
+        spec.width = read_number(&mut chars);
+        if let Some(&(_, '.')) = chars.peek() {
+            chars.next();
+            spec.precision = Some(read_number(&mut chars));
+        }
+
+        match chars.next() {
+            Some((idx, c @ ('s' | 'd' | 'i' | 'f' | 'F' | 'x' | 'X' | 'o' | '%'))) => {
+                spec.conversion = c;
+                Ok((spec, &s[idx + 1..]))
+            }
+            Some((_, c)) => Err(Error::new(
+                ErrorKind::InvalidOperation,
+                format!("unsupported format character {c:?}"),
+            )),
+            None => Err(Error::new(ErrorKind::InvalidOperation, "incomplete format")),
+        }
+    }
+
+    impl FormatSpec {
+        fn apply(&self, out: &mut String, arg: &Value) -> Result<(), Error> {
+            let numeric = self.conversion != 's';
+            let body = match self.conversion {
+                's' => {
+                    let s = arg.to_string();
+                    match self.precision {
+                        Some(precision) => s.chars().take(precision).collect(),
+                        None => s,
+                    }
+                }
+                'f' | 'F' => {
+                    let num = ok!(self.number_arg(arg).and_then(|x| {
+                        as_f64(x, true).ok_or_else(|| self.number_required(arg))
+                    }));
+                    let s = format!("{:.*}", self.precision.unwrap_or(6), num);
+                    self.with_sign(s, num.is_sign_negative())
+                }
+                _ => {
+                    let num = ok!(self.int_arg(arg));
+                    let digits = match self.conversion {
+                        'x' => format!("{:x}", num.unsigned_abs()),
+                        'X' => format!("{:X}", num.unsigned_abs()),
+                        'o' => format!("{:o}", num.unsigned_abs()),
+                        _ => num.unsigned_abs().to_string(),
+                    };
+                    self.with_sign(
+                        if num < 0 {
+                            format!("-{digits}")
+                        } else {
+                            digits
+                        },
+                        num < 0,
+                    )
+                }
+            };
+
+            let len = body.chars().count();
+            if len >= self.width {
+                out.push_str(&body);
+            } else if self.left_align {
+                out.push_str(&body);
+                out.extend(std::iter::repeat(' ').take(self.width - len));
+            } else if self.zero_pad && numeric {
+                let split = if body.starts_with(['-', '+', ' ']) {
+                    1
+                } else {
+                    0
+                };
+                out.push_str(&body[..split]);
+                out.extend(std::iter::repeat('0').take(self.width - len));
+                out.push_str(&body[split..]);
+            } else {
+                out.extend(std::iter::repeat(' ').take(self.width - len));
+                out.push_str(&body);
+            }
+            Ok(())
+        }
+
+        fn with_sign(&self, s: String, negative: bool) -> String {
+            match self.sign {
+                Some(sign) if !negative => format!("{sign}{s}"),
+                _ => s,
+            }
+        }
+
+        fn number_required(&self, arg: &Value) -> Error {
+            Error::new(
+                ErrorKind::InvalidOperation,
+                format!(
+                    "%{} format requires a number, not {}",
+                    self.conversion,
+                    arg.kind()
+                ),
+            )
+        }
+
+        fn number_arg<'a>(&self, arg: &'a Value) -> Result<&'a Value, Error> {
+            match arg.kind() {
+                ValueKind::Number | ValueKind::Bool => Ok(arg),
+                _ => Err(self.number_required(arg)),
+            }
+        }
+
+        fn int_arg(&self, arg: &Value) -> Result<i128, Error> {
+            let arg = ok!(self.number_arg(arg));
+            if arg.is_integer() || arg.kind() == ValueKind::Bool {
+                i128::try_from(arg.clone())
+            } else {
+                as_f64(arg, true)
+                    .filter(|x| x.is_finite())
+                    .map(|x| x.trunc() as i128)
+                    .ok_or_else(|| {
+                        Error::new(
+                            ErrorKind::InvalidOperation,
+                            "cannot convert non-finite float to integer",
+                        )
+                    })
+            }
+        }
+    }
+
     /// Returns the "length" of the value
     ///
     /// By default this filter is also registered under the alias `count`.
@@ -410,6 +821,24 @@ mod builtins {
         a.cmp(b)
     }
 
+    /// HTML escapes a value even if it was already marked as safe.
+    ///
+    /// Unlike [`escape`] which leaves safe values alone, this filter always
+    /// escapes.  Values that are not strings are converted to strings first.
+    /// The result is marked as safe so it will not be escaped again.
+    ///
+    /// ```jinja
+    /// {{ "<br>"|safe|forceescape }} -> &lt;br&gt;
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
+    pub fn forceescape(v: &Value) -> Value {
+        let s = match v.as_str() {
+            Some(s) => Cow::Borrowed(s),
+            None => Cow::Owned(v.to_string()),
+        };
+        Value::from_safe_string(crate::utils::HtmlEscape(&s).to_string())
+    }
+
     /// Dict sorting functionality.
     ///
     /// This filter works like `|items` but sorts the pairs by key first.
@@ -456,6 +885,10 @@ mod builtins {
     /// It's generally better to use `|dictsort` which sorts the map by
     /// key before iterating.
     ///
+    /// This works for all map-like values, including dynamic objects with a
+    /// map representation, and does not require the `pycompat` `.items()`
+    /// method.  Values that are not maps are rejected with an error.
+    ///
     /// ```jinja
     /// <dl>
     /// {% for key, value in my_dict|items %}
@@ -484,11 +917,42 @@ mod builtins {
         } else {
             Err(Error::new(
                 ErrorKind::InvalidOperation,
-                "cannot convert value into pairs",
+                format!("cannot convert {} into pairs", v.kind()),
             ))
         }
     }
 
+    /// Combines one or more maps into a new map.
+    ///
+    /// Keys of later maps override keys of earlier maps.  By default nested
+    /// maps are replaced, pass `recursive=true` to merge them deeply instead.
+    /// This is useful to layer configuration overrides on top of defaults.
+    /// All values need to be maps.
+    ///
+    /// ```jinja
+    /// {% set config = defaults|combine(overrides, recursive=true) %}
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
+    pub fn combine(
+        value: &Value,
+        others: crate::value::Rest<Value>,
+        kwargs: Kwargs,
+    ) -> Result<Value, Error> {
+        let recursive = ok!(kwargs.get::<Option<bool>>("recursive")).unwrap_or(false);
+        ok!(kwargs.assert_all_used());
+        if value.kind() != ValueKind::Map {
+            return Err(Error::new(
+                ErrorKind::InvalidOperation,
+                format!("cannot merge value of type {}", value.kind()),
+            ));
+        }
+        let mut rv = value.clone();
+        for other in others.iter() {
+            rv = ok!(rv.merge(other, recursive));
+        }
+        Ok(rv)
+    }
+
     /// Reverses an iterable or string
     ///
     /// ```jinja
@@ -502,6 +966,20 @@ mod builtins {
     }
 
     /// Trims a value
+    ///
+    /// Without an argument whitespace is removed from both ends of the
+    /// string.  Optionally a string of characters can be passed in which
+    /// case all of these characters are stripped instead, similar to
+    /// Python's `str.strip`.
+    ///
+    /// If the value was marked as safe, the result of the `trim` filter stays
+    /// safe when trimming whitespace.  Trimming custom characters returns an
+    /// unsafe string as it could cut through an escape sequence.  This
+    /// function itself always returns a plain string.
+    ///
+    /// ```jinja
+    /// {{ "/docs/install/"|trim("/") }} -> docs/install
+    /// ```
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
     pub fn trim(s: Cow<'_, str>, chars: Option<Cow<'_, str>>) -> String {
         match chars {
@@ -513,6 +991,57 @@ mod builtins {
         }
     }
 
+    /// The `trim` filter which preserves the safe flag.
+    pub(crate) fn trim_keep_safe(v: &Value, chars: Option<Cow<'_, str>>) -> Result<Value, Error> {
+        trim_impl(v, chars, str::trim, |s, chars| s.trim_matches(chars))
+    }
+
+    /// Trims a value from the start only.
+    ///
+    /// This works like [`trim`] but only strips characters from the start
+    /// of the string.
+    ///
+    /// ```jinja
+    /// {{ "/docs/install/"|lstrip("/") }} -> docs/install/
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
+    pub fn lstrip(v: &Value, chars: Option<Cow<'_, str>>) -> Result<Value, Error> {
+        trim_impl(v, chars, str::trim_start, |s, chars| {
+            s.trim_start_matches(chars)
+        })
+    }
+
+    /// Trims a value from the end only.
+    ///
+    /// This works like [`trim`] but only strips characters from the end
+    /// of the string.
+    ///
+    /// ```jinja
+    /// {{ "/docs/install/"|rstrip("/") }} -> /docs/install
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
+    pub fn rstrip(v: &Value, chars: Option<Cow<'_, str>>) -> Result<Value, Error> {
+        trim_impl(v, chars, str::trim_end, |s, chars| {
+            s.trim_end_matches(chars)
+        })
+    }
+
+    fn trim_impl(
+        v: &Value,
+        chars: Option<Cow<'_, str>>,
+        trim_ws: fn(&str) -> &str,
+        trim_chars: for<'a> fn(&'a str, &[char]) -> &'a str,
+    ) -> Result<Value, Error> {
+        let s = ok!(str_arg(v));
+        Ok(match chars {
+            Some(chars) => {
+                let chars = chars.chars().collect::<Vec<_>>();
+                Value::from(trim_chars(&s, &chars[..]))
+            }
+            None => preserve_safety(v, trim_ws(&s).to_string()),
+        })
+    }
+
     /// Joins a sequence by a character
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
     pub fn join(val: &Value, joiner: Option<Cow<'_, str>>) -> Result<String, Error> {
@@ -630,11 +1159,60 @@ mod builtins {
 
     /// Converts a value into an integer.
     ///
+    /// Strings are parsed as integers.  In base 10 parsing falls back to
+    /// parsing them as floats which are then truncated.  The filter accepts
+    /// two optional arguments which can also be passed as keyword arguments:
+    ///
+    /// * `default`: the value to return if the conversion fails.  If no default
+    ///   is provided, a failed conversion is an error.
+    /// * `base`: the base to parse strings in (defaults to `10`).  For bases 16,
+    ///   8 and 2 the prefixes `0x`, `0o` and `0b` are accepted.  A base of `0`
+    ///   picks the base from the prefix.
+    ///
+    /// Surrounding whitespace is ignored, thousands separators are not supported.
+    /// This function itself does not accept the optional arguments and always
+    /// parses in base 10.
+    ///
     /// ```jinja
     /// {{ "42"|int == 42 }} -> true
+    /// {{ "0x1f"|int(base=16) }} -> 31
+    /// {{ "0b101"|int(base=0) }} -> 5
+    /// {{ "1,000"|int(default=-1) }} -> -1
     /// ```
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
     pub fn int(value: &Value) -> Result<Value, Error> {
+        int_with_base(value, 10)
+    }
+
+    /// The `int` filter with support for `default` and `base`.
+    pub(crate) fn int_kwargs(
+        value: &Value,
+        default: Option<Value>,
+        base: Option<u32>,
+        kwargs: Kwargs,
+    ) -> Result<Value, Error> {
+        let default = match default {
+            Some(default) => Some(default),
+            None => ok!(kwargs.get("default")),
+        };
+        let base = match base {
+            Some(base) => base,
+            None => ok!(kwargs.get::<Option<u32>>("base")).unwrap_or(10),
+        };
+        ok!(kwargs.assert_all_used());
+        if base == 1 || base > 36 {
+            return Err(Error::new(
+                ErrorKind::InvalidOperation,
+                "int base must be 0 or between 2 and 36",
+            ));
+        }
+        match (int_with_base(value, base), default) {
+            (Err(_), Some(default)) if value.kind() != ValueKind::Invalid => Ok(default),
+            (rv, _) => rv,
+        }
+    }
+
+    fn int_with_base(value: &Value, base: u32) -> Result<Value, Error> {
         match &value.0 {
             ValueRepr::Undefined | ValueRepr::None => Ok(Value::from(0)),
             ValueRepr::Bool(x) => Ok(Value::from(*x as u64)),
@@ -643,14 +1221,19 @@ mod builtins {
             }
             ValueRepr::F64(v) => Ok(Value::from(*v as i128)),
             ValueRepr::String(..) | ValueRepr::SmallStr(_) => {
-                let s = value.as_str().unwrap();
-                if let Ok(i) = s.parse::<i128>() {
+                let s = value.as_str().unwrap().trim();
+                if let Some(i) = parse_int(s, base) {
                     Ok(Value::from(i))
-                } else {
+                } else if base == 10 {
                     match s.parse::<f64>() {
                         Ok(f) => Ok(Value::from(f as i128)),
                         Err(err) => Err(Error::new(ErrorKind::InvalidOperation, err.to_string())),
                     }
+                } else {
+                    Err(Error::new(
+                        ErrorKind::InvalidOperation,
+                        format!("invalid digit for integer of base {base}"),
+                    ))
                 }
             }
             ValueRepr::Bytes(_) | ValueRepr::Object(_) => Err(Error::new(
@@ -661,10 +1244,38 @@ mod builtins {
         }
     }
 
+    fn parse_int(s: &str, base: u32) -> Option<i128> {
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let prefix = s.get(..2).map(|x| x.to_ascii_lowercase());
+        let (radix, digits) = match (base, prefix.as_deref()) {
+            (0 | 16, Some("0x")) => (16, &s[2..]),
+            (0 | 8, Some("0o")) => (8, &s[2..]),
+            (0 | 2, Some("0b")) => (2, &s[2..]),
+            (0, _) => (10, s),
+            (base, _) => (base, s),
+        };
+        if digits.starts_with(['+', '-']) {
+            return None;
+        }
+        let rv = i128::from_str_radix(digits, radix).ok()?;
+        Some(if negative { -rv } else { rv })
+    }
+
     /// Converts a value into a float.
     ///
+    /// Strings are parsed as floats, which includes the exponent notation.
+    /// Surrounding whitespace is ignored, thousands separators are not supported.
+    /// If the conversion fails the optional `default` argument is returned.  If
+    /// no default is provided, a failed conversion is an error.  This function
+    /// itself does not accept a default.
+    ///
     /// ```jinja
     /// {{ "42.5"|float == 42.5 }} -> true
+    /// {{ "1.5e3"|float }} -> 1500.0
+    /// {{ "n/a"|float(default=0.0) }} -> 0.0
     /// ```
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
     pub fn float(value: &Value) -> Result<Value, Error> {
@@ -674,6 +1285,7 @@ mod builtins {
             ValueRepr::String(..) | ValueRepr::SmallStr(_) => value
                 .as_str()
                 .unwrap()
+                .trim()
                 .parse::<f64>()
                 .map(Value::from)
                 .map_err(|err| Error::new(ErrorKind::InvalidOperation, err.to_string())),
@@ -687,16 +1299,70 @@ mod builtins {
         }
     }
 
+    /// The `float` filter with support for `default`.
+    pub(crate) fn float_kwargs(
+        value: &Value,
+        default: Option<Value>,
+        kwargs: Kwargs,
+    ) -> Result<Value, Error> {
+        let default = match default {
+            Some(default) => Some(default),
+            None => ok!(kwargs.get("default")),
+        };
+        ok!(kwargs.assert_all_used());
+        match (float(value), default) {
+            (Err(_), Some(default)) if value.kind() != ValueKind::Invalid => Ok(default),
+            (rv, _) => rv,
+        }
+    }
+
     /// Sums up all the values in a sequence.
     ///
+    /// The filter accepts a few keyword arguments:
+    ///
+    /// * `attribute`: sums up the given attribute of the items instead (dotted
+    ///   paths are supported).
+    /// * `start`: the value to start summing from.  Defaults to `0` which is
+    ///   also what is returned for an empty sequence.
+    ///
+    /// Integers and floats can be mixed in which case the result is a float.
+    /// This function itself does not accept keyword arguments.
+    ///
     /// ```jinja
     /// {{ range(10)|sum }} -> 45
+    /// {{ orders|sum(attribute="total", start=10) }}
     /// ```
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
     pub fn sum(state: &State, values: Value) -> Result<Value, Error> {
-        let mut rv = Value::from(0);
+        sum_values(state, values, None, Value::from(0))
+    }
+
+    /// The `sum` filter with support for keyword arguments.
+    pub(crate) fn sum_kwargs(state: &State, values: Value, kwargs: Kwargs) -> Result<Value, Error> {
+        let attr = ok!(kwargs.get::<Option<&str>>("attribute"));
+        let start = ok!(kwargs.get::<Option<Value>>("start")).unwrap_or(Value::from(0));
+        ok!(kwargs.assert_all_used());
+        if !start.is_number() {
+            return Err(Error::new(
+                ErrorKind::InvalidOperation,
+                format!("can only sum numbers, got {} as start value", start.kind()),
+            ));
+        }
+        sum_values(state, values, attr, start)
+    }
+
+    fn sum_values(
+        state: &State,
+        values: Value,
+        attr: Option<&str>,
+        mut rv: Value,
+    ) -> Result<Value, Error> {
         let iter = ok!(state.undefined_behavior().try_iter(values));
         for value in iter {
+            let value = match attr {
+                Some(attr) => ok!(value.get_path_checked(attr)),
+                None => value,
+            };
             if value.is_undefined() {
                 ok!(state.undefined_behavior().handle_undefined(false));
                 continue;
@@ -714,47 +1380,164 @@ mod builtins {
 
     /// Looks up an attribute.
     ///
-    /// In MiniJinja this is the same as the `[]` operator.  In Jinja2 there is a
-    /// small difference which is why this filter is sometimes used in Jinja2
-    /// templates.  For compatibility it's provided here as well.
+    /// Unlike the `[]` operator a string name only performs an attribute
+    /// lookup and is never used as an index into a sequence.  Like in Jinja2
+    /// a missing attribute results in an undefined value.  Other keys such
+    /// as integers are looked up like with the `[]` operator.
     ///
     /// ```jinja
     /// {{ value['key'] == value|attr('key') }} -> true
+    /// {{ [1, 2, 3]|attr('0') }} -> undefined
+    /// {{ [1, 2, 3]|attr(0) }} -> 1
     /// ```
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
     pub fn attr(value: &Value, key: &Value) -> Result<Value, Error> {
-        value.get_item(key)
+        match key.as_str() {
+            Some(name) => value.get_attr(name),
+            None => value.get_item(key),
+        }
     }
 
     /// Round the number to a given precision.
     ///
-    /// Round the number to a given precision. The first parameter specifies the
-    /// precision (default is 0).
+    /// The first parameter specifies the precision (default is 0) in decimal
+    /// places.  A negative precision rounds to tens, hundreds etc.  The second
+    /// parameter specifies the rounding method:
+    ///
+    /// * `common`: rounds to the nearest value.  Like in Python (and Jinja2)
+    ///   ties are rounded to the nearest even value.
+    /// * `ceil`: always rounds up.
+    /// * `floor`: always rounds down.
+    ///
+    /// Both parameters can also be passed as keyword arguments.  Like in Jinja2
+    /// the result is a float, except for integers rounded with the `common`
+    /// method which stay integers.  This function itself only supports the
+    /// `common` method.
     ///
     /// ```jinja
     /// {{ 42.55|round }}
     ///   -> 43.0
+    /// {{ 42.55|round(1, 'floor') }}
+    ///   -> 42.5
+    /// {{ 1250|round(-2) }}
+    ///   -> 1200
     /// ```
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
     pub fn round(value: Value, precision: Option<i32>) -> Result<Value, Error> {
-        match value.0 {
-            ValueRepr::I64(_) | ValueRepr::I128(_) | ValueRepr::U64(_) | ValueRepr::U128(_) => {
-                Ok(value)
+        round_with_method(value, precision.unwrap_or(0), "common")
+    }
+
+    /// The `round` filter with support for `method` and keyword arguments.
+    pub(crate) fn round_kwargs(
+        value: Value,
+        precision: Option<i32>,
+        method: Option<&str>,
+        kwargs: Kwargs,
+    ) -> Result<Value, Error> {
+        let precision = match precision {
+            Some(precision) => precision,
+            None => ok!(kwargs.get::<Option<i32>>("precision")).unwrap_or(0),
+        };
+        let method = match method {
+            Some(method) => method,
+            None => ok!(kwargs.get::<Option<&str>>("method")).unwrap_or("common"),
+        };
+        ok!(kwargs.assert_all_used());
+        round_with_method(value, precision, method)
+    }
+
+    fn round_with_method(value: Value, precision: i32, method: &str) -> Result<Value, Error> {
+        let round_fn: fn(f64) -> f64 = match method {
+            "common" => round_half_even,
+            "ceil" => f64::ceil,
+            "floor" => f64::floor,
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::InvalidOperation,
+                    "method must be common, ceil or floor",
+                ))
             }
-            ValueRepr::F64(val) => {
-                let x = 10f64.powi(precision.unwrap_or(0));
-                Ok(Value::from((x * val).round() / x))
+        };
+
+        let val = match value.0 {
+            ValueRepr::I64(_) | ValueRepr::I128(_) | ValueRepr::U64(_) | ValueRepr::U128(_)
+                if method == "common" =>
+            {
+                return if precision >= 0 {
+                    Ok(value)
+                } else {
+                    round_int(value, precision.unsigned_abs())
+                };
             }
-            _ => Err(Error::new(
-                ErrorKind::InvalidOperation,
-                format!("cannot round value ({})", value.kind()),
-            )),
+            ValueRepr::I64(_)
+            | ValueRepr::I128(_)
+            | ValueRepr::U64(_)
+            | ValueRepr::U128(_)
+            | ValueRepr::F64(_) => as_f64(&value, true).unwrap_or_default(),
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::InvalidOperation,
+                    format!("cannot round value ({})", value.kind()),
+                ))
+            }
+        };
+
+        let rv = if precision >= 0 {
+            let x = 10f64.powi(precision);
+            if (val * x).abs() >= 9007199254740992.0 {
+                // no fractional digits left to round at this precision
+                val
+            } else if method == "common" {
+                // formatting rounds the exact decimal value half to even
+                // which is what Python does too.
+                format!("{:.*}", precision as usize, val)
+                    .parse()
+                    .unwrap_or(val)
+            } else {
+                round_fn(val * x) / x
+            }
+        } else {
+            // scale by dividing for negative precisions to not introduce
+            // errors by multiplying with an inexact fraction.
+            let x = 10f64.powi(-precision);
+            round_fn(val / x) * x
+        };
+        Ok(Value::from(if rv.is_finite() { rv } else { val }))
+    }
+
+    fn round_half_even(val: f64) -> f64 {
+        if (val - val.trunc()).abs() == 0.5 {
+            (val / 2.0).round() * 2.0
+        } else {
+            val.round()
         }
     }
 
+    fn round_int(value: Value, digits: u32) -> Result<Value, Error> {
+        let v = ok!(i128::try_from(value));
+        let factor = match 10i128.checked_pow(digits) {
+            Some(factor) => factor,
+            None => return Ok(Value::from(0)),
+        };
+        let (quotient, remainder) = (v.div_euclid(factor), v.rem_euclid(factor));
+        let quotient = match (remainder * 2).cmp(&factor) {
+            std::cmp::Ordering::Less => quotient,
+            std::cmp::Ordering::Greater => quotient + 1,
+            std::cmp::Ordering::Equal => quotient + quotient.rem_euclid(2),
+        };
+        quotient
+            .checked_mul(factor)
+            .map(Value::from)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidOperation, "overflow on round"))
+    }
+
     /// Returns the first item from an iterable.
     ///
-    /// If the list is empty `undefined` is returned.
+    /// This works with any iterable value.  Lazy iterables are only advanced
+    /// by a single item.  For maps the first key is returned, to get the first
+    /// key and value pair use `map|items|first`.  For strings the first
+    /// character is returned.  If the iterable is empty `undefined` is
+    /// returned.
     ///
     /// ```jinja
     /// <dl>
@@ -765,20 +1548,25 @@ mod builtins {
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
     pub fn first(value: &Value) -> Result<Value, Error> {
         if let Some(s) = value.as_str() {
-            Ok(s.chars().next().map_or(Value::UNDEFINED, Value::from))
-        } else if let Some(mut iter) = value.as_object().and_then(|x| x.try_iter()) {
-            Ok(iter.next().unwrap_or(Value::UNDEFINED))
-        } else {
-            Err(Error::new(
-                ErrorKind::InvalidOperation,
-                "cannot get first item from value",
-            ))
+            return Ok(s.chars().next().map_or(Value::UNDEFINED, Value::from));
         }
+        let mut iter = ok!(iter_for_first_last(value, "first"));
+        Ok(iter.next().unwrap_or(Value::UNDEFINED))
+    }
+
+    /// The `first` filter which respects the undefined behavior.
+    pub(crate) fn first_with_state(state: &State, value: &Value) -> Result<Value, Error> {
+        ok!(state.undefined_behavior().assert_iterable(value));
+        first(value)
     }
 
     /// Returns the last item from an iterable.
     ///
-    /// If the list is empty `undefined` is returned.
+    /// This works with any iterable value.  Sequences look up the last item
+    /// by index, other iterables have to be iterated to the end.  For maps
+    /// the last key is returned, to get the last key and value pair use
+    /// `map|items|last`.  For strings the last character is returned.  If
+    /// the iterable is empty `undefined` is returned.
     ///
     /// ```jinja
     /// <h2>Most Recent Update</h2>
@@ -794,23 +1582,87 @@ mod builtins {
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
     pub fn last(value: Value) -> Result<Value, Error> {
         if let Some(s) = value.as_str() {
-            Ok(s.chars().next_back().map_or(Value::UNDEFINED, Value::from))
-        } else if matches!(value.kind(), ValueKind::Seq | ValueKind::Iterable) {
-            let rev = ok!(value.reverse());
-            let mut iter = ok!(rev.try_iter());
-            Ok(iter.next().unwrap_or_default())
-        } else {
-            Err(Error::new(
+            return Ok(s.chars().next_back().map_or(Value::UNDEFINED, Value::from));
+        }
+        if value.kind() == ValueKind::Seq {
+            if let Some(len) = value.len() {
+                return Ok(match len.checked_sub(1) {
+                    Some(idx) => ok!(value.get_item_by_index(idx)),
+                    None => Value::UNDEFINED,
+                });
+            }
+        }
+        let iter = ok!(iter_for_first_last(&value, "last"));
+        Ok(iter.last().unwrap_or(Value::UNDEFINED))
+    }
+
+    /// The `last` filter which respects the undefined behavior.
+    pub(crate) fn last_with_state(state: &State, value: Value) -> Result<Value, Error> {
+        ok!(state.undefined_behavior().assert_iterable(&value));
+        last(value)
+    }
+
+    fn iter_for_first_last(value: &Value, which: &str) -> Result<crate::value::ValueIter, Error> {
+        value.try_iter().map_err(|err| {
+            Error::new(
                 ErrorKind::InvalidOperation,
-                "cannot get last item from value",
-            ))
+                format!(
+                    "cannot get {} item from value of type {}",
+                    which,
+                    value.kind()
+                ),
+            )
+            .with_source(err)
+        })
+    }
+
+    fn min_max(
+        state: &State,
+        value: Value,
+        kwargs: Kwargs,
+        wanted: Ordering,
+    ) -> Result<Value, Error> {
+        let iter = ok!(state.undefined_behavior().try_iter(value).map_err(|err| {
+            Error::new(ErrorKind::InvalidOperation, "cannot convert value to list").with_source(err)
+        }));
+        let case_sensitive = ok!(kwargs.get::<Option<bool>>("case_sensitive")).unwrap_or(false);
+        let attr = ok!(kwargs.get::<Option<&str>>("attribute"));
+        ok!(kwargs.assert_all_used());
+        let key_of = |item: &Value| match attr {
+            Some(attr) => item.get_path_checked(attr).unwrap_or(Value::UNDEFINED),
+            None => item.clone(),
+        };
+        let mut rv: Option<(Value, Value)> = None;
+        for item in iter {
+            let key = key_of(&item);
+            // ties keep the item that was encountered first
+            if let Some((_, ref best_key)) = rv {
+                if cmp_helper(&key, best_key, case_sensitive) != wanted {
+                    continue;
+                }
+            }
+            rv = Some((item, key));
         }
+        Ok(rv.map_or(Value::UNDEFINED, |x| x.0))
     }
 
     /// Returns the smallest item from an iterable.
     ///
+    /// The filter accepts a few keyword arguments:
+    ///
+    /// * `case_sensitive`: set to `true` to make the comparison of strings case sensitive.
+    /// * `attribute`: compare by the given attribute (dotted paths are supported)
+    ///   and return the item with the smallest attribute.
+    ///
+    /// If the iterable is empty, `undefined` is returned.  If multiple items are
+    /// equally small, the first one is returned.
+    ///
+    /// This function itself does not accept keyword arguments and always
+    /// compares case sensitively.
+    ///
     /// ```jinja
     /// {{ [1, 2, 3, 4]|min }} -> 1
+    /// {{ users|min(attribute="age") }}
     /// ```
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
     pub fn min(state: &State, value: Value) -> Result<Value, Error> {
@@ -820,10 +1672,23 @@ mod builtins {
         Ok(iter.min().unwrap_or(Value::UNDEFINED))
     }
 
+    /// The `min` filter with support for keyword arguments.
+    pub(crate) fn min_kwargs(state: &State, value: Value, kwargs: Kwargs) -> Result<Value, Error> {
+        min_max(state, value, kwargs, Ordering::Less)
+    }
+
     /// Returns the largest item from an iterable.
     ///
+    /// This accepts the same keyword arguments as [`min`](crate::filters::min).
+    /// If multiple items are equally large, the first one is returned.
+    ///
+    /// This function itself does not accept keyword arguments, always
+    /// compares case sensitively and returns the last of multiple equally
+    /// large items.
+    ///
     /// ```jinja
     /// {{ [1, 2, 3, 4]|max }} -> 4
+    /// {{ users|max(attribute="age") }}
     /// ```
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
     pub fn max(state: &State, value: Value) -> Result<Value, Error> {
@@ -833,20 +1698,32 @@ mod builtins {
         Ok(iter.max().unwrap_or(Value::UNDEFINED))
     }
 
+    /// The `max` filter with support for keyword arguments.
+    pub(crate) fn max_kwargs(state: &State, value: Value, kwargs: Kwargs) -> Result<Value, Error> {
+        min_max(state, value, kwargs, Ordering::Greater)
+    }
+
     /// Returns the sorted version of the given list.
     ///
     /// The filter accepts a few keyword arguments:
     ///
     /// * `case_sensitive`: set to `true` to make the sorting of strings case sensitive.
-    /// * `attribute`: can be set to an attribute or dotted path to sort by that attribute
+    /// * `attribute`: can be set to an attribute or dotted path to sort by that attribute.
+    ///   Items where the attribute is missing sort as if it was undefined.
     /// * `reverse`: set to `true` to sort in reverse.
     ///
+    /// The sort is stable: items that compare equal keep their relative order,
+    /// also when sorting in reverse.  Values of different types are ordered by
+    /// type first: undefined, none, bools, numbers, strings, bytes, sequences,
+    /// maps and other objects.  Values of the same type are then compared by value.
+    ///
     /// ```jinja
-    /// {{ [1, 3, 2, 4]|sort }} -> [4, 3, 2, 1]
-    /// {{ [1, 3, 2, 4]|sort(reverse=true) }} -> [1, 2, 3, 4]
-    /// # Sort users by age attribute in descending order.
-    /// {{ users|sort(attribute="age") }}
+    /// {{ [1, 3, 2, 4]|sort }} -> [1, 2, 3, 4]
+    /// {{ [1, 3, 2, 4]|sort(reverse=true) }} -> [4, 3, 2, 1]
+    /// {{ ["a", 2, none, true]|sort }} -> [none, true, 2, "a"]
     /// # Sort users by age attribute in ascending order.
+    /// {{ users|sort(attribute="age") }}
+    /// # Sort users by age attribute in descending order.
     /// {{ users|sort(attribute="age", reverse=true) }}
     /// ```
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
@@ -856,16 +1733,24 @@ mod builtins {
         }))
         .collect::<Vec<_>>();
         let case_sensitive = ok!(kwargs.get::<Option<bool>>("case_sensitive")).unwrap_or(false);
+        let reverse = ok!(kwargs.get::<Option<bool>>("reverse")).unwrap_or(false);
+        let cmp = |a: &Value, b: &Value| {
+            let rv = cmp_helper(a, b, case_sensitive);
+            if reverse {
+                rv.reverse()
+            } else {
+                rv
+            }
+        };
         if let Some(attr) = ok!(kwargs.get::<Option<&str>>("attribute")) {
-            items.sort_by(|a, b| match (a.get_path(attr), b.get_path(attr)) {
-                (Ok(a), Ok(b)) => cmp_helper(&a, &b, case_sensitive),
-                _ => Ordering::Equal,
+            items.sort_by(|a, b| {
+                cmp(
+                    &a.get_path_or_default(attr, &Value::UNDEFINED),
+                    &b.get_path_or_default(attr, &Value::UNDEFINED),
+                )
             });
         } else {
-            items.sort_by(|a, b| cmp_helper(a, b, case_sensitive))
-        }
-        if let Some(true) = ok!(kwargs.get("reverse")) {
-            items.reverse();
+            items.sort_by(cmp)
         }
         ok!(kwargs.assert_all_used());
         Ok(Value::from(items))
@@ -1048,6 +1933,18 @@ mod builtins {
     ///   const GLOBAL_CONFIG = {{ global_config|tojson(indent=2) }};
     /// </script>
     /// ```
+    ///
+    /// When generating JSON that is not embedded in HTML the escaping of the
+    /// `<`, `>`, `&` and `'` characters can be disabled by passing
+    /// `escape_html=false`.  The result is standard JSON.  Note that this is
+    /// only safe if the output is never placed into an HTML document as it then
+    /// becomes possible to break out of a `<script>` tag or an attribute.  For
+    /// that reason the resulting value is not marked as safe and auto escaping
+    /// still applies to it.
+    ///
+    /// ```jinja
+    /// {{ config|tojson(indent=2, escape_html=false) }}
+    /// ```
     #[cfg_attr(docsrs, doc(cfg(all(feature = "builtins", feature = "json"))))]
     #[cfg(feature = "json")]
     pub fn tojson(value: &Value, indent: Option<Value>, args: Kwargs) -> Result<Value, Error> {
@@ -1063,6 +1960,7 @@ mod builtins {
                 None => Some(ok!(usize::try_from(val.clone()))),
             },
         };
+        let escape_html = ok!(args.get::<Option<bool>>("escape_html")).unwrap_or(true);
         ok!(args.assert_all_used());
         if let Some(indent) = indent {
             let mut out = Vec::<u8>::new();
@@ -1078,6 +1976,9 @@ mod builtins {
             Error::new(ErrorKind::InvalidOperation, "cannot serialize to JSON").with_source(err)
         })
         .map(|s| {
+            if !escape_html {
+                return Value::from(s);
+            }
             // When this filter is used the return value is safe for both HTML and JSON
             let mut rv = String::with_capacity(s.len());
             for c in s.chars() {
@@ -1146,6 +2047,110 @@ mod builtins {
         output
     }
 
+    /// Wraps a string to the given width.
+    ///
+    /// This is a basic implementation that measures line width in characters
+    /// and only breaks on whitespace and hyphens.  Existing newlines in the
+    /// input are retained.  For unicode aware wrapping the `wordwrap` filter
+    /// from `minijinja-contrib` can be used instead, which replaces this one
+    /// when registered.
+    ///
+    /// **Keyword arguments:**
+    ///
+    /// - `width`: Maximum length of wrapped lines (default: 79)
+    /// - `break_long_words`: If a word is longer than width, break it across lines (default: true)
+    /// - `break_on_hyphens`: If a word contains hyphens, it may be split across lines (default: true)
+    /// - `wrapstring`: String to join each wrapped line (default: newline)
+    ///
+    /// ```jinja
+    /// {{ long_text|wordwrap(width=40) }}
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(all(feature = "builtins"))))]
+    pub fn wordwrap(value: &Value, kwargs: Kwargs) -> Result<String, Error> {
+        let s = value.as_str().unwrap_or_default();
+        let width = ok!(kwargs.get::<Option<usize>>("width")).unwrap_or(79);
+        let break_long_words = ok!(kwargs.get::<Option<bool>>("break_long_words")).unwrap_or(true);
+        let break_on_hyphens = ok!(kwargs.get::<Option<bool>>("break_on_hyphens")).unwrap_or(true);
+        let wrapstring = ok!(kwargs.get::<Option<&str>>("wrapstring")).unwrap_or("\n");
+        ok!(kwargs.assert_all_used());
+        let width = width.max(1);
+
+        if s.trim().is_empty() {
+            return Ok(String::new());
+        }
+
+        let mut rv = String::new();
+        for (idx, paragraph) in s.lines().enumerate() {
+            if idx > 0 {
+                rv.push_str(wrapstring);
+            }
+
+            let mut lines = Vec::new();
+            let mut line = String::new();
+            let mut line_len = 0;
+            for word in paragraph.split_whitespace() {
+                let mut pieces = Vec::new();
+                if break_on_hyphens {
+                    let mut rest = word;
+                    while let Some(pos) = rest
+                        .char_indices()
+                        .skip(1)
+                        .find(|&(pos, c)| c == '-' && pos + 1 < rest.len())
+                        .map(|(pos, _)| pos + 1)
+                    {
+                        pieces.push(&rest[..pos]);
+                        rest = &rest[pos..];
+                    }
+                    pieces.push(rest);
+                } else {
+                    pieces.push(word);
+                }
+
+                for (piece_idx, piece) in pieces.into_iter().enumerate() {
+                    let piece_len = piece.chars().count();
+                    let sep = if piece_idx == 0 && line_len > 0 { 1 } else { 0 };
+                    if line_len + sep + piece_len <= width {
+                        if sep > 0 {
+                            line.push(' ');
+                        }
+                        line.push_str(piece);
+                        line_len += sep + piece_len;
+                        continue;
+                    }
+
+                    if line_len > 0 {
+                        lines.push(mem::take(&mut line));
+                    }
+                    let mut piece = piece;
+                    let mut piece_len = piece_len;
+                    if break_long_words {
+                        while piece_len > width {
+                            let split =
+                                piece.char_indices().nth(width).map_or(piece.len(), |x| x.0);
+                            lines.push(piece[..split].to_string());
+                            piece = &piece[split..];
+                            piece_len -= width;
+                        }
+                    }
+                    line.push_str(piece);
+                    line_len = piece_len;
+                }
+            }
+            if line_len > 0 {
+                lines.push(line);
+            }
+
+            for (line_idx, line) in lines.iter().enumerate() {
+                if line_idx > 0 {
+                    rv.push_str(wrapstring);
+                }
+                rv.push_str(line);
+            }
+        }
+
+        Ok(rv)
+    }
+
     /// URL encodes a value.
     ///
     /// If given a map it encodes the parameters into a query set, otherwise it
@@ -1155,6 +2160,16 @@ mod builtins {
     /// ```jinja
     /// <a href="/search?{{ {"q": "my search", "lang": "fr"}|urlencode }}">Search</a>
     /// ```
+    ///
+    /// A sequence of `(key, value)` pairs is encoded into a query string as
+    /// well which is useful to control the order or to repeat keys.  Keys are
+    /// also repeated if the value is a sequence.  Parameters with a value of
+    /// none or undefined are skipped.
+    ///
+    /// ```jinja
+    /// {{ {"tag": ["a", "b"], "page": 2}|urlencode }} -> tag=a&tag=b&page=2
+    /// {{ [("q", "x y"), ("q", "z")]|urlencode }} -> q=x%20y&q=z
+    /// ```
     #[cfg_attr(docsrs, doc(cfg(all(feature = "builtins", feature = "urlencode"))))]
     #[cfg(feature = "urlencode")]
     pub fn urlencode(value: &Value) -> Result<String, Error> {
@@ -1165,34 +2180,64 @@ mod builtins {
             .remove(b'_')
             .add(b' ');
 
-        if value.kind() == ValueKind::Map {
-            let mut rv = String::new();
-            for k in ok!(value.try_iter()) {
-                let v = ok!(value.get_item(&k));
-                if v.is_none() || v.is_undefined() {
-                    continue;
+        fn push_param(rv: &mut String, k: &Value, v: &Value) -> Result<(), Error> {
+            if v.is_none() || v.is_undefined() {
+                return Ok(());
+            }
+            if matches!(v.kind(), ValueKind::Seq | ValueKind::Iterable) {
+                for item in ok!(v.try_iter()) {
+                    ok!(push_param(rv, k, &item));
                 }
-                if !rv.is_empty() {
-                    rv.push('&');
+                return Ok(());
+            }
+            if !rv.is_empty() {
+                rv.push('&');
+            }
+            write!(
+                rv,
+                "{}={}",
+                percent_encoding::utf8_percent_encode(&k.to_string(), SET),
+                percent_encoding::utf8_percent_encode(&v.to_string(), SET)
+            )
+            .unwrap();
+            Ok(())
+        }
+
+        match value.kind() {
+            ValueKind::Map => {
+                let mut rv = String::new();
+                for k in ok!(value.try_iter()) {
+                    let v = ok!(value.get_item(&k));
+                    ok!(push_param(&mut rv, &k, &v));
                 }
-                write!(
-                    rv,
-                    "{}={}",
-                    percent_encoding::utf8_percent_encode(&k.to_string(), SET),
-                    percent_encoding::utf8_percent_encode(&v.to_string(), SET)
-                )
-                .unwrap();
+                Ok(rv)
             }
-            Ok(rv)
-        } else {
-            match &value.0 {
+            ValueKind::Seq | ValueKind::Iterable => {
+                let mut rv = String::new();
+                for pair in ok!(value.try_iter()) {
+                    if pair.kind() != ValueKind::Seq || pair.len() != Some(2) {
+                        return Err(Error::new(
+                            ErrorKind::InvalidOperation,
+                            format!(
+                                "urlencode expects a sequence of key-value pairs, got {}",
+                                pair.kind()
+                            ),
+                        ));
+                    }
+                    let k = ok!(pair.get_item_by_index(0));
+                    let v = ok!(pair.get_item_by_index(1));
+                    ok!(push_param(&mut rv, &k, &v));
+                }
+                Ok(rv)
+            }
+            _ => match &value.0 {
                 ValueRepr::None | ValueRepr::Undefined => Ok("".into()),
                 ValueRepr::Bytes(b) => Ok(percent_encoding::percent_encode(b, SET).to_string()),
                 ValueRepr::String(..) | ValueRepr::SmallStr(_) => Ok(
                     percent_encoding::utf8_percent_encode(value.as_str().unwrap(), SET).to_string(),
                 ),
                 _ => Ok(percent_encoding::utf8_percent_encode(&value.to_string(), SET).to_string()),
-            }
+            },
         }
     }
 
@@ -1206,16 +2251,18 @@ mod builtins {
     ) -> Result<Vec<Value>, Error> {
         let mut rv = vec![];
         let test = if let Some(test_name) = test_name {
-            Some(ok!(state
-                .env
-                .get_test(&test_name)
-                .ok_or_else(|| Error::from(ErrorKind::UnknownTest))))
+            Some(ok!(state.env.get_test(&test_name).ok_or_else(|| {
+                Error::new(
+                    ErrorKind::UnknownTest,
+                    format!("test {test_name} is unknown"),
+                )
+            })))
         } else {
             None
         };
         for value in ok!(state.undefined_behavior().try_iter(value)) {
             let test_value = if let Some(ref attr) = attr {
-                ok!(value.get_path(attr))
+                value.get_path(attr)
             } else {
                 value.clone()
             };
@@ -1241,10 +2288,13 @@ mod builtins {
     /// Only values that pass the test are included.
     ///
     /// If no test is specified, each object will be evaluated as a boolean.
+    /// Undefined, none, `false`, zero as well as empty strings, sequences and
+    /// maps are considered false.  This is useful to drop empty values:
     ///
     /// ```jinja
     /// {{ [1, 2, 3, 4]|select("odd") }} -> [1, 3]
     /// {{ [false, null, 42]|select }} -> [42]
+    /// {{ ["a", "", "b"]|select|join(",") }} -> a,b
     /// ```
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
     pub fn select(
@@ -1259,12 +2309,17 @@ mod builtins {
     /// Creates a new sequence of values of which an attribute passes a test.
     ///
     /// This functions like [`select`] but it will test an attribute of the
-    /// object itself:
+    /// object itself.  The attribute can be a dotted path (`"user.age"`) and
+    /// any registered test can be used by name.  Extra arguments are passed
+    /// to the test which makes the comparison tests useful here:
     ///
     /// ```jinja
     /// {{ users|selectattr("is_active") }} -> all users where x.is_active is true
     /// {{ users|selectattr("id", "even") }} -> returns all users with an even id
+    /// {{ users|selectattr("age", "ge", 18) }} -> all users that are 18 or older
     /// ```
+    ///
+    /// A missing attribute is treated as undefined.
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
     pub fn selectattr(
         state: &State,
@@ -1278,7 +2333,12 @@ mod builtins {
 
     /// Creates a new sequence of values that don't pass a test.
     ///
-    /// This is the inverse of [`select`].
+    /// This is the inverse of [`select`].  If no test is specified, only the
+    /// values that evaluate to false are kept:
+    ///
+    /// ```jinja
+    /// {{ [false, none, 42]|reject }} -> [false, none]
+    /// ```
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
     pub fn reject(
         state: &State,
@@ -1357,7 +2417,7 @@ mod builtins {
             };
             for value in ok!(state.undefined_behavior().try_iter(value)) {
                 let sub_val = match attr.as_str() {
-                    Some(path) => value.get_path(path),
+                    Some(path) => value.get_path_checked(path),
                     None => value.get_item(&attr),
                 };
                 rv.push(match (sub_val, &default) {
@@ -1419,8 +2479,10 @@ mod builtins {
     /// {% endfor %}</ul>
     /// ```
     ///
-    /// groupby yields named tuples of `(grouper, list)``, which can be used instead
-    /// of the tuple unpacking above.  As such this example is equivalent:
+    /// groupby yields named tuples of `(grouper, list)`, which can be used instead
+    /// of the tuple unpacking above.  Like in Jinja2 the group can also be indexed
+    /// (`group[0]` and `group[1]`) and `list` is a regular list which supports
+    /// indexing, `length` and friends.  As such this example is equivalent:
     ///
     /// ```jinja
     /// <ul>{% for group in users|groupby(attribute="city") %}
@@ -1462,10 +2524,11 @@ mod builtins {
         });
         ok!(kwargs.assert_all_used());
 
+        /// Works like a named tuple of `(grouper, list)`.
         #[derive(Debug)]
         pub struct GroupTuple {
             grouper: Value,
-            list: Vec<Value>,
+            list: Value,
         }
 
         impl Object for GroupTuple {
@@ -1476,12 +2539,7 @@ mod builtins {
             fn get_value(self: &Arc<Self>, key: &Value) -> Option<Value> {
                 match (key.as_usize(), key.as_str()) {
                     (Some(0), None) | (None, Some("grouper")) => Some(self.grouper.clone()),
-                    (Some(1), None) | (None, Some("list")) => {
-                        Some(Value::make_object_iterable(self.clone(), |this| {
-                            Box::new(this.list.iter().cloned())
-                                as Box<dyn Iterator<Item = _> + Send + Sync>
-                        }))
-                    }
+                    (Some(1), None) | (None, Some("list")) => Some(self.list.clone()),
                     _ => None,
                 }
             }
@@ -1501,7 +2559,7 @@ mod builtins {
                 if cmp_helper(last_grouper, &group_by, case_sensitive) != Ordering::Equal {
                     rv.push(Value::from_object(GroupTuple {
                         grouper: last_grouper.clone(),
-                        list: std::mem::take(&mut list),
+                        list: Value::from(std::mem::take(&mut list)),
                     }));
                 }
             }
@@ -1512,7 +2570,7 @@ mod builtins {
         if !list.is_empty() {
             rv.push(Value::from_object(GroupTuple {
                 grouper: grouper.unwrap(),
-                list,
+                list: Value::from(list),
             }));
         }
 
@@ -1580,6 +2638,13 @@ mod builtins {
     /// Pretty print a variable.
     ///
     /// This is useful for debugging as it better shows what's inside an object.
+    /// Maps and sequences (including dynamic objects that can be enumerated)
+    /// are printed with one item per line.  The output is a plain string and
+    /// is thus escaped when auto escaping is enabled.
+    ///
+    /// ```jinja
+    /// <pre>{{ user|pprint }}</pre>
+    /// ```
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
     pub fn pprint(value: &Value) -> String {
         format!("{:#?}", value)
@@ -96,6 +96,7 @@ env.add_function("is_adult", is_adult);
 //! this module.  Note though that these functions are not to be
 //! called from Rust code as their exact interface (arguments and return types)
 //! might change from one MiniJinja version to another.
+use std::borrow::Cow;
 use std::fmt;
 use std::sync::Arc;
 
@@ -108,7 +109,87 @@ type FuncFunc = dyn Fn(&State, &[Value]) -> Result<Value, Error> + Sync + Send +
 
 /// A boxed function.
 #[derive(Clone)]
-pub(crate) struct BoxedFunction(Arc<FuncFunc>, #[cfg(feature = "debug")] &'static str);
+pub(crate) struct BoxedFunction(
+    Arc<FuncFunc>,
+    Option<Arc<FunctionSignature>>,
+    #[cfg(feature = "debug")] &'static str,
+);
+
+/// Describes the arguments a function accepts.
+///
+/// Functions created with [`Value::from_function`] are opaque and do not
+/// know about their arguments.  For the benefit of tooling and debugging a
+/// signature can be attached with
+/// [`Value::from_function_with_signature`] which can later be retrieved with
+/// [`Value::function_signature`].  The signature is purely informational and
+/// is not used to validate arguments.
+///
+/// ```
+/// # use minijinja::value::Value;
+/// use minijinja::functions::FunctionSignature;
+///
+/// let func = Value::from_function_with_signature(
+///     |a: u32, b: Option<u32>| a + b.unwrap_or(1),
+///     FunctionSignature::new(["a", "b"]),
+/// );
+/// let sig = func.function_signature().unwrap();
+/// assert_eq!(sig.args().collect::<Vec<_>>(), ["a", "b"]);
+/// assert_eq!(sig.to_string(), "(a, b)");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FunctionSignature {
+    args: Vec<Cow<'static, str>>,
+    kwargs: bool,
+}
+
+impl FunctionSignature {
+    /// Creates a signature from the names of the positional arguments.
+    pub fn new<I, A>(args: I) -> FunctionSignature
+    where
+        I: IntoIterator<Item = A>,
+        A: Into<Cow<'static, str>>,
+    {
+        FunctionSignature {
+            args: args.into_iter().map(Into::into).collect(),
+            kwargs: false,
+        }
+    }
+
+    /// Marks the function as accepting keyword arguments.
+    pub fn with_kwargs(mut self, yes: bool) -> FunctionSignature {
+        self.kwargs = yes;
+        self
+    }
+
+    /// Returns the names of the positional arguments.
+    pub fn args(&self) -> impl Iterator<Item = &str> {
+        self.args.iter().map(|x| x.as_ref())
+    }
+
+    /// Returns `true` if the function accepts keyword arguments.
+    pub fn takes_kwargs(&self) -> bool {
+        self.kwargs
+    }
+}
+
+impl fmt::Display for FunctionSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        ok!(f.write_str("("));
+        for (idx, arg) in self.args().enumerate() {
+            if idx > 0 {
+                ok!(f.write_str(", "));
+            }
+            ok!(f.write_str(arg));
+        }
+        if self.kwargs {
+            if !self.args.is_empty() {
+                ok!(f.write_str(", "));
+            }
+            ok!(f.write_str("**kwargs"));
+        }
+        f.write_str(")")
+    }
+}
 
 /// A utility trait that represents global functions.
 ///
@@ -219,11 +300,23 @@ impl BoxedFunction {
                 f.invoke(ok!(Args::from_values(Some(state), args)), SealedMarker)
                     .into_result()
             }),
+            None,
             #[cfg(feature = "debug")]
             std::any::type_name::<F>(),
         )
     }
 
+    /// Attaches a signature to the function.
+    pub fn with_signature(mut self, signature: FunctionSignature) -> BoxedFunction {
+        self.1 = Some(Arc::new(signature));
+        self
+    }
+
+    /// Returns the signature if one was attached.
+    pub fn signature(&self) -> Option<&FunctionSignature> {
+        self.1.as_deref()
+    }
+
     /// Invokes the function.
     pub fn invoke(&self, state: &State, args: &[Value]) -> Result<Value, Error> {
         (self.0)(state, args)
@@ -238,12 +331,17 @@ impl BoxedFunction {
 impl fmt::Debug for BoxedFunction {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         #[cfg(feature = "debug")]
-        {
-            if !self.1.is_empty() {
-                return f.write_str(self.1);
-            }
+        let name = if self.2.is_empty() {
+            "function"
+        } else {
+            self.2
+        };
+        #[cfg(not(feature = "debug"))]
+        let name = "function";
+        match self.signature() {
+            Some(signature) => write!(f, "{name}{signature}"),
+            None => f.write_str(name),
         }
-        f.write_str("function")
     }
 }
 
@@ -262,7 +360,7 @@ mod builtins {
     use super::*;
 
     use crate::error::ErrorKind;
-    use crate::value::{Rest, ValueMap, ValueRepr};
+    use crate::value::{DebugDump, Rest, ValueMap, ValueRepr};
 
     /// Returns a range.
     ///
@@ -279,7 +377,15 @@ mod builtins {
     /// </ul>
     /// ```
     ///
-    /// This function will refuse to create ranges over 10.000 items.
+    /// If any of the arguments is a float, a range of floats is produced
+    /// instead.  In that case the step may also be negative.  To avoid the
+    /// accumulation of rounding errors each item is calculated as
+    /// `start + index * step`.  For instance `range(0, 1, 0.25)` yields
+    /// `0.0`, `0.25`, `0.5` and `0.75`.
+    ///
+    /// This function will refuse to create ranges over 10.000 items.  The
+    /// `range` function of the environment also accepts floats while this
+    /// function only creates integer ranges.
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
     pub fn range(lower: u32, upper: Option<u32>, step: Option<u32>) -> Result<Value, Error> {
         fn to_result<I: ExactSizeIterator<Item = u32> + Send + Sync + Clone + 'static>(
@@ -313,6 +419,148 @@ mod builtins {
         }
     }
 
+    /// The `range` function which also accepts floats.
+    pub(crate) fn range_any(
+        lower: Value,
+        upper: Option<Value>,
+        step: Option<Value>,
+    ) -> Result<Value, Error> {
+        let is_float = |x: &Value| x.is_number() && !x.is_integer();
+        if is_float(&lower)
+            || upper.as_ref().map_or(false, is_float)
+            || step.as_ref().map_or(false, is_float)
+        {
+            return float_range(lower, upper, step);
+        }
+        range(
+            ok!(u32::try_from(lower)),
+            match upper {
+                Some(upper) => Some(ok!(u32::try_from(upper))),
+                None => None,
+            },
+            match step {
+                Some(step) => Some(ok!(u32::try_from(step))),
+                None => None,
+            },
+        )
+    }
+
+    fn float_range(
+        lower: Value,
+        upper: Option<Value>,
+        step: Option<Value>,
+    ) -> Result<Value, Error> {
+        let lower = ok!(f64::try_from(lower));
+        let (start, stop) = match upper {
+            Some(upper) => (lower, ok!(f64::try_from(upper))),
+            None => (0.0, lower),
+        };
+        let step = match step {
+            Some(step) => ok!(f64::try_from(step)),
+            None => 1.0,
+        };
+        if step == 0.0 {
+            return Err(Error::new(
+                ErrorKind::InvalidOperation,
+                "cannot create range with step of 0",
+            ));
+        }
+        let len = ((stop - start) / step).ceil();
+        if len > 100000.0 {
+            return Err(Error::new(
+                ErrorKind::InvalidOperation,
+                "range has too many elements",
+            ));
+        }
+        // NaN and negative lengths produce empty ranges
+        let len = if len > 0.0 { len as usize } else { 0 };
+        Ok(Value::make_iterable(move || {
+            (0..len).map(move |idx| start + idx as f64 * step)
+        }))
+    }
+
+    /// Iterates over multiple iterables in parallel.
+    ///
+    /// Returns an iterable of tuples where the n-th tuple contains the n-th
+    /// item of every argument.  Iteration stops as soon as the shortest
+    /// iterable is exhausted.  With no arguments the result is empty.
+    ///
+    /// ```jinja
+    /// <dl>
+    /// {% for name, value in zip(names, values) %}
+    ///   <dt>{{ name }}<dd>{{ value }}
+    /// {% endfor %}
+    /// </dl>
+    /// ```
+    ///
+    /// The items are produced lazily from the arguments.
+    #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
+    pub fn zip(iterables: Rest<Value>) -> Result<Value, Error> {
+        // make sure all arguments can be iterated over before producing
+        // the lazy iterable so that errors are reported early.
+        for iterable in iterables.iter() {
+            ok!(iterable.try_iter());
+        }
+        let iterables = iterables.0;
+        Ok(Value::make_iterable(move || {
+            let mut iters = iterables
+                .iter()
+                .filter_map(|x| x.try_iter().ok())
+                .collect::<Vec<_>>();
+            std::iter::from_fn(move || {
+                if iters.is_empty() {
+                    return None;
+                }
+                iters
+                    .iter_mut()
+                    .map(|x| x.next())
+                    .collect::<Option<Vec<_>>>()
+                    .map(Value::from)
+            })
+        }))
+    }
+
+    /// Iterates over an iterable together with the index of each item.
+    ///
+    /// Returns an iterable of `(index, item)` tuples.  The index starts at
+    /// `0` unless a different `start` value is given either as second
+    /// argument or as keyword argument.  This is useful when an index is
+    /// needed outside of a `for` loop where `loop.index0` would not be
+    /// available.
+    ///
+    /// ```jinja
+    /// {% for idx, item in enumerate(items, start=1) %}
+    ///   {{ idx }}. {{ item }}
+    /// {% endfor %}
+    /// ```
+    ///
+    /// The items are produced lazily from the argument.  Once the index
+    /// reaches the largest representable integer it stays there.
+    #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
+    pub fn enumerate(
+        iterable: Value,
+        start: Option<i64>,
+        kwargs: crate::value::Kwargs,
+    ) -> Result<Value, Error> {
+        let start = match start {
+            Some(start) => start,
+            None => ok!(kwargs.get::<Option<i64>>("start")).unwrap_or(0),
+        };
+        ok!(kwargs.assert_all_used());
+        ok!(iterable.try_iter());
+        Ok(Value::make_iterable(move || {
+            iterable
+                .try_iter()
+                .into_iter()
+                .flatten()
+                .scan(start, |idx, item| {
+                    let rv = Value::from(vec![Value::from(*idx), item]);
+                    *idx = idx.saturating_add(1);
+                    Some(rv)
+                })
+        }))
+    }
+
     /// Creates a dictionary.
     ///
     /// This is a convenient alternative for a dictionary literal.
@@ -331,6 +579,13 @@ mod builtins {
     /// ```jinja
     /// {% set new_dict = dict(old_dict, extra_value=2) %}
     /// ```
+    ///
+    /// Instead of a map, a sequence of key/value pairs can be passed.  Each
+    /// pair must have exactly two items and the keys must be strings:
+    ///
+    /// ```jinja
+    /// {% set d = dict([["a", 1], ["b", 2]]) %}
+    /// ```
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
     pub fn dict(value: Option<Value>, update_with: crate::value::Kwargs) -> Result<Value, Error> {
         let mut rv = match value {
@@ -340,6 +595,11 @@ mod builtins {
                 ValueRepr::Object(obj) if obj.repr() == ObjectRepr::Map => {
                     obj.try_iter_pairs().into_iter().flatten().collect()
                 }
+                ValueRepr::Object(ref obj)
+                    if matches!(obj.repr(), ObjectRepr::Seq | ObjectRepr::Iterable) =>
+                {
+                    ok!(dict_from_pairs(&value))
+                }
                 _ => return Err(Error::from(ErrorKind::InvalidOperation)),
             },
         };
@@ -356,6 +616,38 @@ mod builtins {
         Ok(Value::from_object(rv))
     }
 
+    fn dict_from_pairs(pairs: &Value) -> Result<ValueMap, Error> {
+        let mut rv = ValueMap::default();
+        for pair in ok!(pairs.try_iter()) {
+            let mut items = ok!(pair.try_iter().map_err(|_| {
+                Error::new(
+                    ErrorKind::InvalidOperation,
+                    format!("dict pairs must be sequences, got {}", pair.kind()),
+                )
+            }))
+            .collect::<Vec<_>>();
+            if items.len() != 2 {
+                return Err(Error::new(
+                    ErrorKind::InvalidOperation,
+                    format!(
+                        "dict pairs must have exactly two items, got {}",
+                        items.len()
+                    ),
+                ));
+            }
+            let value = items.pop().unwrap();
+            let key = items.pop().unwrap();
+            if key.as_str().is_none() {
+                return Err(Error::new(
+                    ErrorKind::InvalidOperation,
+                    format!("dict keys must be strings, got {}", key.kind()),
+                ));
+            }
+            rv.insert(key, value);
+        }
+        Ok(rv)
+    }
+
     /// Outputs the current context or the arguments stringified.
     ///
     /// This is a useful function to quickly figure out the state of affairs
@@ -364,6 +656,10 @@ mod builtins {
     /// and auto escaping setting.  The exact output is not defined and might
     /// change from one version of Jinja2 to the next.
     ///
+    /// Dynamic objects are shown by enumerating them and looking up their
+    /// values, maps that cannot be enumerated are marked as such and very
+    /// large sequences are truncated with an ellipsis.
+    ///
     /// ```jinja
     /// <pre>{{ debug() }}</pre>
     /// <pre>{{ debug(variable1, variable2) }}</pre>
@@ -373,9 +669,10 @@ mod builtins {
         if args.is_empty() {
             format!("{state:#?}")
         } else if args.len() == 1 {
-            format!("{:#?}", args.0[0])
+            format!("{:#?}", DebugDump(&args.0[0]))
         } else {
-            format!("{:#?}", &args.0[..])
+            let dumps = args.iter().map(DebugDump).collect::<Vec<_>>();
+            format!("{dumps:#?}")
         }
     }
 
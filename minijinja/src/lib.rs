@@ -55,7 +55,8 @@
 //!
 //! For super trivial cases where you need to render a string once, you can
 //! also use the [`render!`] macro which acts a bit like a replacement
-//! for the [`format!`] macro.
+//! for the [`format!`] macro.  The [`try_render!`] macro works the same but
+//! returns a [`Result`] instead of panicking.
 //!
 //! # Expression Usage
 //!
@@ -170,6 +171,9 @@
 //! - `fuel`: enables the `fuel` feature which makes the engine track fuel consumption which
 //!   can be used to better protect against expensive templates.
 //! - `loader`: enables owned and dynamic template loading of templates.
+//! - `bytecode`: enables serializing compiled templates with
+//!   [`Template::to_compiled_bytes`] and loading them again with
+//!   [`Environment::add_compiled_template`].
 //! - `custom_syntax`: when this feature is enabled, custom delimiters are supported by
 //!   the parser.
 //! - `preserve_order`: When enable the internal value implementation uses an indexmap
@@ -177,6 +181,8 @@
 //! - `json`: When enabled the `tojson` filter is added as builtin filter as well as
 //!   the ability to auto escape via `AutoEscape::Json`.
 //! - `urlencode`: When enabled the `urlencode` filter is added as builtin filter.
+//! - `regex`: When enabled the `replace` filter accepts `regex=true` to replace by a
+//!   regular expression.
 //! - `loop_controls`: enables the `{% break %}` and `{% continue %}` loop control flow
 //!    tags.
 //!
@@ -234,17 +240,17 @@ mod debug;
 
 pub use self::defaults::{default_auto_escape_callback, escape_formatter};
 pub use self::environment::Environment;
-pub use self::error::{Error, ErrorKind};
+pub use self::error::{Error, ErrorKind, ReportOptions};
 pub use self::expression::Expression;
 pub use self::output::Output;
-pub use self::template::Template;
-pub use self::utils::{AutoEscape, HtmlEscape, UndefinedBehavior};
+pub use self::template::{Template, TemplateComplexity};
+pub use self::utils::{AutoEscape, HtmlEscape, UndefinedBehavior, WhitespacePolicy};
 
 /// Re-export for convenience.
 pub use self::value::Value;
 
 pub use self::macros::__context;
-pub use self::vm::State;
+pub use self::vm::{State, TraceEvent, TraceEventKind, Warning};
 
 // forwards compatibility
 #[cfg(not(feature = "serde"))]
@@ -5,7 +5,7 @@ use std::fs;
 use std::io;
 use std::path::Path;
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use memo_map::MemoMap;
 use self_cell::self_cell;
@@ -28,7 +28,7 @@ pub(crate) struct LoaderStore<'source> {
     pub template_config: TemplateConfig,
     loader: Option<Arc<LoadFunc>>,
     owned_templates: MemoMap<Arc<str>, Arc<LoadedTemplate>>,
-    borrowed_templates: BTreeMap<&'source str, Arc<CompiledTemplate<'source>>>,
+    borrowed_templates: Arc<BTreeMap<&'source str, Arc<CompiledTemplate<'source>>>>,
 }
 
 impl fmt::Debug for LoaderStore<'_> {
@@ -47,7 +47,7 @@ impl fmt::Debug for LoaderStore<'_> {
 }
 
 self_cell! {
-    struct LoadedTemplate {
+    pub(crate) struct LoadedTemplate {
         owner: (Arc<str>, Box<str>),
         #[covariant]
         dependent: CompiledTemplate,
@@ -74,7 +74,7 @@ impl<'source> LoaderStore<'source> {
             template_config,
             loader: None,
             owned_templates: MemoMap::default(),
-            borrowed_templates: BTreeMap::default(),
+            borrowed_templates: Default::default(),
         }
     }
 
@@ -90,7 +90,7 @@ impl<'source> LoaderStore<'source> {
         match (source, name) {
             (Cow::Borrowed(source), Cow::Borrowed(name)) => {
                 self.owned_templates.remove(name);
-                self.borrowed_templates.insert(
+                Arc::make_mut(&mut self.borrowed_templates).insert(
                     name,
                     Arc::new(ok!(CompiledTemplate::new(
                         name,
@@ -100,7 +100,7 @@ impl<'source> LoaderStore<'source> {
                 );
             }
             (source, name) => {
-                self.borrowed_templates.remove(&name as &str);
+                Arc::make_mut(&mut self.borrowed_templates).remove(&name as &str);
                 let name: Arc<str> = name.into();
                 self.owned_templates.replace(
                     name.clone(),
@@ -112,13 +112,26 @@ impl<'source> LoaderStore<'source> {
         Ok(())
     }
 
+    #[cfg(feature = "bytecode")]
+    pub fn insert_compiled(&mut self, name: Cow<'source, str>, data: &[u8]) -> Result<(), Error> {
+        let (blob, code) = ok!(crate::compiler::bytecode::Artifact::parse(data)).into_parts();
+        let name: Arc<str> = name.into();
+        let template = ok!(LoadedTemplate::try_new(
+            (name.clone(), blob.into_boxed_str()),
+            |(name, blob)| -> Result<_, Error> { code.load(name, blob, &self.template_config) },
+        ));
+        Arc::make_mut(&mut self.borrowed_templates).remove(&name as &str);
+        self.owned_templates.replace(name, Arc::new(template));
+        Ok(())
+    }
+
     pub fn remove(&mut self, name: &str) {
-        self.borrowed_templates.remove(name);
+        Arc::make_mut(&mut self.borrowed_templates).remove(name);
         self.owned_templates.remove(name);
     }
 
     pub fn clear(&mut self) {
-        self.borrowed_templates.clear();
+        Arc::make_mut(&mut self.borrowed_templates).clear();
         self.owned_templates.clear();
     }
 
@@ -176,6 +189,125 @@ impl<'source> LoaderStore<'source> {
     }
 }
 
+/// A bounded cache for templates rendered from strings.
+///
+/// Entries are keyed by source and name and remember the config they were
+/// compiled with.  When full, the least recently used entry is evicted.
+pub(crate) struct InlineTemplateCache {
+    capacity: usize,
+    state: Mutex<InlineTemplateCacheState>,
+}
+
+#[derive(Default)]
+struct InlineTemplateCacheState {
+    tick: u64,
+    len: usize,
+    templates: BTreeMap<Box<str>, BTreeMap<Box<str>, InlineTemplateCacheEntry>>,
+}
+
+struct InlineTemplateCacheEntry {
+    template: Arc<LoadedTemplate>,
+    config: TemplateConfig,
+    last_used: u64,
+}
+
+impl fmt::Debug for InlineTemplateCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InlineTemplateCache")
+            .field("capacity", &self.capacity)
+            .finish()
+    }
+}
+
+impl InlineTemplateCache {
+    pub fn new(capacity: usize) -> InlineTemplateCache {
+        InlineTemplateCache {
+            capacity,
+            state: Mutex::default(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Looks up a template or compiles and caches it.
+    pub fn get_or_compile(
+        &self,
+        name: &str,
+        source: &str,
+        config: &TemplateConfig,
+    ) -> Result<Arc<LoadedTemplate>, Error> {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.tick += 1;
+            let tick = state.tick;
+            if let Some(entry) = state
+                .templates
+                .get_mut(source)
+                .and_then(|x| x.get_mut(name))
+                .filter(|x| x.config.compiles_like(config))
+            {
+                entry.last_used = tick;
+                return Ok(entry.template.clone());
+            }
+        }
+
+        // compile without holding the lock
+        let template = ok!(LoadedTemplate::try_new(
+            (Arc::from(name), Box::from(source)),
+            |(name, source)| -> Result<_, Error> { CompiledTemplate::new(name, source, config) },
+        )
+        .map(Arc::new));
+
+        let mut state = self.state.lock().unwrap();
+        let tick = state.tick;
+        let is_new = !state
+            .templates
+            .get(source)
+            .map_or(false, |x| x.contains_key(name));
+        if is_new && state.len >= self.capacity {
+            state.evict_oldest();
+        }
+        state.templates.entry(source.into()).or_default().insert(
+            name.into(),
+            InlineTemplateCacheEntry {
+                template: template.clone(),
+                config: config.clone(),
+                last_used: tick,
+            },
+        );
+        if is_new {
+            state.len += 1;
+        }
+        Ok(template)
+    }
+}
+
+impl InlineTemplateCacheState {
+    fn evict_oldest(&mut self) {
+        let oldest = self
+            .templates
+            .iter()
+            .flat_map(|(source, names)| {
+                names
+                    .iter()
+                    .map(move |(name, entry)| (entry.last_used, source, name))
+            })
+            .min_by_key(|x| x.0)
+            .map(|(_, source, name)| (source.clone(), name.clone()));
+        if let Some((source, name)) = oldest {
+            if let Some(names) = self.templates.get_mut(&source) {
+                names.remove(&name);
+                if names.is_empty() {
+                    self.templates.remove(&source);
+                }
+            }
+            self.len -= 1;
+        }
+    }
+}
+
 /// Safely joins two paths.
 pub fn safe_join(base: &Path, template: &str) -> Option<PathBuf> {
     let mut rv = base.to_path_buf();
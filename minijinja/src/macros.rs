@@ -23,9 +23,11 @@ macro_rules! some {
 /// Hidden utility module for the [`context!`](crate::context!) macro.
 #[doc(hidden)]
 pub mod __context {
+    use crate::error::{Error, ErrorKind};
     pub use crate::value::merge_object::MergeObject;
-    use crate::value::{Value, ValueMap};
+    use crate::value::{Value, ValueKind, ValueMap};
     use crate::Environment;
+    use serde::Serialize;
     use std::rc::Rc;
 
     #[inline(always)]
@@ -34,8 +36,8 @@ pub mod __context {
     }
 
     #[inline(always)]
-    pub fn add(ctx: &mut ValueMap, key: &'static str, value: Value) {
-        ctx.insert(key.into(), value);
+    pub fn add<T: Serialize + ?Sized>(ctx: &mut ValueMap, key: &'static str, value: &T) {
+        ctx.insert(key.into(), Value::from_serialize_at(key, value));
     }
 
     #[inline(always)]
@@ -43,6 +45,24 @@ pub mod __context {
         Value::from_object(ctx)
     }
 
+    /// Merges values into a single context where earlier values win.
+    ///
+    /// If one of the values is not a map, an invalid value is returned
+    /// which fails when it is used.
+    pub fn merge(values: Vec<Value>) -> Value {
+        for value in &values {
+            if value.kind() == ValueKind::Invalid {
+                return value.clone();
+            } else if value.kind() != ValueKind::Map {
+                return Value::from(Error::new(
+                    ErrorKind::InvalidOperation,
+                    format!("cannot merge value of type {} into context", value.kind()),
+                ));
+            }
+        }
+        Value::from_object(MergeObject(values))
+    }
+
     pub fn thread_local_env() -> Rc<Environment<'static>> {
         thread_local! {
             static ENV: Rc<Environment<'static>> = Rc::new(Environment::new());
@@ -109,9 +129,22 @@ pub mod __context {
 /// };
 /// ```
 ///
-/// The merge works with an value, not just values created by the `context!`
+/// Keys that are set directly always take precedence over merged values,
+/// similar to Rust's struct update syntax.  The merged values can also be
+/// placed in front of the keys which makes extending a base context read
+/// more naturally:
+///
+/// ```rust
+/// # use minijinja::context;
+/// let base = context! { a => "A", b => "B" };
+/// let ctx = context! { ..base, b => "overridden", c => "C" };
+/// assert_eq!(ctx.get_attr("b").unwrap().as_str(), Some("overridden"));
+/// ```
+///
+/// The merge works with any map, not just values created by the `context!`
 /// macro and is performed lazy.  This means it also works with dynamic
-/// [`Object`](crate::value::Object)s.
+/// [`Object`](crate::value::Object)s.  Merging a value that is not a map
+/// creates an invalid context which fails when rendered.
 ///
 /// # Note on Conversions
 ///
@@ -130,7 +163,7 @@ macro_rules! context {
     };
     (
         $($key:ident $(=> $value:expr)?),*
-        $(, .. $ctx:expr),* $(,)?
+        $(, .. $ctx:expr)* $(,)?
     ) => {{
         let mut ctx = $crate::__context::make();
         $(
@@ -145,9 +178,15 @@ macro_rules! context {
             ctx
         } else {
             merged_ctx.insert(0, ctx);
-            $crate::value::Value::from_object($crate::__context::MergeObject(merged_ctx))
+            $crate::__context::merge(merged_ctx)
         }
     }};
+    (
+        $(.. $ctx:expr),+ ,
+        $($key:ident $(=> $value:expr)?),+ $(,)?
+    ) => {
+        $crate::context!($($key $(=> $value)?),+ $(, .. $ctx)+)
+    };
     (
         $(.. $ctx:expr),* $(,)?
     ) => {{
@@ -155,7 +194,7 @@ macro_rules! context {
         $(
             ctx.push($crate::value::Value::from($ctx));
         )*;
-        $crate::value::Value::from_object($crate::__context::MergeObject(ctx))
+        $crate::__context::merge(ctx)
     }};
 }
 
@@ -169,7 +208,7 @@ macro_rules! __context_pair {
         $crate::__context::add(
             &mut $ctx,
             stringify!($key),
-            $crate::value::Value::from_serialize(&$value),
+            &$value,
         );
     };
 }
@@ -295,7 +334,8 @@ macro_rules! __args_helper {
 /// # Panics
 ///
 /// This macro panics if the format string is an invalid template or the
-/// template evaluation failed.
+/// template evaluation failed.  To handle errors instead use the
+/// [`try_render!`](crate::try_render) macro.
 #[macro_export]
 macro_rules! render {
     (
@@ -313,3 +353,35 @@ macro_rules! render {
         $crate::render!(in $crate::__context::thread_local_env(), $tmpl, $($key $(=> $value)? ,)*)
     }
 }
+
+/// Like [`render!`](crate::render) but returns a [`Result`] instead of panicking.
+///
+/// This accepts the same forms as [`render!`](crate::render) but returns
+/// `Result<String, Error>` so that invalid templates or evaluation errors
+/// can be handled by the caller.
+///
+/// ```
+/// # use minijinja::{Environment, try_render};
+/// let rv = try_render!("Hello {{ name }}!", name => "World").unwrap();
+/// assert_eq!(rv, "Hello World!");
+///
+/// let env = Environment::new();
+/// let err = try_render!(in env, "Hello {{ name", name => "World").unwrap_err();
+/// assert_eq!(err.kind(), minijinja::ErrorKind::SyntaxError);
+/// ```
+#[macro_export]
+macro_rules! try_render {
+    (
+        in $env:expr,
+        $tmpl:expr
+        $(, $key:ident $(=> $value:expr)?)* $(,)?
+    ) => {
+        ($env).render_str($tmpl, $crate::context! { $($key $(=> $value)? ,)* })
+    };
+    (
+        $tmpl:expr
+        $(, $key:ident $(=> $value:expr)?)* $(,)?
+    ) => {
+        $crate::try_render!(in $crate::__context::thread_local_env(), $tmpl, $($key $(=> $value)? ,)*)
+    }
+}
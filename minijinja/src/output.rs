@@ -18,10 +18,13 @@ pub enum CaptureMode {
 ///
 /// This is a utility type used in the engine which can be written into like one
 /// can write into an [`std::fmt::Write`] value.  It's primarily used internally
-/// in the engine but it's also passed to the custom formatter function.
+/// in the engine but it's also passed to the custom formatter function.  In
+/// that case [`auto_escape`](Self::auto_escape) reflects the auto escape mode
+/// the value is being formatted with.
 pub struct Output<'a> {
     w: &'a mut (dyn fmt::Write + 'a),
     capture_stack: Vec<Option<String>>,
+    auto_escape: AutoEscape,
 }
 
 impl<'a> Output<'a> {
@@ -30,6 +33,7 @@ impl<'a> Output<'a> {
         Self {
             w: buf,
             capture_stack: Vec::new(),
+            auto_escape: AutoEscape::None,
         }
     }
 
@@ -37,6 +41,7 @@ impl<'a> Output<'a> {
         Self {
             w,
             capture_stack: Vec::new(),
+            auto_escape: AutoEscape::None,
         }
     }
 
@@ -48,6 +53,7 @@ impl<'a> Output<'a> {
         Self {
             w: NullWriter::get_mut(),
             capture_stack: vec![None],
+            auto_escape: AutoEscape::None,
         }
     }
 
@@ -88,6 +94,25 @@ impl<'a> Output<'a> {
         matches!(self.capture_stack.last(), Some(None))
     }
 
+    /// Returns the auto escape mode of the value that is being formatted.
+    ///
+    /// This is only set when the output is passed to a formatter in which
+    /// case it is the same value as [`State::auto_escape`](crate::State::auto_escape)
+    /// of the state that is passed alongside it.  This makes it possible to
+    /// write formatting helpers that only have access to the output but need
+    /// to escape differently depending on the context (for instance HTML vs
+    /// JSON).  Outside of a formatter the value is not meaningful.
+    #[inline(always)]
+    pub fn auto_escape(&self) -> AutoEscape {
+        self.auto_escape
+    }
+
+    /// Sets the auto escape mode for the output.
+    #[inline(always)]
+    pub(crate) fn set_auto_escape(&mut self, auto_escape: AutoEscape) {
+        self.auto_escape = auto_escape;
+    }
+
     /// Writes some data to the underlying buffer contained within this output.
     #[inline]
     pub fn write_str(&mut self, s: &str) -> fmt::Result {
@@ -141,6 +166,95 @@ impl fmt::Write for NullWriter {
     }
 }
 
+/// Collapses runs of whitespace written through it.
+///
+/// See [`WhitespacePolicy`](crate::WhitespacePolicy) for the rules.  Because
+/// the last run of whitespace is held back until more output arrives,
+/// [`finish`](Self::finish) must be called once all output was written.
+pub struct WhitespaceCollapser<'a> {
+    w: &'a mut (dyn fmt::Write + 'a),
+    html: bool,
+    pending: Option<char>,
+    tag_name: Option<String>,
+    raw_tag: Option<&'static str>,
+}
+
+/// Elements in which whitespace is preserved in HTML mode.
+const RAW_HTML_TAGS: &[&str] = &["pre", "textarea"];
+
+impl<'a> WhitespaceCollapser<'a> {
+    pub fn new(w: &'a mut (dyn fmt::Write + 'a), html: bool) -> Self {
+        WhitespaceCollapser {
+            w,
+            html,
+            pending: None,
+            tag_name: None,
+            raw_tag: None,
+        }
+    }
+
+    /// Writes out the held back whitespace.
+    pub fn finish(&mut self) -> fmt::Result {
+        match self.pending.take() {
+            Some(c) => self.w.write_char(c),
+            None => Ok(()),
+        }
+    }
+
+    /// Tracks the opening and closing of raw elements.
+    fn track_tag(&mut self, c: char) {
+        if c == '<' {
+            self.tag_name = Some(String::new());
+            return;
+        }
+        let name = match self.tag_name {
+            Some(ref mut name) => name,
+            None => return,
+        };
+        if c.is_ascii_alphanumeric() || (c == '/' && name.is_empty()) {
+            // no interesting tag is that long
+            if name.len() < 10 {
+                name.push(c.to_ascii_lowercase());
+            }
+            return;
+        }
+        match self.raw_tag {
+            None => {
+                self.raw_tag = RAW_HTML_TAGS.iter().copied().find(|x| x == name);
+            }
+            Some(raw_tag) => {
+                if name.strip_prefix('/') == Some(raw_tag) {
+                    self.raw_tag = None;
+                }
+            }
+        }
+        self.tag_name = None;
+    }
+}
+
+impl fmt::Write for WhitespaceCollapser<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut start = 0;
+        for (idx, c) in s.char_indices() {
+            if self.html {
+                self.track_tag(c);
+            }
+            if c.is_whitespace() && self.raw_tag.is_none() {
+                ok!(self.w.write_str(&s[start..idx]));
+                self.pending = Some(if c == '\n' || self.pending == Some('\n') {
+                    '\n'
+                } else {
+                    ' '
+                });
+                start = idx + c.len_utf8();
+            } else if let Some(pending) = self.pending.take() {
+                ok!(self.w.write_char(pending));
+            }
+        }
+        self.w.write_str(&s[start..])
+    }
+}
+
 pub struct WriteWrapper<W> {
     pub w: W,
     pub err: Option<io::Error>,
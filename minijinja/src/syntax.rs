@@ -219,17 +219,19 @@
 //! - `loop.last`: True if this is the last iteration.
 //! - `loop.length`: The number of items in the sequence.
 //! - `loop.cycle`: A helper function to cycle between a list of sequences. See the explanation below.
-//! - `loop.depth`: Indicates how deep in a recursive loop the rendering currently is. Starts at level 1
-//! - `loop.depth0`: Indicates how deep in a recursive loop the rendering currently is. Starts at level 0
+//! - `loop.depth`: Indicates how deep in a recursive loop the rendering currently is. Starts at level 1 and is always 1 for non-recursive loops.
+//! - `loop.depth0`: Indicates how deep in a recursive loop the rendering currently is. Starts at level 0 and is always 0 for non-recursive loops.
 //! - `loop.previtem`: The item from the previous iteration of the loop. `Undefined` during the first iteration.
-//! - `loop.nextitem`: The item from the previous iteration of the loop. `Undefined` during the last iteration.
+//! - `loop.nextitem`: The item from the next iteration of the loop. `Undefined` during the last iteration.
 //! - `loop.changed(...args)`: Returns true if the passed values have changed since the last time it was called with the same arguments.
 //! - `loop.cycle(...args)`: Returns a value from the passed sequence in a cycle.
 //!
 //! A special note on iterators: in the current version of MiniJinja, some sequences are actually
 //! lazy iterators.  They behave a bit like sequences not not entirely.  They can be iterated over,
 //! will happily serialize once into a a list etc.  However when iterating over an actual iterator,
-//! `last`, `revindex` and `revindex0` will always be undefined.
+//! `length`, `revindex` and `revindex0` will always be undefined.  `previtem` and `nextitem`
+//! work as the loop looks ahead by a single item, which also allows `last` to be determined.
+//! Without the `adjacent_loop_items` feature `last` is always false for iterators.
 //!
 //! Within a for-loop, it’s possible to cycle among a list of strings/variables each time through
 //! the loop by using the special `loop.cycle` helper:
@@ -263,6 +265,11 @@
 //! {% endfor %}
 //! ```
 //!
+//! The filtering happens before the loop starts, so the loop attributes such as
+//! `loop.length`, `loop.last`, `loop.revindex` or `loop.nextitem` only consider the
+//! items that pass the condition.  In recursive loops the condition is also
+//! applied to the iterables passed to `loop()`.
+//!
 //! If no iteration took place because the sequence was empty or the filtering
 //! removed all the items from the sequence, you can render a default block by
 //! using else:
@@ -381,7 +388,8 @@
 //! first tag in the template.
 //!
 //! As you can see it's also possible to render the contents of the parent block by calling
-//! ``super()``. You can’t define multiple ``{% block %}`` tags with the same name in
+//! ``super()``.  In longer inheritance chains the parent block can itself call ``super()``
+//! to render the block of its own parent. You can’t define multiple ``{% block %}`` tags with the same name in
 //! the same template. This limitation exists because a block tag works in “both”
 //! directions. That is, a block tag doesn’t just provide a placeholder to fill -
 //! it also defines the content that fills the placeholder in the parent. If
@@ -410,6 +418,24 @@
 //!
 //! However, the name after the `endblock` word must match the block name.
 //!
+//! A block can be marked as `required`.  Such a block must be overridden by a
+//! template extending it, otherwise rendering fails.  Required blocks can only
+//! contain whitespace and comments:
+//!
+//! ```jinja
+//! {% block body required %}{% endblock %}
+//! ```
+//!
+//! For compatibility with Jinja2 blocks can also be marked as `scoped`.  This has
+//! no effect in MiniJinja as blocks always have access to the variables of the
+//! enclosing scope, such as the loop variable of a surrounding `for` loop:
+//!
+//! ```jinja
+//! {% for item in seq %}
+//!   <li>{% block loop_item scoped %}{{ item }}{% endblock %}</li>
+//! {% endfor %}
+//! ```
+//!
 //! ## `{% include %}`
 //!
 //! **Feature:** `multi_template` (included by default)
@@ -524,6 +550,13 @@
 //! {% set title | upper %}Title of the page{% endset %}
 //! ```
 //!
+//! Multiple filters can be chained (`{% set nav | trim | upper %}`) and they
+//! are applied to the captured string before it is assigned.  As in Jinja2,
+//! if auto escaping is enabled the captured content is marked as safe as
+//! everything in it was already escaped while rendering the block.  String
+//! filters such as `upper` or `trim` keep that marker so the value is not
+//! escaped a second time when printed.
+//!
 //! More complex use cases can be handled using namespace objects which allow
 //! propagating of changes across scopes:
 //!
@@ -650,6 +683,20 @@
 //! {% endcall %}
 //! ```
 //!
+//! A call block always emits what the macro returns.  To keep the result for
+//! further processing instead, wrap the call block in a [`set`](#-set-) block.
+//! The variable then holds the output of the macro, and like with any other
+//! `set` block a filter can be applied:
+//!
+//! ```jinja
+//! {% set dialog_html %}
+//!   {% call dialog(title="Hello World") %}
+//!     This is the dialog body.
+//!   {% endcall %}
+//! {% endset %}
+//! {{ dialog_html|trim }}
+//! ```
+//!
 //! ## `{% do %}`
 //!
 //! The do tag has the same functionality as regular template tags (`{{ ... }}`);
@@ -700,6 +747,18 @@
 //! ```
 //!
 //! After an `endautoescape` the behavior is reverted to what it was before.
+//! Blocks can be nested and the argument can be any expression that
+//! evaluates to a boolean, so escaping can also be toggled based on a
+//! variable:
+//!
+//! ```jinja
+//! {% autoescape not fragment_is_trusted %}
+//!   {{ fragment }}
+//! {% endautoescape %}
+//! ```
+//!
+//! Instead of a boolean, the name of an escaping mode can be provided as
+//! string (`"html"`, `"json"` or `"none"`).
 //!
 //! The exact auto escaping behavior is determined by the value of
 //! [`AutoEscape`](crate::AutoEscape) set to the template.
@@ -916,6 +975,11 @@ Additionally whitespace control is not available for line statements.
 //!
 //! By default, MiniJinja also removes trailing newlines.  To keep single trailing newlines,
 //! configure MiniJinja to [`keep_trailing_newline`](crate::Environment::set_keep_trailing_newline).
+//!
+//! All of these rules apply the same way to custom delimiters.
+//! With block delimiters set to `<%` and `%>` for instance, `lstrip_blocks` strips
+//! the indentation in front of `<%`, `trim_blocks` removes the newline after `%>` and
+//! `<%-`, `<%+`, `-%>` and `+%>` control the whitespace manually.
 
 #[cfg(feature = "custom_syntax")]
 mod imp {
@@ -950,27 +1014,45 @@ mod imp {
 
     impl Delims {
         fn validated_start_delims(&self) -> Result<Vec<&str>, Error> {
-            let mut delims = Vec::with_capacity(5);
-            for (delim, required) in [
-                (&self.variable_start, true),
-                (&self.block_start, true),
-                (&self.comment_start, true),
-                (&self.line_statement_prefix, false),
-                (&self.line_comment_prefix, false),
+            fn invalid(msg: String) -> Error {
+                Error::new(ErrorKind::InvalidDelimiter, msg)
+            }
+
+            for (delim, name) in [
+                (&self.block_end, "block end delimiter"),
+                (&self.variable_end, "variable end delimiter"),
+                (&self.comment_end, "comment end delimiter"),
+            ] {
+                if delim.trim().is_empty() {
+                    return Err(invalid(format!("{} must not be empty or whitespace", name)));
+                }
+            }
+
+            let mut delims: Vec<(&str, &str)> = Vec::with_capacity(5);
+            for (delim, name, required) in [
+                (&self.variable_start, "variable start delimiter", true),
+                (&self.block_start, "block start delimiter", true),
+                (&self.comment_start, "comment start delimiter", true),
+                (&self.line_statement_prefix, "line statement prefix", false),
+                (&self.line_comment_prefix, "line comment prefix", false),
             ] {
                 let delim = delim as &str;
-                if delim.is_empty() {
-                    if required {
-                        return Err(ErrorKind::InvalidDelimiter.into());
-                    }
-                } else if delims.contains(&delim) {
-                    return Err(ErrorKind::InvalidDelimiter.into());
-                } else {
-                    delims.push(delim);
+                if delim.is_empty() && !required {
+                    continue;
+                }
+                if delim.trim().is_empty() {
+                    return Err(invalid(format!("{} must not be empty or whitespace", name)));
+                }
+                if let Some((_, other)) = delims.iter().find(|x| x.0 == delim) {
+                    return Err(invalid(format!(
+                        "{} '{}' is the same as the {}",
+                        name, delim, other
+                    )));
                 }
+                delims.push((delim, name));
             }
 
-            Ok(delims)
+            Ok(delims.into_iter().map(|x| x.0).collect())
         }
     }
 
@@ -1070,7 +1152,10 @@ mod imp {
     /// You can  override the syntax configuration for templates by setting different
     /// delimiters.  The end markers can be shared, but the start markers need to be
     /// distinct.  It would thus not be valid to configure `{{` to be the marker for
-    /// both variables and blocks.
+    /// both variables and blocks.  Delimiters must also not be empty or consist of
+    /// whitespace only.  [`SyntaxConfigBuilder::build`] fails with an error of kind
+    /// [`InvalidDelimiter`](crate::ErrorKind::InvalidDelimiter) describing the problem
+    /// if the configuration is invalid.
     ///
     /// ```
     /// # use minijinja::{Environment, syntax::SyntaxConfig};
@@ -1,26 +1,30 @@
+use std::borrow::Cow;
 use std::collections::{BTreeMap, HashSet};
-use std::ops::Deref;
+use std::ops::{Deref, Range};
 use std::sync::Arc;
 use std::{fmt, io};
 
 use serde::Serialize;
 
 use crate::compiler::codegen::CodeGenerator;
-use crate::compiler::instructions::Instructions;
+use crate::compiler::instructions::{Instruction, Instructions, LOOP_FLAG_WITH_LOOP_VAR};
 use crate::compiler::lexer::WhitespaceConfig;
 use crate::compiler::meta::find_undeclared;
-use crate::compiler::parser::parse;
+use crate::compiler::parser::parse_from_offset;
 use crate::environment::Environment;
 use crate::error::{attach_basic_debug_info, Error};
-use crate::output::{Output, WriteWrapper};
+use crate::output::{CaptureMode, Output, WhitespaceCollapser, WriteWrapper};
 use crate::syntax::SyntaxConfig;
-use crate::utils::AutoEscape;
+use crate::utils::{AutoEscape, UndefinedBehavior, WhitespacePolicy};
 use crate::value::Value;
-use crate::vm::{prepare_blocks, Context, State, Vm};
+use crate::vm::{prepare_blocks, Context, State, Vm, Warning};
 
 /// Callback for auto escape determination
 pub type AutoEscapeFunc = dyn Fn(&str) -> AutoEscape + Sync + Send;
 
+/// Callback that parses the front matter of a template.
+pub type FrontMatterFunc = dyn Fn(&str) -> Result<Value, Error> + Sync + Send;
+
 /// Internal struct that holds template loading level config values.
 #[derive(Clone)]
 pub struct TemplateConfig {
@@ -30,6 +34,10 @@ pub struct TemplateConfig {
     pub ws_config: WhitespaceConfig,
     /// The callback that determines the initial auto escaping for templates.
     pub default_auto_escape: Arc<AutoEscapeFunc>,
+    /// The parser for front matter.  If not set front matter is not extracted.
+    pub front_matter_parser: Option<Arc<FrontMatterFunc>>,
+    /// The line that opens and closes the front matter.
+    pub front_matter_delimiter: Cow<'static, str>,
 }
 
 impl TemplateConfig {
@@ -38,8 +46,32 @@ impl TemplateConfig {
             syntax_config: SyntaxConfig::default(),
             ws_config: WhitespaceConfig::default(),
             default_auto_escape,
+            front_matter_parser: None,
+            front_matter_delimiter: Cow::Borrowed("---"),
         }
     }
+
+    /// Checks if templates compiled with both configs are interchangeable.
+    #[cfg(feature = "loader")]
+    pub(crate) fn compiles_like(&self, other: &TemplateConfig) -> bool {
+        let (a, b) = (&self.syntax_config, &other.syntax_config);
+        Arc::ptr_eq(&self.default_auto_escape, &other.default_auto_escape)
+            && match (&self.front_matter_parser, &other.front_matter_parser) {
+                (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+                (None, None) => true,
+                _ => false,
+            }
+            && self.front_matter_delimiter == other.front_matter_delimiter
+            && self.ws_config.keep_trailing_newline == other.ws_config.keep_trailing_newline
+            && self.ws_config.lstrip_blocks == other.ws_config.lstrip_blocks
+            && self.ws_config.trim_blocks == other.ws_config.trim_blocks
+            && self.ws_config.keep_comments == other.ws_config.keep_comments
+            && a.block_delimiters() == b.block_delimiters()
+            && a.variable_delimiters() == b.variable_delimiters()
+            && a.comment_delimiters() == b.comment_delimiters()
+            && a.line_statement_prefix() == b.line_statement_prefix()
+            && a.line_comment_prefix() == b.line_comment_prefix()
+    }
 }
 
 /// Represents a handle to a template.
@@ -53,6 +85,7 @@ impl TemplateConfig {
 pub struct Template<'env: 'source, 'source> {
     env: &'env Environment<'env>,
     pub(crate) compiled: CompiledTemplateRef<'env, 'source>,
+    undefined_behavior: UndefinedBehavior,
 }
 
 impl fmt::Debug for Template<'_, '_> {
@@ -74,7 +107,39 @@ impl<'env, 'source> Template<'env, 'source> {
         env: &'env Environment<'env>,
         compiled: CompiledTemplateRef<'env, 'source>,
     ) -> Template<'env, 'source> {
-        Template { env, compiled }
+        Template {
+            env,
+            compiled,
+            undefined_behavior: env.undefined_behavior(),
+        }
+    }
+
+    /// Overrides the undefined behavior for renders of this template handle.
+    ///
+    /// By default a template uses the [undefined behavior](UndefinedBehavior)
+    /// of the environment.  This returns a handle that uses a different
+    /// behavior instead.  The override applies to everything that is evaluated
+    /// as part of rendering this handle, including templates that are
+    /// included, imported or extended from.  Other handles to the same
+    /// template are not affected.
+    ///
+    /// ```
+    /// # use minijinja::{Environment, UndefinedBehavior};
+    /// let mut env = Environment::new();
+    /// env.add_template("hello", "Hello {{ name }}!").unwrap();
+    /// let tmpl = env.get_template("hello").unwrap();
+    /// assert_eq!(tmpl.render(()).unwrap(), "Hello !");
+    /// let strict = tmpl.with_undefined_behavior(UndefinedBehavior::Strict);
+    /// assert!(strict.render(()).is_err());
+    /// ```
+    pub fn with_undefined_behavior(mut self, behavior: UndefinedBehavior) -> Self {
+        self.undefined_behavior = behavior;
+        self
+    }
+
+    /// Returns the undefined behavior used when rendering this template.
+    pub fn undefined_behavior(&self) -> UndefinedBehavior {
+        self.undefined_behavior
     }
 
     /// Returns the name of the template.
@@ -87,6 +152,64 @@ impl<'env, 'source> Template<'env, 'source> {
         self.compiled.instructions.source()
     }
 
+    /// Returns the parsed front matter of the template.
+    ///
+    /// Front matter is only extracted if a parser was configured with
+    /// [`Environment::set_front_matter_parser`].  If the template does not
+    /// start with a front matter block `None` is returned.
+    pub fn front_matter(&self) -> Option<Value> {
+        self.compiled.front_matter.clone()
+    }
+
+    /// Returns static complexity metrics of the template.
+    ///
+    /// The metrics are computed once when the template is compiled and can
+    /// be used to reject overly complex templates upfront, for instance
+    /// before storing templates uploaded by users.  Together with
+    /// [`Environment::set_fuel`](crate::Environment::set_fuel) this gives
+    /// both a static and a runtime limit.
+    ///
+    /// ```
+    /// # use minijinja::Environment;
+    /// let mut env = Environment::new();
+    /// env.add_template("x", "{% for a in b %}{% for c in a %}{{ c }}{% endfor %}{% endfor %}").unwrap();
+    /// let complexity = env.get_template("x").unwrap().complexity();
+    /// assert_eq!(complexity.loop_count(), 2);
+    /// assert_eq!(complexity.max_nesting(), 2);
+    /// ```
+    pub fn complexity(&self) -> TemplateComplexity {
+        self.compiled.complexity
+    }
+
+    /// Returns the byte range of the given line in the source.
+    ///
+    /// Lines are numbered starting with `1` like in errors and the range does
+    /// not include the trailing newline.  For lines outside of the template
+    /// `None` is returned.  This is useful to map an error's
+    /// [`line`](crate::Error::line) back to the source, for instance to
+    /// highlight it in an editor.
+    ///
+    /// ```
+    /// # use minijinja::Environment;
+    /// # let env = Environment::new();
+    /// let tmpl = env.template_from_str("Hello\n{{ name }}!").unwrap();
+    /// let range = tmpl.line_range(2).unwrap();
+    /// assert_eq!(&tmpl.source()[range], "{{ name }}!");
+    /// ```
+    pub fn line_range(&self, lineno: usize) -> Option<Range<usize>> {
+        let source = self.source();
+        let mut offset = 0;
+        for (idx, line) in source.split_inclusive('\n').enumerate() {
+            if idx + 1 == lineno {
+                let line = line.strip_suffix('\n').unwrap_or(line);
+                let line = line.strip_suffix('\r').unwrap_or(line);
+                return Some(offset..offset + line.len());
+            }
+            offset += line.len();
+        }
+        None
+    }
+
     /// Renders the template into a string.
     ///
     /// The provided value is used as the initial context for the template.  It
@@ -112,10 +235,31 @@ impl<'env, 'source> Template<'env, 'source> {
     ///
     /// **Note on values:** The [`Value`] type implements `Serialize` and can be
     /// efficiently passed to render.  It does not undergo actual serialization.
+    /// If you already have a [`Value`] you can also use
+    /// [`render_value`](Self::render_value) which skips the serialization step
+    /// entirely.
     pub fn render<S: Serialize>(&self, ctx: S) -> Result<String, Error> {
         // reduce total amount of code faling under mono morphization into
         // this function, and share the rest in _render.
-        self._render(Value::from_serialize(&ctx)).map(|x| x.0)
+        self.render_value(Value::from_serialize(&ctx))
+    }
+
+    /// Renders the template with a [`Value`] as context.
+    ///
+    /// This works like [`render`](Self::render) but takes the context as
+    /// [`Value`] which is then used directly.  This is useful if the context
+    /// was already built as value, for instance with [`context!`](crate::context).
+    ///
+    /// ```
+    /// # use minijinja::{Environment, context};
+    /// # let mut env = Environment::new();
+    /// # env.add_template("hello", "Hello {{ name }}!").unwrap();
+    /// let tmpl = env.get_template("hello").unwrap();
+    /// let ctx = context!(name => "John");
+    /// assert_eq!(tmpl.render_value(ctx).unwrap(), "Hello John!");
+    /// ```
+    pub fn render_value(&self, ctx: Value) -> Result<String, Error> {
+        self._render(ctx).map(|x| x.0)
     }
 
     /// Like [`render`](Self::render) but also return the evaluated [`State`].
@@ -144,10 +288,38 @@ impl<'env, 'source> Template<'env, 'source> {
         self._render(Value::from_serialize(&ctx))
     }
 
+    /// Like [`render`](Self::render) but also returns the issued warnings.
+    ///
+    /// Warnings are non fatal problems reported through [`State::warn`] by
+    /// filters, tests and functions while rendering.  This is useful for
+    /// logging or when migrating templates away from deprecated features.
+    ///
+    /// ```
+    /// # use minijinja::{Environment, State, context};
+    /// let mut env = Environment::new();
+    /// env.add_function("legacy", |state: &State| {
+    ///     state.warn("legacy() is deprecated");
+    ///     ""
+    /// });
+    /// let tmpl = env.template_from_str("{{ legacy() }}Hello!").unwrap();
+    /// let (rv, warnings) = tmpl.render_with_warnings(context!()).unwrap();
+    /// assert_eq!(rv, "Hello!");
+    /// assert_eq!(warnings.len(), 1);
+    /// ```
+    ///
+    /// **Note on values:** The [`Value`] type implements `Serialize` and can be
+    /// efficiently passed to render.  It does not undergo actual serialization.
+    pub fn render_with_warnings<S: Serialize>(
+        &self,
+        ctx: S,
+    ) -> Result<(String, Vec<Warning>), Error> {
+        self._render(Value::from_serialize(&ctx))
+            .map(|(rv, state)| (rv, state.warnings.take()))
+    }
+
     fn _render(&self, root: Value) -> Result<(String, State<'_, 'env>), Error> {
         let mut rv = String::with_capacity(self.compiled.buffer_size_hint);
-        self._eval(root, &mut Output::with_string(&mut rv))
-            .map(|(_, state)| (rv, state))
+        self._eval_to(root, &mut rv).map(|state| (rv, state))
     }
 
     /// Renders the template into an [`io::Write`].
@@ -172,14 +344,22 @@ impl<'env, 'source> Template<'env, 'source> {
         &self,
         ctx: S,
         w: W,
+    ) -> Result<State<'_, 'env>, Error> {
+        self.render_value_to_write(Value::from_serialize(&ctx), w)
+    }
+
+    /// Renders the template with a [`Value`] as context into an [`io::Write`].
+    ///
+    /// This works like [`render_to_write`](Self::render_to_write) but takes
+    /// the context as [`Value`] like [`render_value`](Self::render_value) does.
+    pub fn render_value_to_write<W: io::Write>(
+        &self,
+        ctx: Value,
+        w: W,
     ) -> Result<State<'_, 'env>, Error> {
         let mut wrapper = WriteWrapper { w, err: None };
-        self._eval(
-            Value::from_serialize(&ctx),
-            &mut Output::with_write(&mut wrapper),
-        )
-        .map(|(_, state)| state)
-        .map_err(|err| wrapper.take_err(err))
+        self._eval_to(ctx, &mut wrapper)
+            .map_err(|err| wrapper.take_err(err))
     }
 
     /// Evaluates the template into a [`State`].
@@ -213,11 +393,29 @@ impl<'env, 'source> Template<'env, 'source> {
             &self.compiled.blocks,
             &mut out,
             self.compiled.initial_auto_escape,
+            self.undefined_behavior,
         ))
         .1;
         Ok(state)
     }
 
+    /// Evaluates the template into a writer honoring the output whitespace policy.
+    fn _eval_to(&self, root: Value, w: &mut dyn fmt::Write) -> Result<State<'_, 'env>, Error> {
+        let html = match self.env.output_whitespace() {
+            WhitespacePolicy::Preserve => {
+                return self
+                    ._eval(root, &mut Output::with_write(w))
+                    .map(|(_, state)| state)
+            }
+            WhitespacePolicy::Collapse => false,
+            WhitespacePolicy::CollapseHtml => true,
+        };
+        let mut collapser = WhitespaceCollapser::new(w, html);
+        let (_, state) = ok!(self._eval(root, &mut Output::with_write(&mut collapser)));
+        ok!(collapser.finish().map_err(Error::from));
+        Ok(state)
+    }
+
     fn _eval(
         &self,
         root: Value,
@@ -229,9 +427,24 @@ impl<'env, 'source> Template<'env, 'source> {
             &self.compiled.blocks,
             out,
             self.compiled.initial_auto_escape,
+            self.undefined_behavior,
         )
     }
 
+    /// Serializes the compiled template into bytes.
+    ///
+    /// The returned data contains the source and the compiled instructions of
+    /// the template and can be loaded with
+    /// [`Environment::add_compiled_template`] without parsing the template
+    /// again.  The data is only compatible with the exact same version of
+    /// MiniJinja.  This fails if the template contains constants that cannot
+    /// be stored.
+    #[cfg(feature = "bytecode")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "bytecode")))]
+    pub fn to_compiled_bytes(&self) -> Result<Vec<u8>, Error> {
+        crate::compiler::bytecode::dump(&self.compiled)
+    }
+
     /// Returns a set of all undeclared variables in the template.
     ///
     /// This returns a set of all variables that might be looked up
@@ -252,9 +465,10 @@ impl<'env, 'source> Template<'env, 'source> {
     /// // returns ["foo", "bar.baz"]
     /// ```
     pub fn undeclared_variables(&self, nested: bool) -> HashSet<String> {
-        match parse(
+        match parse_from_offset(
             self.compiled.instructions.source(),
             self.name(),
+            self.compiled.body_offset,
             self.compiled.syntax_config.clone(),
             // TODO: this is not entirely great, but good enough for this use case.
             Default::default(),
@@ -264,19 +478,40 @@ impl<'env, 'source> Template<'env, 'source> {
         }
     }
 
+    /// Returns the names of all blocks defined in the template.
+    ///
+    /// This only returns the blocks that are declared directly in this
+    /// template.  Because the template to extend from is only known at
+    /// runtime, the blocks of parent templates are not included.  To also
+    /// get those, evaluate the template with [`eval_to_state`](Self::eval_to_state)
+    /// and use [`State::blocks`].
+    ///
+    /// ```
+    /// # use minijinja::Environment;
+    /// let mut env = Environment::new();
+    /// env.add_template("x", "{% block title %}{% endblock %}{% block body %}{% endblock %}").unwrap();
+    /// let tmpl = env.get_template("x").unwrap();
+    /// assert_eq!(tmpl.blocks(), vec!["body", "title"]);
+    /// ```
+    pub fn blocks(&self) -> Vec<&str> {
+        self.compiled.blocks.keys().copied().collect()
+    }
+
     /// Creates an empty [`State`] for this template.
     ///
     /// It's very rare that you need to actually do this but it can be useful when
     /// testing values or working with macros or other callable objects from outside
     /// the template environment.
     pub fn new_state(&self) -> State<'_, 'env> {
-        State::new(
+        let mut state = State::new(
             self.env,
             Context::new(self.env.recursion_limit()),
             self.compiled.initial_auto_escape,
             &self.compiled.instructions,
             prepare_blocks(&self.compiled.blocks),
-        )
+        );
+        state.undefined_behavior = self.undefined_behavior;
+        state
     }
 
     /// Returns the instructions and blocks if the template is loaded from the
@@ -343,6 +578,12 @@ pub struct CompiledTemplate<'source> {
     pub syntax_config: SyntaxConfig,
     /// The initial setting of auto escaping.
     pub initial_auto_escape: AutoEscape,
+    /// The parsed front matter.
+    pub front_matter: Option<Value>,
+    /// The offset in the source where the template body starts.
+    pub body_offset: usize,
+    /// Static complexity metrics.
+    pub complexity: TemplateComplexity,
 }
 
 impl fmt::Debug for CompiledTemplate<'_> {
@@ -372,9 +613,25 @@ impl<'source> CompiledTemplate<'source> {
         source: &'source str,
         config: &TemplateConfig,
     ) -> Result<CompiledTemplate<'source>, Error> {
-        let ast = ok!(parse(
+        let (front_matter, body_offset) = match config.front_matter_parser {
+            Some(ref parser) => match split_front_matter(source, &config.front_matter_delimiter) {
+                Some((front_matter, body_offset)) => {
+                    // like syntax errors, errors from the front matter parser
+                    // point to the template and the start of the block
+                    let front_matter = ok!(parser(front_matter).map_err(|mut err| {
+                        err.set_filename_and_line(name, 1);
+                        err
+                    }));
+                    (Some(front_matter), body_offset)
+                }
+                None => (None, 0),
+            },
+            None => (None, 0),
+        };
+        let ast = ok!(parse_from_offset(
             source,
             name,
+            body_offset,
             config.syntax_config.clone(),
             config.ws_config
         ));
@@ -382,12 +639,124 @@ impl<'source> CompiledTemplate<'source> {
         g.compile_stmt(&ast);
         let buffer_size_hint = g.buffer_size_hint();
         let (instructions, blocks) = g.finish();
+        let complexity = TemplateComplexity::compute(&instructions, &blocks);
         Ok(CompiledTemplate {
             instructions,
             blocks,
             buffer_size_hint,
             syntax_config: config.syntax_config.clone(),
             initial_auto_escape: (config.default_auto_escape)(name),
+            front_matter,
+            body_offset,
+            complexity,
         })
     }
 }
+
+/// Static complexity metrics of a template.
+///
+/// This is returned by [`Template::complexity`].  All metrics are derived
+/// from the compiled template and are deterministic for a given source.
+///
+/// * [`loop_count`](Self::loop_count) and [`max_nesting`](Self::max_nesting)
+///   only depend on the template syntax and are stable across releases.
+/// * [`instruction_count`](Self::instruction_count) depends on the compiler
+///   and can change between releases of MiniJinja.  Thresholds based on it
+///   should leave some headroom.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TemplateComplexity {
+    instruction_count: usize,
+    loop_count: usize,
+    max_nesting: usize,
+}
+
+impl TemplateComplexity {
+    pub(crate) fn compute(
+        root: &Instructions<'_>,
+        blocks: &BTreeMap<&str, Instructions<'_>>,
+    ) -> Self {
+        let mut rv = TemplateComplexity::default();
+        for instructions in Some(root).into_iter().chain(blocks.values()) {
+            // every pushed frame records if it counts towards the nesting.
+            // The inner loops of filtered `for` loops and the frames that
+            // `import` and `from` push for the imported template are not
+            // visible in the template syntax and are thus ignored.
+            let mut frames = Vec::new();
+            let mut nesting = 0;
+            for idx in 0..instructions.len() {
+                let counts = match instructions.get(idx) {
+                    Some(Instruction::PushLoop(flags)) => {
+                        let counts = flags & LOOP_FLAG_WITH_LOOP_VAR != 0;
+                        if counts {
+                            rv.loop_count += 1;
+                        }
+                        counts
+                    }
+                    Some(Instruction::PushWith) => !matches!(
+                        idx.checked_sub(1).and_then(|x| instructions.get(x)),
+                        Some(Instruction::BeginCapture(CaptureMode::Discard))
+                    ),
+                    Some(Instruction::PopFrame) => {
+                        if frames.pop() == Some(true) {
+                            nesting -= 1;
+                        }
+                        continue;
+                    }
+                    _ => continue,
+                };
+                frames.push(counts);
+                if counts {
+                    nesting += 1;
+                    rv.max_nesting = rv.max_nesting.max(nesting);
+                }
+            }
+            rv.instruction_count += instructions.len();
+        }
+        rv
+    }
+
+    /// The total number of instructions across the template and all of
+    /// its blocks.
+    pub fn instruction_count(&self) -> usize {
+        self.instruction_count
+    }
+
+    /// The number of `for` loops in the template and its blocks.
+    ///
+    /// A filtered loop (`{% for x in seq if x %}`) counts as a single loop.
+    pub fn loop_count(&self) -> usize {
+        self.loop_count
+    }
+
+    /// The deepest nesting of `for` loops and scoping blocks such as
+    /// `with`.
+    ///
+    /// Every block is measured on its own, so a block called from within
+    /// a loop does not add to the nesting of the loop.  The `import` and
+    /// `from` tags do not add to the nesting either.
+    pub fn max_nesting(&self) -> usize {
+        self.max_nesting
+    }
+}
+
+/// Splits a leading front matter block off the source.
+///
+/// The block has to start on the first line of the source and ends with the
+/// next line that consists of only the delimiter.  Returns the front matter
+/// and the offset where the template body starts.
+fn split_front_matter<'s>(source: &'s str, delimiter: &str) -> Option<(&'s str, usize)> {
+    let rest = source.strip_prefix(delimiter)?;
+    let rest = rest
+        .strip_prefix("\r\n")
+        .or_else(|| rest.strip_prefix('\n'))?;
+    let start = source.len() - rest.len();
+    let mut offset = start;
+    for line in rest.split_inclusive('\n') {
+        let end = offset + line.len();
+        if line.trim_end_matches(['\r', '\n']) == delimiter {
+            return Some((&source[start..offset], end));
+        }
+        offset = end;
+    }
+    None
+}
@@ -241,6 +241,7 @@ mod builtins {
 
     use std::borrow::Cow;
 
+    use crate::error::ErrorKind;
     use crate::value::ops::{coerce, CoerceResult};
     use crate::value::ValueKind;
 
@@ -256,36 +257,74 @@ mod builtins {
 
     /// Checks if a value is odd.
     ///
+    /// Floats are only considered if they hold an integral value, so
+    /// `5.0 is odd` is true but `5.5 is odd` is false.  Values that are not
+    /// numbers, including booleans, are neither odd nor even.
+    ///
     /// ```jinja
     /// {{ 41 is odd }} -> true
+    /// {{ -3 is odd }} -> true
     /// ```
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
     pub fn is_odd(v: Value) -> bool {
+        if v.kind() == ValueKind::Bool {
+            return false;
+        }
         i128::try_from(v).ok().map_or(false, |x| x % 2 != 0)
     }
 
     /// Checks if a value is even.
     ///
+    /// Like with [`is_odd`] floats are only considered if they hold an
+    /// integral value.
+    ///
     /// ```jinja
     /// {{ 42 is even }} -> true
+    /// {{ 42.0 is even }} -> true
+    /// {{ 42.5 is even }} -> false
     /// ```
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
     pub fn is_even(v: Value) -> bool {
+        if v.kind() == ValueKind::Bool {
+            return false;
+        }
         i128::try_from(v).ok().map_or(false, |x| x % 2 == 0)
     }
 
     /// Return true if the value is divisible by another one.
     ///
+    /// If either value is a float, the check is performed on floats so
+    /// `7.5 is divisibleby(2.5)` is true.  Values that are not numbers,
+    /// including booleans, are never divisible.  Dividing by zero is an error
+    /// in templates, this function returns `false` in that case.
+    ///
     /// ```jinja
     /// {{ 42 is divisibleby(2) }} -> true
+    /// {{ -9 is divisibleby(3) }} -> true
     /// ```
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
     pub fn is_divisibleby(v: &Value, other: &Value) -> bool {
-        match coerce(v, other, false) {
-            Some(CoerceResult::I128(a, b)) => (a % b) == 0,
-            Some(CoerceResult::F64(a, b)) => (a % b) == 0.0,
-            _ => false,
+        is_divisibleby_checked(v, other).unwrap_or(false)
+    }
+
+    /// The `divisibleby` test which fails when dividing by zero.
+    pub(crate) fn is_divisibleby_checked(v: &Value, other: &Value) -> Result<bool, Error> {
+        if v.kind() == ValueKind::Bool || other.kind() == ValueKind::Bool {
+            return Ok(false);
         }
+        let rv = match coerce(v, other, false) {
+            Some(CoerceResult::I128(_, 0)) => None,
+            // i128::MIN % -1 overflows but is divisible
+            Some(CoerceResult::I128(a, b)) => Some(a.checked_rem(b).map_or(true, |x| x == 0)),
+            Some(CoerceResult::F64(a, b)) => (b != 0.0).then(|| (a % b) == 0.0),
+            _ => Some(false),
+        };
+        rv.ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidOperation,
+                "cannot check divisibility by zero",
+            )
+        })
     }
 
     /// Checks if this value is a number.
@@ -334,6 +373,9 @@ mod builtins {
 
     /// Checks if this value is a sequence
     ///
+    /// This is true for lists as well as for objects that are represented as
+    /// sequence ([`ObjectRepr::Seq`](crate::value::ObjectRepr::Seq)).
+    ///
     /// ```jinja
     /// {{ [1, 2, 3] is sequence }} -> true
     /// {{ 42 is sequence }} -> false
@@ -345,6 +387,9 @@ mod builtins {
 
     /// Checks if this value can be iterated over.
     ///
+    /// This is true for sequences, maps, strings and all objects that can be
+    /// enumerated.
+    ///
     /// ```jinja
     /// {{ [1, 2, 3] is iterable }} -> true
     /// {{ 42 is iterable }} -> false
@@ -356,6 +401,9 @@ mod builtins {
 
     /// Checks if this value is a mapping
     ///
+    /// This is true for maps as well as for objects that are represented as
+    /// map ([`ObjectRepr::Map`](crate::value::ObjectRepr::Map)).
+    ///
     /// ```jinja
     /// {{ {"foo": "bar"} is mapping }} -> true
     /// {{ [1, 2, 3] is mapping }} -> false
@@ -367,26 +415,76 @@ mod builtins {
 
     /// Checks if the value is starting with a string.
     ///
+    /// Instead of a single string a sequence of candidates can be passed in
+    /// which case the test passes if the value starts with any of them.
+    ///
     /// ```jinja
     /// {{ "foobar" is startingwith "foo" }} -> true
     /// {{ "foobar" is startingwith "bar" }} -> false
+    /// {{ "foobar" is startingwith(("bar", "foo")) }} -> true
     /// ```
+    ///
+    /// This function itself only accepts a single string.
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
     pub fn is_startingwith(v: Cow<'_, str>, other: Cow<'_, str>) -> bool {
         v.starts_with(&other as &str)
     }
 
+    /// The `startingwith` test which also accepts a sequence of candidates.
+    pub(crate) fn is_startingwith_any(v: Cow<'_, str>, other: &Value) -> Result<bool, Error> {
+        match_affixes(other, "startingwith", |affix| v.starts_with(affix))
+    }
+
     /// Checks if the value is ending with a string.
     ///
+    /// Instead of a single string a sequence of candidates can be passed in
+    /// which case the test passes if the value ends with any of them.
+    ///
     /// ```jinja
     /// {{ "foobar" is endingwith "bar" }} -> true
     /// {{ "foobar" is endingwith "foo" }} -> false
+    /// {{ "foobar" is endingwith(("foo", "bar")) }} -> true
     /// ```
+    ///
+    /// This function itself only accepts a single string.
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
     pub fn is_endingwith(v: Cow<'_, str>, other: Cow<'_, str>) -> bool {
         v.ends_with(&other as &str)
     }
 
+    /// The `endingwith` test which also accepts a sequence of candidates.
+    pub(crate) fn is_endingwith_any(v: Cow<'_, str>, other: &Value) -> Result<bool, Error> {
+        match_affixes(other, "endingwith", |affix| v.ends_with(affix))
+    }
+
+    fn match_affixes<F: Fn(&str) -> bool>(other: &Value, test: &str, f: F) -> Result<bool, Error> {
+        fn bad_affix(test: &str, value: &Value) -> Error {
+            Error::new(
+                ErrorKind::InvalidOperation,
+                format!(
+                    "{} test expects a string or a sequence of strings, got {}",
+                    test,
+                    value.kind()
+                ),
+            )
+        }
+
+        if let Some(affix) = other.as_str() {
+            return Ok(f(affix));
+        }
+        if other.kind() != ValueKind::Seq {
+            return Err(bad_affix(test, other));
+        }
+        for item in ok!(other.try_iter()) {
+            match item.as_str() {
+                Some(affix) if f(affix) => return Ok(true),
+                Some(_) => {}
+                None => return Err(bad_affix(test, &item)),
+            }
+        }
+        Ok(false)
+    }
+
     /// Test version of `==`.
     ///
     /// This is useful when combined with [`select`](crate::filters::select).
@@ -568,10 +666,12 @@ mod builtins {
 
     /// Checks if two values are identical.
     ///
-    /// This primarily exists for compatibilith with Jinja2.  It can be seen as a much
+    /// This primarily exists for compatibility with Jinja2.  It can be seen as a much
     /// stricter comparison than a regular comparison.  The main difference is that
     /// values that have the same structure but a different internal object will not
-    /// compare equal.
+    /// compare equal.  Objects (including sequences, maps and dynamic objects) are
+    /// compared by identity, all other values fall back to value equality but only
+    /// if they are of the same kind.
     ///
     /// ```jinja
     /// {{ [1, 2, 3] is sameas [1, 2, 3] }}
@@ -580,6 +680,15 @@ mod builtins {
     /// {{ false is sameas false }}
     ///     -> true
     /// ```
+    ///
+    /// This is useful to find a specific object in a list, for instance to
+    /// highlight the current page in a navigation:
+    ///
+    /// ```jinja
+    /// {% for item in nav %}
+    ///   <li{% if item is sameas current_page %} class="active"{% endif %}>{{ item.title }}</li>
+    /// {% endfor %}
+    /// ```
     #[cfg_attr(docsrs, doc(cfg(feature = "builtins")))]
     #[cfg(feature = "builtins")]
     pub fn is_sameas(value: &Value, other: &Value) -> bool {
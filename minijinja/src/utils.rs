@@ -4,6 +4,7 @@ use std::fmt;
 use std::iter::{once, repeat};
 use std::str::Chars;
 
+use crate::environment::EscaperFunc;
 use crate::error::{Error, ErrorKind};
 use crate::value::{StringType, Value, ValueIter, ValueKind, ValueRepr};
 use crate::Output;
@@ -40,10 +41,24 @@ fn write_with_html_escaping(out: &mut Output, value: &Value) -> fmt::Result {
     }
 }
 
+pub fn write_with_custom_escaping(
+    out: &mut Output,
+    escaper: &EscaperFunc,
+    value: &Value,
+) -> Result<(), Error> {
+    match value.0 {
+        ValueRepr::String(ref s, StringType::Safe) => out.write_str(s),
+        ValueRepr::String(ref s, _) => escaper(out, s),
+        ValueRepr::SmallStr(ref s) => escaper(out, s.as_str()),
+        _ => escaper(out, &value.to_string()),
+    }
+    .map_err(Error::from)
+}
+
 fn invalid_autoescape(name: &str) -> Result<(), Error> {
     Err(Error::new(
         ErrorKind::InvalidOperation,
-        format!("Default formatter does not know how to format to custom format '{name}'"),
+        format!("no escaper registered for custom auto escape format '{name}'"),
     ))
 }
 
@@ -58,6 +73,10 @@ pub fn write_escaped(
         if matches!(ty, StringType::Safe) || matches!(auto_escape, AutoEscape::None) {
             return out.write_str(s).map_err(Error::from);
         }
+    } else if let ValueRepr::SmallStr(ref s) = value.0 {
+        if matches!(auto_escape, AutoEscape::None) {
+            return out.write_str(s.as_str()).map_err(Error::from);
+        }
     }
 
     match auto_escape {
@@ -98,16 +117,46 @@ pub enum AutoEscape {
     Json,
     /// A custom auto escape format.
     ///
-    /// The default formatter does not know how to deal with a custom escaping
-    /// format and would error.  The use of these requires a custom formatter.
-    /// See [`set_formatter`](crate::Environment::set_formatter).
+    /// The default formatter dispatches custom formats to the escaper that was
+    /// registered under the same name with
+    /// [`add_escaper`](crate::Environment::add_escaper).  If no such escaper
+    /// exists formatting fails.  Alternatively a custom formatter can handle
+    /// the format, see [`set_formatter`](crate::Environment::set_formatter).
     Custom(&'static str),
 }
 
+/// Controls how whitespace in the rendered output is handled.
+///
+/// This can be configured with
+/// [`set_output_whitespace`](crate::Environment::set_output_whitespace) and is
+/// applied to the output of [`Template::render`](crate::Template::render) and
+/// related methods as it is written.  Unlike `trim_blocks` and `lstrip_blocks`
+/// this affects all whitespace in the output including whitespace produced by
+/// the template data.  The collapsing modes replace every run of whitespace
+/// characters with a single newline if the run contains a newline, or a single
+/// space otherwise.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum WhitespacePolicy {
+    /// Whitespace is written as is.  This is the default.
+    #[default]
+    Preserve,
+    /// Collapses all runs of whitespace in the output.
+    Collapse,
+    /// Like [`Collapse`](Self::Collapse) but HTML aware.
+    ///
+    /// Whitespace within `<pre>` and `<textarea>` elements is preserved.  The
+    /// detection of these elements is naive and does not understand comments
+    /// or tags within attribute values.
+    CollapseHtml,
+}
+
 /// Defines the behavior of undefined values in the engine.
 ///
-/// At present there are three types of behaviors available which mirror the behaviors
-/// that Jinja2 provides out of the box.
+/// Three of the behaviors mirror the behaviors that Jinja2 provides out of the
+/// box.  Additionally [`StrictOutput`](Self::StrictOutput) sits between
+/// [`Lenient`](Self::Lenient) and [`Strict`](Self::Strict): it only fails when an
+/// undefined value is printed.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
 #[non_exhaustive]
 pub enum UndefinedBehavior {
@@ -123,6 +172,7 @@ pub enum UndefinedBehavior {
     /// * **printing:** allowed (returns empty string)
     /// * **iteration:** allowed (returns empty array)
     /// * **attribute access of undefined values:** allowed (returns [`undefined`](Value::UNDEFINED))
+    /// * **calling undefined values or unknown functions:** allowed (returns [`undefined`](Value::UNDEFINED))
     Chainable,
     /// Complains very quickly about undefined values.
     ///
@@ -130,6 +180,17 @@ pub enum UndefinedBehavior {
     /// * **iteration:** fails
     /// * **attribute access of undefined values:** fails
     Strict,
+    /// Like `Lenient`, but fails when undefined values are printed.
+    ///
+    /// This is useful to catch typos in `{{ variable }}` while still allowing
+    /// templates to test for optional values with `{% if variable %}` or to
+    /// loop over missing sequences.
+    ///
+    /// * **printing:** fails
+    /// * **iteration:** allowed (returns empty array)
+    /// * **if checks:** allowed (undefined is false)
+    /// * **attribute access of undefined values:** fails
+    StrictOutput,
 }
 
 impl UndefinedBehavior {
@@ -144,8 +205,11 @@ impl UndefinedBehavior {
         match (self, parent_was_undefined) {
             (UndefinedBehavior::Lenient, false)
             | (UndefinedBehavior::Strict, false)
+            | (UndefinedBehavior::StrictOutput, false)
             | (UndefinedBehavior::Chainable, _) => Ok(Value::UNDEFINED),
-            (UndefinedBehavior::Lenient, true) | (UndefinedBehavior::Strict, true) => {
+            (UndefinedBehavior::Lenient, true)
+            | (UndefinedBehavior::Strict, true)
+            | (UndefinedBehavior::StrictOutput, true) => {
                 Err(Error::from(ErrorKind::UndefinedError))
             }
         }
@@ -173,6 +237,20 @@ impl UndefinedBehavior {
         self.assert_iterable(&value).and_then(|_| value.try_iter())
     }
 
+    /// Are we strict on printing?
+    #[inline]
+    pub(crate) fn assert_printable(self, value: &Value) -> Result<(), Error> {
+        if matches!(
+            self,
+            UndefinedBehavior::Strict | UndefinedBehavior::StrictOutput
+        ) && value.is_undefined()
+        {
+            Err(Error::from(ErrorKind::UndefinedError))
+        } else {
+            Ok(())
+        }
+    }
+
     /// Are we strict on iteration?
     #[inline]
     pub(crate) fn assert_iterable(self, value: &Value) -> Result<(), Error> {
@@ -1,6 +1,6 @@
 use std::borrow::Cow;
 use std::cell::RefCell;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::ops::{Deref, DerefMut};
 use std::sync::Arc;
 
@@ -88,6 +88,9 @@ pub trait FunctionArgs<'a> {
 /// # Ok(())
 /// # } fn main() { foo().unwrap(); }
 /// ```
+///
+/// If the keyword arguments are known upfront they can also be deserialized
+/// straight into a struct with [`ViaKwargs`](crate::value::ViaKwargs).
 #[inline(always)]
 pub fn from_args<'a, Args>(values: &'a [Value]) -> Result<Args, Error>
 where
@@ -113,7 +116,7 @@ where
 /// * vectors: [`Vec<T>`]
 /// * objects: [`DynObject`], [`Arc<T>`], `&T` (where `T` is an [`Object`])
 /// * serde deserializable: [`ViaDeserialize<T>`](crate::value::deserialize::ViaDeserialize)
-/// * keyword arguments: [`Kwargs`]
+/// * keyword arguments: [`Kwargs`], [`ViaKwargs<T>`](crate::value::deserialize::ViaKwargs)
 /// * leftover arguments: [`Rest<T>`]
 ///
 /// The type is also implemented for optional values (`Option<T>`) which is used
@@ -279,6 +282,11 @@ impl<'a> From<&'a String> for Value {
 impl From<String> for Value {
     #[inline(always)]
     fn from(val: String) -> Self {
+        // short strings are stored inline so that repeatedly created keys
+        // and attribute names do not each hold on to a separate allocation.
+        if let Some(small_str) = SmallStr::try_new(&val) {
+            return Value(ValueRepr::SmallStr(small_str));
+        }
         ValueRepr::String(Arc::from(val), StringType::Normal).into()
     }
 }
@@ -733,6 +741,9 @@ impl<'a, T: ArgType<'a, Output = T>> ArgType<'a> for Rest<T> {
 /// assert!(value.is_kwargs());
 /// ```
 ///
+/// Keyword arguments retain the order in which they were passed at the call
+/// site.  This is the case even if the `preserve_order` feature is disabled.
+///
 /// When working with [`Rest`] you can use [`from_args`] to split all arguments into
 /// positional arguments and keyword arguments:
 ///
@@ -751,29 +762,79 @@ pub struct Kwargs {
     used: RefCell<HashSet<String>>,
 }
 
-#[repr(transparent)]
+/// The storage behind [`Kwargs`].
+///
+/// Unlike [`ValueMap`] this always retains the order in which the keyword
+/// arguments were passed, even if the `preserve_order` feature is disabled.
 #[derive(Default, Debug)]
-pub(crate) struct KwargsValues(ValueMap);
+pub(crate) struct KwargsValues {
+    entries: Vec<(Value, Value)>,
+    index: BTreeMap<Value, usize>,
+}
+
+impl KwargsValues {
+    /// Inserts a keyword argument.
+    ///
+    /// If the key was already passed, the value is replaced but the key
+    /// retains its original position.
+    pub(crate) fn insert(&mut self, key: Value, value: Value) {
+        match self.index.get(&key) {
+            Some(&idx) => self.entries[idx].1 = value,
+            None => {
+                self.index.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+    }
 
-impl Deref for KwargsValues {
-    type Target = ValueMap;
+    /// Looks up a keyword argument by key.
+    pub(crate) fn get(&self, key: &Value) -> Option<&Value> {
+        self.index.get(key).map(|&idx| &self.entries[idx].1)
+    }
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
+    /// Checks if a keyword argument exists.
+    pub(crate) fn contains_key(&self, key: &Value) -> bool {
+        self.index.contains_key(key)
+    }
+
+    /// Iterates over the keys in the order they were passed.
+    pub(crate) fn keys(&self) -> impl Iterator<Item = &Value> {
+        self.entries.iter().map(|x| &x.0)
+    }
+
+    /// Iterates over the key value pairs in the order they were passed.
+    #[cfg(any(feature = "builtins", feature = "deserialization"))]
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&Value, &Value)> {
+        self.entries.iter().map(|x| (&x.0, &x.1))
+    }
+
+    /// Returns the number of keyword arguments.
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+impl FromIterator<(Value, Value)> for KwargsValues {
+    fn from_iter<T: IntoIterator<Item = (Value, Value)>>(iter: T) -> Self {
+        let mut rv = KwargsValues::default();
+        for (key, value) in iter {
+            rv.insert(key, value);
+        }
+        rv
     }
 }
 
 impl Object for KwargsValues {
     fn get_value(self: &Arc<Self>, key: &Value) -> Option<Value> {
-        self.0.get(key).cloned()
+        self.get(key).cloned()
     }
 
     fn enumerate(self: &Arc<Self>) -> Enumerator {
-        self.mapped_enumerator(|this| Box::new(this.0.keys().cloned()))
+        self.mapped_enumerator(|this| Box::new(this.keys().cloned()))
     }
 
     fn enumerator_len(self: &Arc<Self>) -> Option<usize> {
-        Some(self.0.len())
+        Some(self.len())
     }
 }
 
@@ -824,9 +885,9 @@ impl Kwargs {
             .map(Kwargs::new)
     }
 
-    /// Wraps a value map into kwargs.
-    pub(crate) fn wrap(map: ValueMap) -> Value {
-        Value::from_object(KwargsValues(map))
+    /// Wraps key value pairs into kwargs retaining their order.
+    pub(crate) fn wrap<I: IntoIterator<Item = (Value, Value)>>(iter: I) -> Value {
+        Value::from_object(iter.into_iter().collect::<KwargsValues>())
     }
 
     /// Get a single argument from the kwargs but don't mark it as used.
@@ -878,8 +939,11 @@ impl Kwargs {
     }
 
     /// Iterates over all passed keyword arguments.
+    ///
+    /// The arguments are yielded in the order in which they were passed at
+    /// the call site.  This is independent of the `preserve_order` feature.
     pub fn args(&self) -> impl Iterator<Item = &str> {
-        self.values.iter().filter_map(|x| x.0.as_str())
+        self.values.keys().filter_map(|x| x.as_str())
     }
 
     /// Asserts that all kwargs were used.
@@ -909,9 +973,9 @@ impl FromIterator<(String, Value)> for Kwargs {
     where
         T: IntoIterator<Item = (String, Value)>,
     {
-        Kwargs::new(Arc::new(KwargsValues(
+        Kwargs::new(Arc::new(
             iter.into_iter().map(|(k, v)| (Value::from(k), v)).collect(),
-        )))
+        ))
     }
 }
 
@@ -920,9 +984,9 @@ impl<'a> FromIterator<(&'a str, Value)> for Kwargs {
     where
         T: IntoIterator<Item = (&'a str, Value)>,
     {
-        Kwargs::new(Arc::new(KwargsValues(
+        Kwargs::new(Arc::new(
             iter.into_iter().map(|(k, v)| (Value::from(k), v)).collect(),
-        )))
+        ))
     }
 }
 
@@ -2,12 +2,13 @@ use std::ops::{Deref, DerefMut};
 
 use serde::de::value::{MapDeserializer, SeqDeserializer};
 use serde::de::{
-    self, Deserialize, DeserializeSeed, Deserializer, EnumAccess, IntoDeserializer, MapAccess,
-    SeqAccess, Unexpected, VariantAccess, Visitor,
+    self, Deserialize, DeserializeOwned, DeserializeSeed, Deserializer, EnumAccess,
+    IntoDeserializer, MapAccess, SeqAccess, Unexpected, VariantAccess, Visitor,
 };
 use serde::forward_to_deserialize_any;
 
-use crate::value::{ArgType, ObjectRepr, Value, ValueKind, ValueMap, ValueRepr};
+use crate::value::{ArgType, Kwargs, ObjectRepr, Value, ValueKind, ValueMap, ValueRepr};
+use crate::vm::State;
 use crate::{Error, ErrorKind};
 
 #[cfg_attr(docsrs, doc(cfg(feature = "deserialization")))]
@@ -145,6 +146,225 @@ impl<T> DerefMut for ViaDeserialize<T> {
     }
 }
 
+/// Utility type to deserialize keyword arguments into a struct.
+///
+/// This works like [`ViaDeserialize`] but instead of consuming a positional
+/// argument it consumes the trailing keyword arguments (see
+/// [`Kwargs`](crate::value::Kwargs)).  This lets you declare all optional
+/// keyword arguments as fields of a struct.  If no keyword arguments were
+/// passed, the struct is deserialized from an empty map so fields should
+/// typically be optional or have defaults.
+///
+/// Keyword arguments that do not correspond to a field of the struct are
+/// rejected the same way [`Kwargs::assert_all_used`](crate::value::Kwargs::assert_all_used)
+/// would reject them.
+///
+/// ```rust
+/// # use minijinja::value::{from_args, Value, Kwargs};
+/// use serde::Deserialize;
+/// use minijinja::value::ViaKwargs;
+///
+/// #[derive(Deserialize)]
+/// struct Options {
+///     #[serde(default)]
+///     limit: Option<usize>,
+///     #[serde(default)]
+///     reverse: bool,
+/// }
+///
+/// # fn foo() -> Result<(), minijinja::Error> {
+/// # let args = [Value::from("foo"), Value::from(Kwargs::from_iter([("limit", Value::from(2))]))];
+/// // args is &[Value]
+/// let (name, opts): (&str, ViaKwargs<Options>) = from_args(&args)?;
+/// assert_eq!(name, "foo");
+/// assert_eq!(opts.limit, Some(2));
+/// assert!(!opts.reverse);
+/// # Ok(()) } fn main() { foo().unwrap(); }
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "deserialization")))]
+#[derive(Debug)]
+pub struct ViaKwargs<T>(pub T);
+
+impl<T: DeserializeOwned> ViaKwargs<T> {
+    fn from_kwargs_value(value: Option<&Value>) -> Result<Self, Error> {
+        let kwargs = match value {
+            Some(value) => ok!(Kwargs::extract(value).ok_or_else(|| Error::new(
+                ErrorKind::InvalidOperation,
+                "expected keyword arguments"
+            ))),
+            None => Kwargs::from_iter(std::iter::empty::<(&str, Value)>()),
+        };
+        let mut unknown = None;
+        let rv = ok!(T::deserialize(KwargsDeserializer {
+            kwargs: &kwargs,
+            unknown: &mut unknown,
+        }));
+        match unknown {
+            Some(key) => Err(Error::new(
+                ErrorKind::TooManyArguments,
+                format!("unknown keyword argument '{}'", key),
+            )),
+            None => Ok(ViaKwargs(rv)),
+        }
+    }
+}
+
+impl<'a, T: DeserializeOwned> ArgType<'a> for ViaKwargs<T> {
+    type Output = Self;
+
+    fn from_value(value: Option<&'a Value>) -> Result<Self, Error> {
+        Self::from_kwargs_value(value)
+    }
+
+    fn from_state_and_values(
+        _state: Option<&'a State>,
+        values: &'a [Value],
+        offset: usize,
+    ) -> Result<(Self, usize), Error> {
+        match values.get(offset).filter(|x| x.is_kwargs()) {
+            Some(value) => Self::from_kwargs_value(Some(value)).map(|rv| (rv, 1)),
+            None => Self::from_kwargs_value(None).map(|rv| (rv, 0)),
+        }
+    }
+
+    fn is_trailing() -> bool {
+        true
+    }
+}
+
+impl<T> Deref for ViaKwargs<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for ViaKwargs<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+/// Deserializes kwargs as a map and records keys the target ignored.
+struct KwargsDeserializer<'a> {
+    kwargs: &'a Kwargs,
+    unknown: &'a mut Option<Value>,
+}
+
+impl<'de> Deserializer<'de> for KwargsDeserializer<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_map(KwargsMapAccess {
+            iter: self.kwargs.values.iter(),
+            value: None,
+            unknown: self.unknown,
+        })
+    }
+
+    forward_to_deserialize_any! {
+        bool u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 char str string unit
+        seq bytes byte_buf map option unit_struct newtype_struct enum
+        tuple_struct struct tuple ignored_any identifier
+    }
+}
+
+struct KwargsMapAccess<'a, I> {
+    iter: I,
+    value: Option<(&'a Value, &'a Value)>,
+    unknown: &'a mut Option<Value>,
+}
+
+impl<'a, 'de, I> MapAccess<'de> for KwargsMapAccess<'a, I>
+where
+    I: Iterator<Item = (&'a Value, &'a Value)>,
+{
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match self.iter.next() {
+            Some((key, value)) => {
+                self.value = Some((key, value));
+                seed.deserialize(key.clone()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let (key, value) = ok!(self
+            .value
+            .take()
+            .ok_or_else(|| de::Error::custom("value requested before key")));
+        let mut ignored = false;
+        let rv = ok!(seed.deserialize(KwargValueDeserializer {
+            value: value.clone(),
+            ignored: &mut ignored,
+        }));
+        if ignored && self.unknown.is_none() {
+            *self.unknown = Some(key.clone());
+        }
+        Ok(rv)
+    }
+}
+
+/// Deserializes a single kwarg value and notes if the target ignored it.
+struct KwargValueDeserializer<'a> {
+    value: Value,
+    ignored: &'a mut bool,
+}
+
+impl<'de> Deserializer<'de> for KwargValueDeserializer<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.value.deserialize_any(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.value.deserialize_option(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.value.deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.value.deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.value.deserialize_newtype_struct(name, visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        *self.ignored = true;
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bool u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 char str string unit
+        seq bytes byte_buf map tuple_struct struct tuple identifier
+    }
+}
+
 // this is a macro so that we don't accidentally diverge between
 // the Value and &Value deserializer
 macro_rules! common_forward {
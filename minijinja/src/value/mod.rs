@@ -213,7 +213,7 @@ use crate::error::{Error, ErrorKind};
 use crate::functions;
 use crate::utils::OnDrop;
 use crate::value::ops::as_f64;
-use crate::value::serialize::transform;
+use crate::value::serialize::{transform, transform_at, PathSegment};
 use crate::vm::State;
 
 pub use crate::value::argtypes::{from_args, ArgType, FunctionArgs, FunctionResult, Kwargs, Rest};
@@ -231,7 +231,7 @@ pub(crate) mod ops;
 mod serialize;
 
 #[cfg(feature = "deserialization")]
-pub use self::deserialize::ViaDeserialize;
+pub use self::deserialize::{ViaDeserialize, ViaKwargs};
 
 // We use in-band signalling to roundtrip some internal values.  This is
 // not ideal but unfortunately there is no better system in serde today.
@@ -263,6 +263,10 @@ thread_local! {
     // AtomicU64 available.
     static LAST_VALUE_HANDLE: Cell<u32> = const { Cell::new(0) };
     static VALUE_HANDLES: RefCell<BTreeMap<u32, Value>> = RefCell::new(BTreeMap::new());
+
+    // Addresses of the objects that are currently being serialized.  This is
+    // used to detect objects that (indirectly) contain themselves.
+    static SERIALIZED_OBJECTS: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
 }
 
 /// Function that returns true when serialization for [`Value`] is taking place.
@@ -285,6 +289,29 @@ pub fn serializing_for_value() -> bool {
     INTERNAL_SERIALIZATION.with(|flag| flag.get())
 }
 
+/// Marks an object as being serialized.
+///
+/// Fails if the object is already being serialized further up which means
+/// that it contains itself.
+fn mark_object_serialization<E: serde::ser::Error>(addr: usize) -> Result<impl Drop, E> {
+    let recursive = SERIALIZED_OBJECTS.with(|objects| {
+        let mut objects = objects.borrow_mut();
+        if objects.contains(&addr) {
+            true
+        } else {
+            objects.push(addr);
+            false
+        }
+    });
+    if recursive {
+        Err(E::custom("cannot serialize object that contains itself"))
+    } else {
+        Ok(OnDrop::new(|| {
+            SERIALIZED_OBJECTS.with(|objects| objects.borrow_mut().pop());
+        }))
+    }
+}
+
 fn mark_internal_serialization() -> impl Drop {
     let old = INTERNAL_SERIALIZATION.with(|flag| {
         let old = flag.get();
@@ -331,6 +358,51 @@ pub enum ValueKind {
     Invalid,
 }
 
+impl ValueKind {
+    /// Returns a stable name for this kind.
+    ///
+    /// Unlike the [`Display`](std::fmt::Display) implementation which is
+    /// intended for error messages, this name is guaranteed to stay stable
+    /// and is suitable for type introspection.  The names do not always
+    /// match the built-in test for the kind (`bool` is checked with
+    /// `is boolean` and `map` with `is mapping`):
+    ///
+    /// | Kind | Name | Test |
+    /// |---|---|---|
+    /// | [`Undefined`](Self::Undefined) | `undefined` | `is undefined` |
+    /// | [`None`](Self::None) | `none` | `is none` |
+    /// | [`Bool`](Self::Bool) | `bool` | `is boolean` |
+    /// | [`Number`](Self::Number) | `number` | `is number` |
+    /// | [`String`](Self::String) | `string` | `is string` |
+    /// | [`Bytes`](Self::Bytes) | `bytes` | |
+    /// | [`Seq`](Self::Seq) | `sequence` | `is sequence` |
+    /// | [`Map`](Self::Map) | `map` | `is mapping` |
+    /// | [`Iterable`](Self::Iterable) | `iterable` | |
+    /// | [`Plain`](Self::Plain) | `plain` | |
+    /// | [`Invalid`](Self::Invalid) | `invalid` | |
+    ///
+    /// ```
+    /// # use minijinja::value::{Value, ValueKind};
+    /// assert_eq!(Value::from(vec![1, 2]).kind().name(), "sequence");
+    /// assert_eq!(ValueKind::Map.name(), "map");
+    /// ```
+    pub fn name(self) -> &'static str {
+        match self {
+            ValueKind::Undefined => "undefined",
+            ValueKind::None => "none",
+            ValueKind::Bool => "bool",
+            ValueKind::Number => "number",
+            ValueKind::String => "string",
+            ValueKind::Bytes => "bytes",
+            ValueKind::Seq => "sequence",
+            ValueKind::Map => "map",
+            ValueKind::Iterable => "iterable",
+            ValueKind::Plain => "plain",
+            ValueKind::Invalid => "invalid",
+        }
+    }
+}
+
 impl fmt::Display for ValueKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.write_str(match *self {
@@ -629,6 +701,53 @@ impl fmt::Debug for Value {
     }
 }
 
+/// Maximum number of items shown per container by [`DebugDump`].
+const MAX_DEBUG_ITEMS: usize = 100;
+
+/// Formats a value for the `debug()` function.
+///
+/// Unlike the regular debug output of a value this always looks into
+/// objects by enumerating them, explicitly marks maps that cannot be
+/// enumerated and truncates very large containers with an ellipsis.
+pub(crate) struct DebugDump<'a>(pub &'a Value);
+
+impl fmt::Debug for DebugDump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let obj = match self.0 .0 {
+            ValueRepr::Object(ref obj) => obj,
+            _ => return fmt::Debug::fmt(self.0, f),
+        };
+        match obj.repr() {
+            ObjectRepr::Map => match obj.try_iter_pairs() {
+                Some(iter) => {
+                    let mut dbg = f.debug_map();
+                    for (idx, (key, value)) in iter.enumerate() {
+                        if idx == MAX_DEBUG_ITEMS {
+                            dbg.entry(&format_args!("..."), &format_args!("..."));
+                            break;
+                        }
+                        dbg.entry(&DebugDump(&key), &DebugDump(&value));
+                    }
+                    dbg.finish()
+                }
+                None => write!(f, "<non-enumerable map {obj:?}>"),
+            },
+            ObjectRepr::Seq | ObjectRepr::Iterable if obj.enumerator_len().is_some() => {
+                let mut dbg = f.debug_list();
+                for (idx, value) in obj.try_iter().into_iter().flatten().enumerate() {
+                    if idx == MAX_DEBUG_ITEMS {
+                        dbg.entry(&format_args!("..."));
+                        break;
+                    }
+                    dbg.entry(&DebugDump(&value));
+                }
+                dbg.finish()
+            }
+            _ => fmt::Debug::fmt(self.0, f),
+        }
+    }
+}
+
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self.0 {
@@ -703,7 +822,23 @@ impl Value {
     /// fails.  There are also cases where invalid objects are silently hidden in the
     /// engine today.  This is for instance the case for when keys are used in hash maps
     /// that the engine cannot deal with.  Invalid values are considered an implementation
-    /// detail.  There is currently no API to validate a value.
+    /// detail.  There is currently no API to validate a value.  The error of an
+    /// invalid value includes the path to the field that failed to serialize
+    /// (for instance `users.3.address`) to make it easier to track down.
+    ///
+    /// Enums follow serde's default externally tagged representation.  Unit
+    /// variants become the name of the variant as string, all other variants
+    /// become a map with a single key (the name of the variant) that holds the
+    /// newtype value, the list of tuple fields or the map of struct fields.
+    /// This means that `Shape::Circle { r: 1.0 }` becomes `{"Circle": {"r": 1.0}}`
+    /// and a template can branch on the variant with the `in` operator:
+    ///
+    /// ```jinja
+    /// {% if "Circle" in shape %}radius: {{ shape.Circle.r }}{% endif %}
+    /// ```
+    ///
+    /// Internally tagged, adjacently tagged and untagged enums (`#[serde(tag = ...)]`,
+    /// `#[serde(untagged)]` etc.) are serialized by serde as regular maps and values.
     ///
     /// If the `deserialization` feature is enabled then the inverse of this method
     /// is to use the [`Value`] type as serializer.  You can pass a value into the
@@ -714,6 +849,16 @@ impl Value {
         transform(value)
     }
 
+    /// Like [`from_serialize`](Self::from_serialize) but reports `segment`
+    /// as path in serialization errors.
+    pub(crate) fn from_serialize_at<T: Serialize + ?Sized>(
+        segment: &'static str,
+        value: &T,
+    ) -> Value {
+        let _serialization_guard = mark_internal_serialization();
+        transform_at(None, PathSegment::Field(segment), value)
+    }
+
     /// Extracts a contained error.
     ///
     /// An invalid value carres an error internally and will reveal that error
@@ -989,6 +1134,40 @@ impl Value {
         })
     }
 
+    /// Creates a sequence value by eagerly consuming an iterator.
+    ///
+    /// This is the safe choice for iterators that cannot be re-created.  All
+    /// items are collected up front into a sequence, so the resulting value
+    /// can be iterated over any number of times and reports a known length.
+    /// This is equivalent to collecting via [`FromIterator`].
+    ///
+    /// ```
+    /// # use minijinja::value::Value;
+    /// let val = Value::from_iterator("abc".chars().map(|c| c.to_string()));
+    /// assert_eq!(val.len(), Some(3));
+    /// assert_eq!(val.to_string(), r#"["a", "b", "c"]"#);
+    /// ```
+    ///
+    /// There are three ways to expose iterators to templates, which differ in
+    /// what happens when a template iterates over a value more than once (for
+    /// instance by first calling `|length` and then looping over it):
+    ///
+    /// * [`from_iterator`](Self::from_iterator): consumes the iterator eagerly.
+    ///   Every iteration sees all items.
+    /// * [`make_iterable`](Self::make_iterable): invokes a closure to create a
+    ///   fresh iterator for every iteration.  Every iteration sees all items
+    ///   and nothing is buffered.
+    /// * [`make_one_shot_iterator`](Self::make_one_shot_iterator): streams the
+    ///   iterator lazily.  Only the first iteration sees items, all later ones
+    ///   come up empty.
+    pub fn from_iterator<I, T>(iter: I) -> Value
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<Value>,
+    {
+        iter.into_iter().collect()
+    }
+
     /// Creates a value from a one-shot iterator.
     ///
     /// This takes an iterator (yielding values that can be turned into a [`Value`])
@@ -1008,6 +1187,10 @@ impl Value {
     /// ```
     ///
     /// Attempting to iterate over it a second time will not yield any more items.
+    /// This includes implicit iterations performed by filters such as `length`
+    /// or `list`.  If the iterator is finite and the template might need to look
+    /// at the items more than once, use [`from_iterator`](Self::from_iterator)
+    /// instead.
     pub fn make_one_shot_iterator<I, T>(iter: I) -> Value
     where
         I: Iterator<Item = T> + Send + Sync + 'static,
@@ -1037,6 +1220,37 @@ impl Value {
         functions::BoxedFunction::new(f).to_value()
     }
 
+    /// Creates a callable value from a function with a signature attached.
+    ///
+    /// This works like [`from_function`](Self::from_function) but the
+    /// function additionally carries a [`FunctionSignature`](functions::FunctionSignature)
+    /// describing its arguments.  It is shown in debug output and can be
+    /// retrieved with [`function_signature`](Self::function_signature).
+    pub fn from_function_with_signature<F, Rv, Args>(
+        f: F,
+        signature: functions::FunctionSignature,
+    ) -> Value
+    where
+        // the crazy bounds here exist to enable borrowing in closures
+        F: functions::Function<Rv, Args>
+            + for<'a> functions::Function<Rv, <Args as FunctionArgs<'a>>::Output>,
+        Rv: FunctionResult,
+        Args: for<'a> FunctionArgs<'a>,
+    {
+        functions::BoxedFunction::new(f)
+            .with_signature(signature)
+            .to_value()
+    }
+
+    /// Returns the signature of a function if one is known.
+    ///
+    /// This returns `None` for values that are not functions and for
+    /// functions that were created without a signature.
+    pub fn function_signature(&self) -> Option<&functions::FunctionSignature> {
+        self.downcast_object_ref::<functions::BoxedFunction>()
+            .and_then(|x| x.signature())
+    }
+
     /// Returns the kind of the value.
     ///
     /// This can be used to determine what's in the value before trying to
@@ -1161,6 +1375,49 @@ impl Value {
         i64::try_from(self.clone()).ok()
     }
 
+    /// If this is an i128 return it
+    ///
+    /// ```
+    /// # use minijinja::value::Value;
+    /// assert_eq!(Value::from(u64::MAX).as_i128(), Some(u64::MAX as i128));
+    /// assert_eq!(Value::from(42.0).as_i128(), Some(42));
+    /// assert_eq!(Value::from(42.5).as_i128(), None);
+    /// assert_eq!(Value::from(true).as_i128(), None);
+    /// ```
+    pub fn as_i128(&self) -> Option<i128> {
+        match self.0 {
+            ValueRepr::Bool(_) => None,
+            _ => i128::try_from(self.clone()).ok(),
+        }
+    }
+
+    /// If this is a number return it as f64.
+    ///
+    /// Integers are converted if this can be done without losing precision,
+    /// otherwise `None` is returned.
+    ///
+    /// ```
+    /// # use minijinja::value::Value;
+    /// assert_eq!(Value::from(42).as_f64(), Some(42.0));
+    /// assert_eq!(Value::from(0.5).as_f64(), Some(0.5));
+    /// assert_eq!(Value::from(9007199254740993i64).as_f64(), None);
+    /// assert_eq!(Value::from("42").as_f64(), None);
+    /// ```
+    pub fn as_f64(&self) -> Option<f64> {
+        match self.0 {
+            ValueRepr::Bool(_) => None,
+            _ => as_f64(self, false),
+        }
+    }
+
+    /// If this is a bool return it
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.0 {
+            ValueRepr::Bool(val) => Some(val),
+            _ => None,
+        }
+    }
+
     /// Returns the bytes of this value if they exist.
     pub fn as_bytes(&self) -> Option<&[u8]> {
         match self.0 {
@@ -1183,6 +1440,31 @@ impl Value {
         }
     }
 
+    /// Returns an identity token for object values.
+    ///
+    /// For values that are backed by an object (this includes sequences and
+    /// maps) this returns the address of the shared object, for all other
+    /// values `None` is returned.  Two values have the same id if they refer
+    /// to the same object, which is also what the `sameas` test checks.  This
+    /// can be used to deduplicate objects or to memoize results computed
+    /// for an object.
+    ///
+    /// The id is only unique while the object is alive.  Once the object is
+    /// dropped the id can be reused by another object.
+    ///
+    /// ```
+    /// # use minijinja::value::Value;
+    /// let a = Value::from(vec![1, 2, 3]);
+    /// let b = a.clone();
+    /// assert!(a.object_id().is_some());
+    /// assert_eq!(a.object_id(), b.object_id());
+    /// assert_ne!(a.object_id(), Value::from(vec![1, 2, 3]).object_id());
+    /// assert_eq!(Value::from(42).object_id(), None);
+    /// ```
+    pub fn object_id(&self) -> Option<usize> {
+        self.as_object().map(|x| x.addr())
+    }
+
     /// Returns the length of the contained value.
     ///
     /// Values without a length will return `None`.
@@ -1278,6 +1560,27 @@ impl Value {
         }
     }
 
+    /// Looks up a nested value by a dotted path.
+    ///
+    /// The path is split on `.` and every segment is looked up in turn.
+    /// Segments that are numeric are used as indexes, all others as
+    /// attributes.  This is the same resolution that filters such as
+    /// `map` or `sort` use for their `attribute` argument.  Rather than
+    /// failing, this returns [`UNDEFINED`](Self::UNDEFINED) if any part of
+    /// the path cannot be resolved.
+    ///
+    /// ```
+    /// # use minijinja::value::Value;
+    /// let ctx = minijinja::context! {
+    ///     users => vec![minijinja::context! { name => "Peter" }],
+    /// };
+    /// assert_eq!(ctx.get_path("users.0.name").as_str(), Some("Peter"));
+    /// assert!(ctx.get_path("users.1.name").is_undefined());
+    /// ```
+    pub fn get_path(&self, path: &str) -> Value {
+        self.get_path_checked(path).unwrap_or(Value::UNDEFINED)
+    }
+
     /// Iterates over the value.
     ///
     /// Depending on the [`kind`](Self::kind) of the value the iterator
@@ -1398,6 +1701,52 @@ impl Value {
         })
     }
 
+    /// Merges this map with another map and returns the merged map.
+    ///
+    /// Keys from `other` override the keys of this value.  If `recursive` is
+    /// set to `true`, nested maps that exist in both values are merged as
+    /// well instead of being replaced.  Both values need to be maps, or an
+    /// error is returned.
+    ///
+    /// ```
+    /// # use minijinja::{context, Value};
+    /// let defaults = context! { a => 1, nested => context! { x => 1, y => 2 } };
+    /// let overrides = context! { nested => context! { y => 42 } };
+    /// let merged = defaults.merge(&overrides, true).unwrap();
+    /// let nested = merged.get_attr("nested").unwrap();
+    /// assert_eq!(nested.get_attr("x").unwrap(), Value::from(1));
+    /// assert_eq!(nested.get_attr("y").unwrap(), Value::from(42));
+    /// ```
+    pub fn merge(&self, other: &Value, recursive: bool) -> Result<Value, Error> {
+        fn pairs(value: &Value) -> Result<ValueMap, Error> {
+            match value.0 {
+                ValueRepr::Object(ref obj) if obj.repr() == ObjectRepr::Map => {
+                    Ok(obj.try_iter_pairs().into_iter().flatten().collect())
+                }
+                _ => Err(Error::new(
+                    ErrorKind::InvalidOperation,
+                    format!("cannot merge value of type {}", value.kind()),
+                )),
+            }
+        }
+
+        let mut rv = ok!(pairs(self));
+        for (key, value) in ok!(pairs(other)) {
+            let value = match rv.get(&key) {
+                Some(old)
+                    if recursive
+                        && old.kind() == ValueKind::Map
+                        && value.kind() == ValueKind::Map =>
+                {
+                    ok!(old.merge(&value, true))
+                }
+                _ => value,
+            };
+            rv.insert(key, value);
+        }
+        Ok(Value::from_object(rv))
+    }
+
     /// Returns some reference to the boxed object if it is of type `T`, or None if it isn’t.
     ///
     /// This is basically the "reverse" of [`from_object`](Self::from_object)
@@ -1498,15 +1847,17 @@ impl Value {
     /// keyword arguments use the [`Kwargs`] type.
     ///
     /// Usually the state is already available when it's useful to call this method,
-    /// but when it's not available you can get a fresh template state straight
-    /// from the [`Template`](crate::Template) via [`new_state`](crate::Template::new_state).
+    /// but when it's not available (for instance outside of a render) you can get
+    /// a detached state from the environment via
+    /// [`Environment::empty_state`](crate::Environment::empty_state) or a fresh
+    /// template state straight from the [`Template`](crate::Template) via
+    /// [`new_state`](crate::Template::new_state).
     ///
     /// ```
     /// # use minijinja::{Environment, value::{Value, Kwargs}};
-    /// # let mut env = Environment::new();
-    /// # env.add_template("foo", "").unwrap();
-    /// # let tmpl = env.get_template("foo").unwrap();
-    /// # let state = tmpl.new_state(); let state = &state;
+    /// # let env = Environment::new();
+    /// let state = env.empty_state();
+    /// # let state = &state;
     /// let func = Value::from_function(|v: i64, kwargs: Kwargs| {
     ///     v * kwargs.get::<i64>("mult").unwrap_or(1)
     /// });
@@ -1546,10 +1897,41 @@ impl Value {
         }
     }
 
+    /// Calls the value like [`call`](Self::call) but with a better error.
+    ///
+    /// If the value cannot be called at all, the error names the value it
+    /// was loaded as (if known) and its kind.
+    pub(crate) fn call_named(
+        &self,
+        state: &State,
+        name: Option<&str>,
+        args: &[Value],
+    ) -> Result<Value, Error> {
+        let is_not_callable = |err: &Error| {
+            err.kind() == ErrorKind::InvalidOperation
+                && err.detail() == Some(crate::value::object::NOT_CALLABLE)
+        };
+        if let ValueRepr::Object(ref dy) = self.0 {
+            match dy.call(state, args) {
+                Err(err) if is_not_callable(&err) => {}
+                rv => return rv,
+            }
+        }
+        Err(Error::new(
+            ErrorKind::InvalidOperation,
+            match name {
+                Some(name) => format!("{name} is not callable (value of type {})", self.kind()),
+                None => format!("value of type {} is not callable", self.kind()),
+            },
+        ))
+    }
+
     /// Calls a method on the value.
     ///
     /// The name of the method is `name`, the arguments passed are in the `args`
-    /// slice.
+    /// slice.  Like with [`call`](Self::call) a detached state from
+    /// [`Environment::empty_state`](crate::Environment::empty_state) can be
+    /// used when calling outside of a render.
     pub fn call_method(&self, state: &State, name: &str, args: &[Value]) -> Result<Value, Error> {
         match self._call_method(state, name, args) {
             Ok(rv) => Ok(rv),
@@ -1574,8 +1956,7 @@ impl Value {
         }
     }
 
-    #[cfg(feature = "builtins")]
-    pub(crate) fn get_path(&self, path: &str) -> Result<Value, Error> {
+    pub(crate) fn get_path_checked(&self, path: &str) -> Result<Value, Error> {
         let mut rv = self.clone();
         for part in path.split('.') {
             if let Ok(num) = part.parse::<usize>() {
@@ -1589,7 +1970,7 @@ impl Value {
 
     #[cfg(feature = "builtins")]
     pub(crate) fn get_path_or_default(&self, path: &str, default: &Value) -> Value {
-        match self.get_path(path) {
+        match self.get_path_checked(path) {
             Err(_) => default.clone(),
             Ok(val) if val.is_undefined() => default.clone(),
             Ok(val) => val,
@@ -1639,6 +2020,7 @@ impl Serialize for Value {
                 ObjectRepr::Plain => serializer.serialize_str(&o.to_string()),
                 ObjectRepr::Seq | ObjectRepr::Iterable => {
                     use serde::ser::SerializeSeq;
+                    let _guard = ok!(mark_object_serialization::<S::Error>(o.addr()));
                     let mut seq = ok!(serializer.serialize_seq(o.enumerator_len()));
                     if let Some(iter) = o.try_iter() {
                         for item in iter {
@@ -1650,6 +2032,7 @@ impl Serialize for Value {
                 }
                 ObjectRepr::Map => {
                     use serde::ser::SerializeMap;
+                    let _guard = ok!(mark_object_serialization::<S::Error>(o.addr()));
                     let mut map = ok!(serializer.serialize_map(None));
                     if let Some(iter) = o.try_iter_pairs() {
                         for (key, value) in iter {
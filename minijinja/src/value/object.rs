@@ -8,6 +8,9 @@ use crate::error::{Error, ErrorKind};
 use crate::value::{mapped_enumerator, Value};
 use crate::vm::State;
 
+/// The error detail of the default [`Object::call`] implementation.
+pub(crate) const NOT_CALLABLE: &str = "object is not callable";
+
 /// A trait that represents a dynamic object.
 ///
 /// There is a type erased wrapper of this trait available called
@@ -217,10 +220,7 @@ pub trait Object: fmt::Debug + Send + Sync {
     /// [`InvalidOperation`](crate::ErrorKind::InvalidOperation) error.
     fn call(self: &Arc<Self>, state: &State<'_, '_>, args: &[Value]) -> Result<Value, Error> {
         let (_, _) = (state, args);
-        Err(Error::new(
-            ErrorKind::InvalidOperation,
-            "object is not callable",
-        ))
+        Err(Error::new(ErrorKind::InvalidOperation, NOT_CALLABLE))
     }
 
     /// The engine calls this to invoke a method on the object.
@@ -628,6 +628,11 @@ impl DynObject {
     pub(crate) fn is_same_object(&self, other: &DynObject) -> bool {
         self.ptr == other.ptr && self.vtable == other.vtable
     }
+
+    /// Returns the address of the object.
+    pub(crate) fn addr(&self) -> usize {
+        self.ptr as usize
+    }
 }
 
 impl Hash for DynObject {
@@ -1,4 +1,3 @@
-use std::collections::BTreeMap;
 use std::fmt;
 
 use serde::{ser, Serialize, Serializer};
@@ -30,30 +29,108 @@ impl serde::ser::Error for InvalidValue {
     }
 }
 
+/// A segment of the path to the value that is currently being serialized.
+#[derive(Clone, Copy)]
+pub enum PathSegment<'a> {
+    Field(&'static str),
+    Index(usize),
+    Key(&'a Value),
+}
+
+/// The path to the value that is currently being serialized.
+///
+/// The path is a linked list of segments that lives on the stack.  It's
+/// only formatted into a string if the serialization of a value fails.
+pub struct Path<'a> {
+    segment: PathSegment<'a>,
+    parent: Option<&'a Path<'a>>,
+}
+
+impl fmt::Display for Path<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(parent) = self.parent {
+            ok!(write!(f, "{parent}."));
+        }
+        match self.segment {
+            PathSegment::Field(name) => f.write_str(name),
+            PathSegment::Index(idx) => write!(f, "{idx}"),
+            PathSegment::Key(key) => write!(f, "{key}"),
+        }
+    }
+}
+
 /// Transforms a serializable value to a value object.
 ///
 /// This neither fails nor panics.  For objects that cannot be represented
 /// the value might be represented as a half broken error object.
 pub fn transform<T: Serialize>(value: T) -> Value {
-    match value.serialize(ValueSerializer) {
+    transform_in(None, &value)
+}
+
+/// Transforms a value that is held by a container at the given segment.
+///
+/// The segment is reported as part of the path to the value if the
+/// serialization fails.
+pub fn transform_at<T: Serialize + ?Sized>(
+    parent: Option<&Path<'_>>,
+    segment: PathSegment<'_>,
+    value: &T,
+) -> Value {
+    transform_in(Some(&Path { segment, parent }), value)
+}
+
+/// Transforms a value that is held by an enum variant at the given segment.
+///
+/// This works like [`transform_at`] but also reports the name of the variant
+/// in the path as the variant is represented as an extra map in the value.
+fn transform_in_variant<T: Serialize + ?Sized>(
+    parent: Option<&Path<'_>>,
+    variant: &'static str,
+    segment: PathSegment<'_>,
+    value: &T,
+) -> Value {
+    let parent = Path {
+        segment: PathSegment::Field(variant),
+        parent,
+    };
+    transform_at(Some(&parent), segment, value)
+}
+
+fn transform_in<T: Serialize + ?Sized>(path: Option<&Path<'_>>, value: &T) -> Value {
+    match value.serialize(ValueSerializer { path }) {
         Ok(rv) => rv,
-        Err(invalid) => Value::from(Error::new(ErrorKind::BadSerialization, invalid.0)),
+        Err(invalid) => Value::from(Error::new(
+            ErrorKind::BadSerialization,
+            match path {
+                Some(path) => format!("{} (at {})", invalid.0, path),
+                None => invalid.0,
+            },
+        )),
     }
 }
 
-pub struct ValueSerializer;
+/// Wraps the value of an enum variant in a map keyed by the variant name.
+fn make_variant(variant: &'static str, value: Value) -> Value {
+    let mut map = value_map_with_capacity(1);
+    map.insert(variant.into(), value);
+    Value::from_object(map)
+}
+
+pub struct ValueSerializer<'a> {
+    path: Option<&'a Path<'a>>,
+}
 
-impl Serializer for ValueSerializer {
+impl<'a> Serializer for ValueSerializer<'a> {
     type Ok = Value;
     type Error = InvalidValue;
 
-    type SerializeSeq = SerializeSeq;
-    type SerializeTuple = SerializeTuple;
-    type SerializeTupleStruct = SerializeTupleStruct;
-    type SerializeTupleVariant = SerializeTupleVariant;
-    type SerializeMap = SerializeMap;
-    type SerializeStruct = SerializeStruct;
-    type SerializeStructVariant = SerializeStructVariant;
+    type SerializeSeq = SerializeSeq<'a>;
+    type SerializeTuple = SerializeTuple<'a>;
+    type SerializeTupleStruct = SerializeTupleStruct<'a>;
+    type SerializeTupleVariant = SerializeTupleVariant<'a>;
+    type SerializeMap = SerializeMap<'a>;
+    type SerializeStruct = SerializeStruct<'a>;
+    type SerializeStructVariant = SerializeStructVariant<'a>;
 
     fn serialize_bool(self, v: bool) -> Result<Value, InvalidValue> {
         Ok(ValueRepr::Bool(v).into())
@@ -127,7 +204,7 @@ impl Serializer for ValueSerializer {
     where
         T: Serialize + ?Sized,
     {
-        Ok(transform(value))
+        Ok(transform_in(self.path, value))
     }
 
     fn serialize_unit(self) -> Result<Value, InvalidValue> {
@@ -155,7 +232,7 @@ impl Serializer for ValueSerializer {
     where
         T: Serialize + ?Sized,
     {
-        Ok(transform(value))
+        Ok(transform_in(self.path, value))
     }
 
     fn serialize_newtype_variant<T>(
@@ -168,20 +245,23 @@ impl Serializer for ValueSerializer {
     where
         T: Serialize + ?Sized,
     {
-        let mut map = value_map_with_capacity(1);
-        map.insert(variant.into(), transform(value));
-        Ok(Value::from_object(map))
+        Ok(make_variant(
+            variant,
+            transform_at(self.path, PathSegment::Field(variant), value),
+        ))
     }
 
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, InvalidValue> {
         Ok(SerializeSeq {
             elements: Vec::with_capacity(untrusted_size_hint(len.unwrap_or(0))),
+            path: self.path,
         })
     }
 
     fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, InvalidValue> {
         Ok(SerializeTuple {
             elements: Vec::with_capacity(untrusted_size_hint(len)),
+            path: self.path,
         })
     }
 
@@ -193,7 +273,7 @@ impl Serializer for ValueSerializer {
         Ok(if name == VALUE_HANDLE_MARKER {
             SerializeTupleStruct::Handle(None)
         } else {
-            SerializeTupleStruct::Fields(Vec::with_capacity(untrusted_size_hint(len)))
+            SerializeTupleStruct::Fields(Vec::with_capacity(untrusted_size_hint(len)), self.path)
         })
     }
 
@@ -207,6 +287,7 @@ impl Serializer for ValueSerializer {
         Ok(SerializeTupleVariant {
             name: variant,
             fields: Vec::with_capacity(untrusted_size_hint(len)),
+            path: self.path,
         })
     }
 
@@ -214,6 +295,7 @@ impl Serializer for ValueSerializer {
         Ok(SerializeMap {
             entries: value_map_with_capacity(len.unwrap_or(0)),
             key: None,
+            path: self.path,
         })
     }
 
@@ -224,6 +306,7 @@ impl Serializer for ValueSerializer {
     ) -> Result<Self::SerializeStruct, InvalidValue> {
         Ok(SerializeStruct {
             fields: value_map_with_capacity(len),
+            path: self.path,
         })
     }
 
@@ -237,15 +320,17 @@ impl Serializer for ValueSerializer {
         Ok(SerializeStructVariant {
             variant,
             map: value_map_with_capacity(len),
+            path: self.path,
         })
     }
 }
 
-pub struct SerializeSeq {
+pub struct SerializeSeq<'a> {
     elements: Vec<Value>,
+    path: Option<&'a Path<'a>>,
 }
 
-impl ser::SerializeSeq for SerializeSeq {
+impl ser::SerializeSeq for SerializeSeq<'_> {
     type Ok = Value;
     type Error = InvalidValue;
 
@@ -253,7 +338,11 @@ impl ser::SerializeSeq for SerializeSeq {
     where
         T: Serialize + ?Sized,
     {
-        self.elements.push(transform(value));
+        self.elements.push(transform_at(
+            self.path,
+            PathSegment::Index(self.elements.len()),
+            value,
+        ));
         Ok(())
     }
 
@@ -262,11 +351,12 @@ impl ser::SerializeSeq for SerializeSeq {
     }
 }
 
-pub struct SerializeTuple {
+pub struct SerializeTuple<'a> {
     elements: Vec<Value>,
+    path: Option<&'a Path<'a>>,
 }
 
-impl ser::SerializeTuple for SerializeTuple {
+impl ser::SerializeTuple for SerializeTuple<'_> {
     type Ok = Value;
     type Error = InvalidValue;
 
@@ -274,7 +364,11 @@ impl ser::SerializeTuple for SerializeTuple {
     where
         T: Serialize + ?Sized,
     {
-        self.elements.push(transform(value));
+        self.elements.push(transform_at(
+            self.path,
+            PathSegment::Index(self.elements.len()),
+            value,
+        ));
         Ok(())
     }
 
@@ -283,12 +377,12 @@ impl ser::SerializeTuple for SerializeTuple {
     }
 }
 
-pub enum SerializeTupleStruct {
+pub enum SerializeTupleStruct<'a> {
     Handle(Option<u32>),
-    Fields(Vec<Value>),
+    Fields(Vec<Value>, Option<&'a Path<'a>>),
 }
 
-impl ser::SerializeTupleStruct for SerializeTupleStruct {
+impl ser::SerializeTupleStruct for SerializeTupleStruct<'_> {
     type Ok = Value;
     type Error = InvalidValue;
 
@@ -300,8 +394,8 @@ impl ser::SerializeTupleStruct for SerializeTupleStruct {
             SerializeTupleStruct::Handle(ref mut handle) => {
                 *handle = transform(value).as_usize().map(|x| x as u32);
             }
-            SerializeTupleStruct::Fields(ref mut fields) => {
-                fields.push(transform(value));
+            SerializeTupleStruct::Fields(ref mut fields, path) => {
+                fields.push(transform_at(*path, PathSegment::Index(fields.len()), value));
             }
         }
         Ok(())
@@ -314,17 +408,18 @@ impl ser::SerializeTupleStruct for SerializeTupleStruct {
                     .and_then(|h| handles.borrow_mut().remove(&h))
                     .ok_or_else(|| InvalidValue("value handle not in registry".into()))
             }),
-            SerializeTupleStruct::Fields(fields) => Ok(Value::from_object(fields)),
+            SerializeTupleStruct::Fields(fields, _) => Ok(Value::from_object(fields)),
         }
     }
 }
 
-pub struct SerializeTupleVariant {
+pub struct SerializeTupleVariant<'a> {
     name: &'static str,
     fields: Vec<Value>,
+    path: Option<&'a Path<'a>>,
 }
 
-impl ser::SerializeTupleVariant for SerializeTupleVariant {
+impl ser::SerializeTupleVariant for SerializeTupleVariant<'_> {
     type Ok = Value;
     type Error = InvalidValue;
 
@@ -332,23 +427,27 @@ impl ser::SerializeTupleVariant for SerializeTupleVariant {
     where
         T: Serialize + ?Sized,
     {
-        self.fields.push(transform(value));
+        self.fields.push(transform_in_variant(
+            self.path,
+            self.name,
+            PathSegment::Index(self.fields.len()),
+            value,
+        ));
         Ok(())
     }
 
     fn end(self) -> Result<Value, InvalidValue> {
-        let mut map = value_map_with_capacity(1);
-        map.insert(self.name.into(), Value::from_object(self.fields));
-        Ok(Value::from_object(map))
+        Ok(make_variant(self.name, Value::from_object(self.fields)))
     }
 }
 
-pub struct SerializeMap {
+pub struct SerializeMap<'a> {
     entries: ValueMap,
     key: Option<Value>,
+    path: Option<&'a Path<'a>>,
 }
 
-impl ser::SerializeMap for SerializeMap {
+impl ser::SerializeMap for SerializeMap<'_> {
     type Ok = Value;
     type Error = InvalidValue;
 
@@ -356,7 +455,7 @@ impl ser::SerializeMap for SerializeMap {
     where
         T: Serialize + ?Sized,
     {
-        match key.serialize(ValueSerializer) {
+        match key.serialize(ValueSerializer { path: self.path }) {
             Ok(key) => self.key = Some(key),
             Err(_) => self.key = None,
         }
@@ -368,7 +467,8 @@ impl ser::SerializeMap for SerializeMap {
         T: Serialize + ?Sized,
     {
         if let Some(key) = self.key.take() {
-            self.entries.insert(key, transform(value));
+            let value = transform_at(self.path, PathSegment::Key(&key), value);
+            self.entries.insert(key, value);
         }
         Ok(())
     }
@@ -382,18 +482,20 @@ impl ser::SerializeMap for SerializeMap {
         K: Serialize + ?Sized,
         V: Serialize + ?Sized,
     {
-        if let Ok(key) = key.serialize(ValueSerializer) {
-            self.entries.insert(key, transform(value));
+        if let Ok(key) = key.serialize(ValueSerializer { path: self.path }) {
+            let value = transform_at(self.path, PathSegment::Key(&key), value);
+            self.entries.insert(key, value);
         }
         Ok(())
     }
 }
 
-pub struct SerializeStruct {
+pub struct SerializeStruct<'a> {
     fields: ValueMap,
+    path: Option<&'a Path<'a>>,
 }
 
-impl ser::SerializeStruct for SerializeStruct {
+impl ser::SerializeStruct for SerializeStruct<'_> {
     type Ok = Value;
     type Error = InvalidValue;
 
@@ -401,7 +503,10 @@ impl ser::SerializeStruct for SerializeStruct {
     where
         T: Serialize + ?Sized,
     {
-        self.fields.insert(key.into(), transform(value));
+        self.fields.insert(
+            key.into(),
+            transform_at(self.path, PathSegment::Field(key), value),
+        );
         Ok(())
     }
 
@@ -410,12 +515,13 @@ impl ser::SerializeStruct for SerializeStruct {
     }
 }
 
-pub struct SerializeStructVariant {
+pub struct SerializeStructVariant<'a> {
     variant: &'static str,
     map: ValueMap,
+    path: Option<&'a Path<'a>>,
 }
 
-impl ser::SerializeStructVariant for SerializeStructVariant {
+impl ser::SerializeStructVariant for SerializeStructVariant<'_> {
     type Ok = Value;
     type Error = InvalidValue;
 
@@ -423,13 +529,14 @@ impl ser::SerializeStructVariant for SerializeStructVariant {
     where
         T: Serialize + ?Sized,
     {
-        self.map.insert(key.into(), transform(value));
+        self.map.insert(
+            key.into(),
+            transform_in_variant(self.path, self.variant, PathSegment::Field(key), value),
+        );
         Ok(())
     }
 
     fn end(self) -> Result<Value, InvalidValue> {
-        let mut rv = BTreeMap::new();
-        rv.insert(self.variant.to_string(), Value::from_object(self.map));
-        Ok(rv.into())
+        Ok(make_variant(self.variant, Value::from_object(self.map)))
     }
 }
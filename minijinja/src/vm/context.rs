@@ -5,7 +5,7 @@ use std::sync::Arc;
 
 use crate::environment::Environment;
 use crate::error::{Error, ErrorKind};
-use crate::value::{Value, ValueIter};
+use crate::value::{DebugDump, Value, ValueIter};
 use crate::vm::loop_object::Loop;
 
 #[cfg(feature = "macros")]
@@ -153,7 +153,7 @@ impl fmt::Debug for Context<'_> {
             for frame in ctx.stack.iter().rev() {
                 for (key, value) in frame.locals.iter() {
                     if !seen.contains(&Cow::Borrowed(*key)) {
-                        m.entry(&key, value);
+                        m.entry(&key, &DebugDump(value));
                         seen.insert(Cow::Borrowed(key));
                     }
                 }
@@ -170,7 +170,7 @@ impl fmt::Debug for Context<'_> {
                         if let Some(str_key) = key.as_str() {
                             if !seen.contains(&Cow::Borrowed(str_key)) {
                                 if let Ok(value) = frame.ctx.get_item(&key) {
-                                    m.entry(&str_key, &value);
+                                    m.entry(&str_key, &DebugDump(&value));
                                     seen.insert(Cow::Owned(str_key.to_owned()));
                                 }
                             }
@@ -350,10 +350,7 @@ impl<'env> Context<'env> {
 
     fn check_depth(&self) -> Result<(), Error> {
         if self.depth() > self.recursion_limit {
-            return Err(Error::new(
-                ErrorKind::InvalidOperation,
-                "recursion limit exceeded",
-            ));
+            return Err(Error::from(ErrorKind::RecursionLimitExceeded));
         }
         Ok(())
     }
@@ -39,7 +39,8 @@ impl FuelTracker {
 
     /// Returns the remaining fuel.
     pub fn remaining(&self) -> u64 {
-        self.remaining.load(Ordering::Relaxed) as _
+        // the counter goes negative once the fuel ran out
+        self.remaining.load(Ordering::Relaxed).max(0) as _
     }
 
     /// Returns the consumed fuel.
@@ -101,8 +101,14 @@ impl Object for Loop {
                     .unwrap_or(Value::UNDEFINED),
             ),
             "first" => Some(Value::from(idx == 0)),
-            "last" => Some(len.map_or(Value::from(false), |len| {
-                Value::from(len == 0 || idx == len - 1)
+            "last" => Some(Value::from(match len {
+                Some(len) => len == 0 || idx == len - 1,
+                // without a known length the lookahead item tells us if
+                // this is the last item.
+                #[cfg(feature = "adjacent_loop_items")]
+                None => self.value_triple.lock().unwrap().2.is_none(),
+                #[cfg(not(feature = "adjacent_loop_items"))]
+                None => false,
             })),
             "depth" => Some(Value::from(self.depth + 1)),
             "depth0" => Some(Value::from(self.depth)),
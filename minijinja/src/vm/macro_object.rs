@@ -7,6 +7,7 @@ use crate::output::Output;
 use crate::utils::AutoEscape;
 use crate::value::{Enumerator, Kwargs, Object, Value};
 use crate::vm::state::State;
+use crate::vm::trace::{trace_end, trace_start, TraceEventKind};
 use crate::vm::Vm;
 
 pub(crate) struct Macro {
@@ -127,7 +128,8 @@ impl Object for Macro {
         // Because macros cannot return anything other than strings (most importantly they)
         // can't return other macros this is however not an issue, as modifications in the
         // macro cannot leak out.
-        ok!(vm.eval_macro(
+        let trace = trace_start(state);
+        let result = vm.eval_macro(
             instructions,
             *offset,
             self.closure.clone(),
@@ -135,8 +137,17 @@ impl Object for Macro {
             caller,
             &mut out,
             state,
-            arg_values
-        ));
+            arg_values,
+        );
+        trace_end(
+            state,
+            trace,
+            TraceEventKind::Macro,
+            self.name.as_str().unwrap_or_default(),
+            instructions,
+            *offset,
+        );
+        ok!(result);
 
         Ok(if !matches!(state.auto_escape(), AutoEscape::None) {
             Value::from_safe_string(rv)
@@ -4,23 +4,27 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 
 use crate::compiler::instructions::{
-    Instruction, Instructions, LOOP_FLAG_RECURSIVE, LOOP_FLAG_WITH_LOOP_VAR, MAX_LOCALS,
+    Instruction, Instructions, LOOP_FLAG_FILTERED, LOOP_FLAG_RECURSIVE, LOOP_FLAG_WITH_LOOP_VAR,
+    MAX_LOCALS,
 };
 use crate::environment::Environment;
 use crate::error::{Error, ErrorKind};
 use crate::output::{CaptureMode, Output};
 use crate::utils::{untrusted_size_hint, AutoEscape, UndefinedBehavior};
 use crate::value::namespace_object::Namespace;
-use crate::value::{ops, value_map_with_capacity, Kwargs, ObjectRepr, Value, ValueMap};
+use crate::value::{ops, value_map_with_capacity, Kwargs, ObjectRepr, Value};
 use crate::vm::context::{Frame, LoopState, Stack};
 use crate::vm::loop_object::Loop;
 use crate::vm::state::BlockStack;
+use crate::vm::trace::{trace_end, trace_start};
 
 #[cfg(feature = "macros")]
 use crate::vm::closure_object::Closure;
 
 pub(crate) use crate::vm::context::Context;
 pub use crate::vm::state::State;
+pub use crate::vm::trace::{TraceEvent, TraceEventKind};
+pub use crate::vm::warning::Warning;
 
 #[cfg(feature = "macros")]
 mod closure_object;
@@ -31,6 +35,8 @@ mod loop_object;
 #[cfg(feature = "macros")]
 mod macro_object;
 mod state;
+mod trace;
+mod warning;
 
 // the cost of a single include against the stack limit.
 #[cfg(feature = "multi_template")]
@@ -40,6 +46,9 @@ const INCLUDE_RECURSION_COST: usize = 10;
 #[cfg(feature = "macros")]
 const MACRO_RECURSION_COST: usize = 4;
 
+// the cost of evaluating an expression from a state against the stack limit.
+const EXPR_RECURSION_COST: usize = 4;
+
 /// Helps to evaluate something.
 #[cfg_attr(feature = "internal_debug", derive(Debug))]
 pub struct Vm<'env> {
@@ -88,6 +97,7 @@ impl<'env> Vm<'env> {
         blocks: &'template BTreeMap<&'env str, Instructions<'env>>,
         out: &mut Output,
         auto_escape: AutoEscape,
+        undefined_behavior: UndefinedBehavior,
     ) -> Result<(Option<Value>, State<'template, 'env>), Error> {
         let mut state = State::new(
             self.env,
@@ -96,6 +106,7 @@ impl<'env> Vm<'env> {
             instructions,
             prepare_blocks(blocks),
         );
+        state.undefined_behavior = undefined_behavior;
         self.eval_state(&mut state, out).map(|x| (x, state))
     }
 
@@ -119,15 +130,19 @@ impl<'env> Vm<'env> {
             ctx.store("caller", caller);
         }
         ok!(ctx.incr_depth(state.ctx.depth() + MACRO_RECURSION_COST));
+        let mut outer_templates = state.outer_templates.clone();
+        outer_templates.push(state.instructions.name());
         self.do_eval(
             &mut State {
                 env: self.env,
                 ctx,
                 current_block: None,
                 auto_escape: state.auto_escape(),
+                undefined_behavior: state.undefined_behavior(),
                 instructions,
                 blocks: BTreeMap::default(),
                 loaded_templates: Default::default(),
+                outer_templates,
                 #[cfg(feature = "macros")]
                 id: state.id,
                 #[cfg(feature = "macros")]
@@ -136,6 +151,7 @@ impl<'env> Vm<'env> {
                 closure_tracker: state.closure_tracker.clone(),
                 #[cfg(feature = "fuel")]
                 fuel_tracker: state.fuel_tracker.clone(),
+                warnings: state.warnings.clone(),
             },
             out,
             Stack::from(args),
@@ -143,6 +159,47 @@ impl<'env> Vm<'env> {
         )
     }
 
+    /// Evaluates expression instructions on behalf of an existing state.
+    ///
+    /// The expression sees the passed root value as context but otherwise
+    /// shares the undefined behavior, auto escaping, fuel, macros and the
+    /// template stack with the given state.
+    pub(crate) fn eval_expr_in_state(
+        &self,
+        instructions: &Instructions<'env>,
+        root: Value,
+        state: &State<'_, 'env>,
+    ) -> Result<Value, Error> {
+        let mut ctx = Context::new_with_frame(Frame::new(root), self.env.recursion_limit());
+        ok!(ctx.incr_depth(state.ctx.depth() + EXPR_RECURSION_COST));
+        Ok(ok!(self.do_eval(
+            &mut State {
+                env: self.env,
+                ctx,
+                current_block: state.current_block,
+                auto_escape: state.auto_escape(),
+                undefined_behavior: state.undefined_behavior(),
+                instructions,
+                blocks: BTreeMap::default(),
+                loaded_templates: Default::default(),
+                outer_templates: state.outer_templates.clone(),
+                #[cfg(feature = "macros")]
+                id: state.id,
+                #[cfg(feature = "macros")]
+                macros: state.macros.clone(),
+                #[cfg(feature = "macros")]
+                closure_tracker: state.closure_tracker.clone(),
+                #[cfg(feature = "fuel")]
+                fuel_tracker: state.fuel_tracker.clone(),
+                warnings: state.warnings.clone(),
+            },
+            &mut Output::null(),
+            Stack::default(),
+            0,
+        ))
+        .expect("expression evaluation did not leave value on stack"))
+    }
+
     /// This is the actual evaluation loop that works with a specific context.
     #[inline(always)]
     fn eval_state(
@@ -224,10 +281,12 @@ impl<'env> Vm<'env> {
                     // stashed away (which means we found an extends tag which invoked
                     // `LoadBlocks`).  If we do find instructions, we reset back to 0
                     // from the new instructions.
+                    let old_name = state.instructions.name();
                     state.instructions = match parent_instructions.take() {
                         Some(instr) => instr,
                         None => break,
                     };
+                    state.outer_templates.push(old_name);
                     out.end_capture(AutoEscape::None);
                     pc = 0;
                     // because we swap out the instructions we also need to unload all
@@ -321,6 +380,7 @@ impl<'env> Vm<'env> {
                 }
                 Instruction::GetAttr(name) => {
                     a = stack.pop();
+                    ctx_ok!(state.env().check_attribute_access(&a, name));
                     // This is a common enough operation that it's interesting to consider a fast
                     // path here.  This is slightly faster than the regular attr lookup because we
                     // do not need to pass down the error object for the more common success case.
@@ -346,6 +406,9 @@ impl<'env> Vm<'env> {
                 Instruction::GetItem => {
                     a = stack.pop();
                     b = stack.pop();
+                    if let Some(name) = a.as_str() {
+                        ctx_ok!(state.env().check_attribute_access(&b, name));
+                    }
                     stack.push(match b.get_item_opt(&a) {
                         Some(value) => assert_valid!(value),
                         None => ctx_ok!(undefined_behavior.handle_undefined(b.is_undefined())),
@@ -375,14 +438,14 @@ impl<'env> Vm<'env> {
                     stack.push(Value::from_object(map))
                 }
                 Instruction::BuildKwargs(pair_count) => {
-                    let mut map = value_map_with_capacity(*pair_count);
+                    let mut pairs = Vec::with_capacity(*pair_count);
                     stack.reverse_top(*pair_count * 2);
                     for _ in 0..*pair_count {
                         let key = stack.pop();
                         let value = stack.pop();
-                        map.insert(key, value);
+                        pairs.push((key, value));
                     }
-                    stack.push(Kwargs::wrap(map))
+                    stack.push(Kwargs::wrap(pairs))
                 }
                 Instruction::MergeKwargs(count) => {
                     let mut kwargs_sources = Vec::new();
@@ -391,9 +454,9 @@ impl<'env> Vm<'env> {
                     }
                     kwargs_sources.reverse();
                     let values: &[Value] = &kwargs_sources;
-                    let mut rv = ValueMap::new();
+                    let mut rv = Vec::new();
                     for value in values {
-                        ctx_ok!(self.env.undefined_behavior().assert_iterable(value));
+                        ctx_ok!(undefined_behavior.assert_iterable(value));
                         let iter = ctx_ok!(value
                             .as_object()
                             .filter(|x| x.repr() == ObjectRepr::Map)
@@ -407,9 +470,7 @@ impl<'env> Vm<'env> {
                                     ),
                                 )
                             }));
-                        for (key, value) in iter {
-                            rv.insert(key, value);
-                        }
+                        rv.extend(iter);
                     }
                     stack.push(Kwargs::wrap(rv));
                 }
@@ -495,7 +556,15 @@ impl<'env> Vm<'env> {
                 }
                 Instruction::PushLoop(flags) => {
                     a = stack.pop();
-                    ctx_ok!(self.push_loop(state, a, *flags, pc, next_loop_recursion_jump.take()));
+                    // loops without the loop var are the filter passes of filtered
+                    // loops.  A pending recursion jump belongs to the loop that
+                    // follows the filter pass.
+                    let recursion_jump = if *flags & LOOP_FLAG_WITH_LOOP_VAR != 0 {
+                        next_loop_recursion_jump.take()
+                    } else {
+                        None
+                    };
+                    ctx_ok!(self.push_loop(state, a, *flags, pc, recursion_jump));
                 }
                 Instruction::Iterate(jump_target) => {
                     let l = state.ctx.current_loop().unwrap();
@@ -557,9 +626,26 @@ impl<'env> Vm<'env> {
                 #[cfg(feature = "multi_template")]
                 Instruction::CallBlock(name) => {
                     if parent_instructions.is_none() && !out.is_discarding() {
-                        self.call_block(name, state, out)?;
+                        let trace = trace_start(state);
+                        let rv = self.call_block(name, state, out);
+                        trace_end(
+                            state,
+                            trace,
+                            TraceEventKind::Block,
+                            name,
+                            state.instructions,
+                            pc,
+                        );
+                        rv?;
                     }
                 }
+                #[cfg(feature = "multi_template")]
+                Instruction::RequiredBlock(name) => {
+                    bail!(Error::new(
+                        ErrorKind::UnknownBlock,
+                        format!("required block '{}' was not overridden", name),
+                    ));
+                }
                 Instruction::PushAutoEscape => {
                     a = stack.pop();
                     auto_escape_stack.push(state.auto_escape);
@@ -587,7 +673,17 @@ impl<'env> Vm<'env> {
                         }));
                     let args = stack.get_call_args(*arg_count);
                     let arg_count = args.len();
-                    a = ctx_ok!(filter.apply_to(state, args));
+                    let trace = trace_start(state);
+                    let rv = filter.apply_to(state, args);
+                    trace_end(
+                        state,
+                        trace,
+                        TraceEventKind::Filter,
+                        name,
+                        state.instructions,
+                        pc,
+                    );
+                    a = ctx_ok!(rv);
                     stack.drop_top(arg_count);
                     stack.push(a);
                 }
@@ -600,7 +696,17 @@ impl<'env> Vm<'env> {
                     }));
                     let args = stack.get_call_args(*arg_count);
                     let arg_count = args.len();
-                    let rv = ctx_ok!(test.perform(state, args));
+                    let trace = trace_start(state);
+                    let rv = test.perform(state, args);
+                    trace_end(
+                        state,
+                        trace,
+                        TraceEventKind::Test,
+                        name,
+                        state.instructions,
+                        pc,
+                    );
+                    let rv = ctx_ok!(rv);
                     stack.drop_top(arg_count);
                     stack.push(Value::from(rv));
                 }
@@ -627,7 +733,26 @@ impl<'env> Vm<'env> {
                         // recurse_loop! macro itself will perform a jump and not return here.
                         recurse_loop!(true);
                     } else if let Some(func) = state.lookup(name) {
-                        ctx_ok!(func.call(state, args))
+                        // under chainable undefined behavior, calling an undefined
+                        // value is undefined like attribute access on it is.
+                        if func.is_undefined() && undefined_behavior == UndefinedBehavior::Chainable
+                        {
+                            Value::UNDEFINED
+                        } else {
+                            let trace = trace_start(state);
+                            let rv = func.call_named(state, Some(name), args);
+                            trace_end(
+                                state,
+                                trace,
+                                TraceEventKind::Function,
+                                name,
+                                state.instructions,
+                                pc,
+                            );
+                            ctx_ok!(rv)
+                        }
+                    } else if undefined_behavior == UndefinedBehavior::Chainable {
+                        Value::UNDEFINED
                     } else {
                         bail!(Error::new(
                             ErrorKind::UnknownFunction,
@@ -641,14 +766,36 @@ impl<'env> Vm<'env> {
                 Instruction::CallMethod(name, arg_count) => {
                     let args = stack.get_call_args(*arg_count);
                     let arg_count = args.len();
-                    a = ctx_ok!(args[0].call_method(state, name, &args[1..]));
+                    if args[0].is_undefined() && undefined_behavior == UndefinedBehavior::Chainable
+                    {
+                        a = Value::UNDEFINED;
+                    } else {
+                        ctx_ok!(state.env().check_attribute_access(&args[0], name));
+                        let trace = trace_start(state);
+                        let rv = args[0].call_method(state, name, &args[1..]);
+                        trace_end(
+                            state,
+                            trace,
+                            TraceEventKind::Method,
+                            name,
+                            state.instructions,
+                            pc,
+                        );
+                        a = ctx_ok!(rv);
+                    }
                     stack.drop_top(arg_count);
                     stack.push(a);
                 }
                 Instruction::CallObject(arg_count) => {
                     let args = stack.get_call_args(*arg_count);
                     let arg_count = args.len();
-                    a = ctx_ok!(args[0].call(state, &args[1..]));
+                    a = if args[0].is_undefined()
+                        && undefined_behavior == UndefinedBehavior::Chainable
+                    {
+                        Value::UNDEFINED
+                    } else {
+                        ctx_ok!(args[0].call_named(state, None, &args[1..]))
+                    };
                     stack.drop_top(arg_count);
                     stack.push(a);
                 }
@@ -784,6 +931,11 @@ impl<'env> Vm<'env> {
             };
 
             let (new_instructions, new_blocks) = ok!(tmpl.instructions_and_blocks());
+            ok!(state.ctx.incr_depth(INCLUDE_RECURSION_COST));
+            // an included template that extends another template pushes
+            // additional frames, so restore the original stack afterwards.
+            let old_outer_templates = state.outer_templates.clone();
+            state.outer_templates.push(state.instructions.name());
             let old_escape = mem::replace(&mut state.auto_escape, tmpl.initial_auto_escape());
             let old_instructions = mem::replace(&mut state.instructions, new_instructions);
             let old_blocks = mem::replace(&mut state.blocks, prepare_blocks(new_blocks));
@@ -791,7 +943,6 @@ impl<'env> Vm<'env> {
             // to forget about the templates that an include triggered by the
             // time the include finishes.
             let old_loaded_templates = state.loaded_templates.clone();
-            ok!(state.ctx.incr_depth(INCLUDE_RECURSION_COST));
             let rv;
             #[cfg(feature = "macros")]
             {
@@ -804,6 +955,7 @@ impl<'env> Vm<'env> {
                 rv = self.eval_state(state, out);
             }
             state.ctx.decr_depth(INCLUDE_RECURSION_COST);
+            state.outer_templates = old_outer_templates;
             state.loaded_templates = old_loaded_templates;
             state.auto_escape = old_escape;
             state.instructions = old_instructions;
@@ -939,9 +1091,17 @@ impl<'env> Vm<'env> {
             let old_block = mem::replace(&mut state.current_block, Some(name));
             let old_instructions =
                 mem::replace(&mut state.instructions, block_stack.instructions());
+            let outer_template = old_instructions.name();
+            let new_frame = outer_template != state.instructions.name();
+            if new_frame {
+                state.outer_templates.push(outer_template);
+            }
             state.ctx.push_frame(Frame::default())?;
             let rv = self.eval_state(state, out);
             state.ctx.pop_frame();
+            if new_frame {
+                state.outer_templates.pop();
+            }
             state.instructions = old_instructions;
             state.current_block = old_block;
             rv
@@ -993,17 +1153,30 @@ impl<'env> Vm<'env> {
             (lower, Some(upper)) if lower == upper => Some(lower),
             _ => None,
         };
-        let depth = state
-            .ctx
-            .current_loop()
-            .filter(|x| x.recurse_jump_target.is_some())
-            .map_or(0, |x| x.object.depth + 1);
+        // only loops entered by recursing via `loop()` are nested deeper,
+        // every other loop starts out at the top level.
+        let depth = if current_recursion_jump.is_some() {
+            state
+                .ctx
+                .current_loop()
+                .filter(|x| x.recurse_jump_target.is_some())
+                .map_or(0, |x| x.object.depth + 1)
+        } else {
+            0
+        };
         let recursive = flags & LOOP_FLAG_RECURSIVE != 0;
         let with_loop_var = flags & LOOP_FLAG_WITH_LOOP_VAR != 0;
         ok!(state.ctx.push_frame(Frame {
             current_loop: Some(LoopState {
                 with_loop_var,
-                recurse_jump_target: if recursive { Some(pc) } else { None },
+                recurse_jump_target: if !recursive {
+                    None
+                } else if flags & LOOP_FLAG_FILTERED != 0 {
+                    // the instruction before the loop push jumps back to the filter
+                    Some(pc - 1)
+                } else {
+                    Some(pc)
+                },
                 current_recursion_jump,
                 object: Arc::new(Loop {
                     idx: AtomicUsize::new(!0usize),
@@ -1,14 +1,20 @@
 use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 
+use crate::compiler::ast;
+use crate::compiler::codegen::CodeGenerator;
 use crate::compiler::instructions::Instructions;
+use crate::compiler::meta::find_undeclared;
+use crate::compiler::parser::parse_expr;
 use crate::environment::Environment;
-use crate::error::{Error, ErrorKind};
+use crate::error::{attach_basic_debug_info, Error, ErrorKind};
 use crate::output::Output;
 use crate::template::Template;
 use crate::utils::{AutoEscape, UndefinedBehavior};
-use crate::value::{ArgType, Value};
+use crate::value::{value_map_with_capacity, ArgType, Value};
 use crate::vm::context::Context;
+use crate::vm::warning::{Warning, WarningCollector};
+use crate::vm::Vm;
 
 #[cfg(feature = "fuel")]
 use crate::vm::fuel::FuelTracker;
@@ -43,10 +49,12 @@ pub struct State<'template, 'env> {
     pub(crate) ctx: Context<'env>,
     pub(crate) current_block: Option<&'env str>,
     pub(crate) auto_escape: AutoEscape,
+    pub(crate) undefined_behavior: UndefinedBehavior,
     pub(crate) instructions: &'template Instructions<'env>,
     pub(crate) blocks: BTreeMap<&'env str, BlockStack<'template, 'env>>,
     #[allow(unused)]
     pub(crate) loaded_templates: BTreeSet<&'env str>,
+    pub(crate) outer_templates: TemplateStack<'env>,
     #[cfg(feature = "macros")]
     pub(crate) id: isize,
     #[cfg(feature = "macros")]
@@ -55,6 +63,7 @@ pub struct State<'template, 'env> {
     pub(crate) closure_tracker: std::sync::Arc<crate::vm::closure_object::ClosureTracker>,
     #[cfg(feature = "fuel")]
     pub(crate) fuel_tracker: Option<std::sync::Arc<FuelTracker>>,
+    pub(crate) warnings: std::sync::Arc<WarningCollector>,
 }
 
 impl fmt::Debug for State<'_, '_> {
@@ -85,15 +94,18 @@ impl<'template, 'env> State<'template, 'env> {
             ctx,
             current_block: None,
             auto_escape,
+            undefined_behavior: env.undefined_behavior(),
             instructions,
             blocks,
             loaded_templates: BTreeSet::new(),
+            outer_templates: TemplateStack::default(),
             #[cfg(feature = "macros")]
             macros: Default::default(),
             #[cfg(feature = "macros")]
             closure_tracker: Default::default(),
             #[cfg(feature = "fuel")]
             fuel_tracker: env.fuel().map(FuelTracker::new),
+            warnings: Default::default(),
         }
     }
 
@@ -119,6 +131,31 @@ impl<'template, 'env> State<'template, 'env> {
         self.instructions.name()
     }
 
+    /// Returns the names of the templates that led to the current one.
+    ///
+    /// The first item is the template that rendering started with and the
+    /// last item is the current template (the same as [`name`](Self::name)).
+    /// Every `{% include %}` and `{% extends %}` as well as every macro call
+    /// adds a frame.  For a macro call the frame is the name of the template
+    /// the macro was declared in.  Likewise rendering a block that was
+    /// overridden in another template adds a frame for that template.  The
+    /// depth of this stack is bounded by the recursion limit.
+    ///
+    /// ```
+    /// # use minijinja::{Environment, State, context};
+    /// let mut env = Environment::new();
+    /// env.add_function("stack", |state: &State| state.template_stack().join(" > "));
+    /// env.add_template("layout.txt", "{% include 'nav.txt' %}").unwrap();
+    /// env.add_template("nav.txt", "{{ stack() }}").unwrap();
+    /// let tmpl = env.get_template("layout.txt").unwrap();
+    /// assert_eq!(tmpl.render(context!()).unwrap(), "layout.txt > nav.txt");
+    /// ```
+    pub fn template_stack(&self) -> Vec<&str> {
+        let mut rv = self.outer_templates.names();
+        rv.push(self.instructions.name());
+        rv
+    }
+
     /// Returns the current value of the auto escape flag.
     #[inline(always)]
     pub fn auto_escape(&self) -> AutoEscape {
@@ -126,9 +163,12 @@ impl<'template, 'env> State<'template, 'env> {
     }
 
     /// Returns the current undefined behavior.
+    ///
+    /// This is the undefined behavior of the environment unless it was
+    /// overridden with [`Template::with_undefined_behavior`].
     #[inline(always)]
     pub fn undefined_behavior(&self) -> UndefinedBehavior {
-        self.env.undefined_behavior()
+        self.undefined_behavior
     }
 
     /// Returns the name of the innermost block.
@@ -151,6 +191,56 @@ impl<'template, 'env> State<'template, 'env> {
         self.ctx.load(self.env, name)
     }
 
+    /// Evaluates an expression against the current state.
+    ///
+    /// The expression is compiled and evaluated with access to all variables
+    /// that [`lookup`](Self::lookup) can find.  It shares the undefined behavior,
+    /// auto escaping and fuel of the current render.  This is useful to build
+    /// generic helper functions that accept expressions as strings:
+    ///
+    /// ```
+    /// # use minijinja::{Environment, State, Error, Value, context};
+    /// fn lookup(state: &State, expr: &str) -> Result<Value, Error> {
+    ///     state.eval_expr(expr)
+    /// }
+    ///
+    /// let mut env = Environment::new();
+    /// env.add_function("lookup", lookup);
+    /// let rv = env.render_str(
+    ///     "{{ lookup('user.name|upper') }}",
+    ///     context!(user => context!(name => "Peter")),
+    /// ).unwrap();
+    /// assert_eq!(rv, "PETER");
+    /// ```
+    ///
+    /// The expression is compiled under the name of the current template so
+    /// that [`name`](Self::name) and [`template_stack`](Self::template_stack)
+    /// report the calling template.  Compiled expressions are not cached and
+    /// the expression is compiled again on every call.  If the same expression
+    /// is evaluated many times, prefer [`Environment::compile_expression`].
+    pub fn eval_expr(&self, expr: &str) -> Result<Value, Error> {
+        let stmt = ast::Stmt::EmitExpr(ast::Spanned::new(
+            ast::EmitExpr {
+                expr: ok!(attach_basic_debug_info(parse_expr(expr), expr)),
+            },
+            Default::default(),
+        ));
+        let undeclared = find_undeclared(&stmt, false);
+        let mut root = value_map_with_capacity(undeclared.len());
+        for name in undeclared {
+            if let Some(value) = self.lookup(&name) {
+                root.insert(Value::from(name), value);
+            }
+        }
+
+        let mut g = CodeGenerator::new(self.instructions.name(), expr);
+        if let ast::Stmt::EmitExpr(ref emit) = stmt {
+            g.compile_expr(&emit.expr);
+        }
+        let instructions = g.finish().0;
+        Vm::new(self.env).eval_expr_in_state(&instructions, Value::from_object(root), self)
+    }
+
     /// Looks up a global macro and calls it.
     ///
     /// This looks up a value as [`lookup`](Self::lookup) does and calls it
@@ -221,6 +311,27 @@ impl<'template, 'env> State<'template, 'env> {
         self.ctx.exports().keys().copied().collect()
     }
 
+    /// Returns the names of all blocks known to the state.
+    ///
+    /// Once a template was evaluated (for instance with
+    /// [`Template::eval_to_state`]) this includes the blocks of all templates
+    /// it extended from, even if they were not overridden.
+    ///
+    /// ```
+    /// # use minijinja::{Environment, context};
+    /// # fn test() -> Result<(), minijinja::Error> {
+    /// # let mut env = Environment::new();
+    /// env.add_template("layout", "{% block title %}{% endblock %}{% block body %}{% endblock %}")?;
+    /// env.add_template("page", "{% extends 'layout' %}{% block body %}...{% endblock %}")?;
+    /// let tmpl = env.get_template("page")?;
+    /// let state = tmpl.eval_to_state(context!())?;
+    /// assert_eq!(state.blocks(), vec!["body", "title"]);
+    /// # Ok(()) } fn main() { test().unwrap(); }
+    /// ```
+    pub fn blocks(&self) -> Vec<&str> {
+        self.blocks.keys().copied().collect()
+    }
+
     /// Fetches a template by name with path joining.
     ///
     /// This works like [`Environment::get_template`] with the difference that the lookup
@@ -285,6 +396,39 @@ impl<'template, 'env> State<'template, 'env> {
         self.env.format(&value, self, &mut out).map(|_| rv)
     }
 
+    /// Issues a non fatal warning.
+    ///
+    /// Filters, tests and functions can use this to report problems that should
+    /// not abort rendering, such as the use of deprecated features.  The warning
+    /// records the current template and block.  Warnings are collected for the
+    /// entire render and can be retrieved with [`warnings`](Self::warnings) or
+    /// by rendering with [`Template::render_with_warnings`].
+    ///
+    /// As this returns `()`, functions that only issue a warning should return
+    /// an empty string (or an undefined value) as `()` renders as `none`.
+    ///
+    /// ```
+    /// # use minijinja::{Environment, State, context};
+    /// let mut env = Environment::new();
+    /// env.add_filter("old_upper", |state: &State, value: String| {
+    ///     state.warn("old_upper is deprecated, use upper instead");
+    ///     value.to_uppercase()
+    /// });
+    /// let tmpl = env.template_from_str("{{ 'hello'|old_upper }}").unwrap();
+    /// let (rv, warnings) = tmpl.render_with_warnings(context!()).unwrap();
+    /// assert_eq!(rv, "HELLO");
+    /// assert_eq!(warnings[0].message(), "old_upper is deprecated, use upper instead");
+    /// ```
+    pub fn warn<M: Into<String>>(&self, message: M) {
+        self.warnings
+            .push(message.into(), self.name(), self.current_block);
+    }
+
+    /// Returns the warnings issued so far.
+    pub fn warnings(&self) -> Vec<Warning> {
+        self.warnings.warnings()
+    }
+
     /// Returns the fuel levels.
     ///
     /// When the fuel feature is enabled, during evaluation the template will keep
@@ -299,6 +443,27 @@ impl<'template, 'env> State<'template, 'env> {
             .map(|x| (x.consumed(), x.remaining()))
     }
 
+    /// Returns the remaining fuel.
+    ///
+    /// This is a shortcut for the second value of [`fuel_levels`](Self::fuel_levels)
+    /// and returns `None` if fuel tracking is not enabled.  Expensive functions
+    /// can use this to cooperatively respect the budget and stop early:
+    ///
+    /// ```
+    /// # use minijinja::{State, Error, ErrorKind};
+    /// fn expensive(state: &State) -> Result<u64, Error> {
+    ///     if state.fuel_remaining().map_or(false, |x| x < 1000) {
+    ///         return Err(Error::from(ErrorKind::OutOfFuel));
+    ///     }
+    ///     Ok(42)
+    /// }
+    /// ```
+    #[cfg(feature = "fuel")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "fuel")))]
+    pub fn fuel_remaining(&self) -> Option<u64> {
+        self.fuel_tracker.as_ref().map(|x| x.remaining())
+    }
+
     #[cfg(feature = "debug")]
     pub(crate) fn make_debug_info(
         &self,
@@ -375,3 +540,42 @@ impl<'template, 'env> BlockStack<'template, 'env> {
         self.instructions.push(instructions);
     }
 }
+
+/// Tracks the names of the templates that led to the current one.
+///
+/// The entries are shared so that a macro call can extend the stack of the
+/// calling state without copying it.
+#[derive(Default, Clone)]
+pub(crate) struct TemplateStack<'env> {
+    top: Option<std::sync::Arc<TemplateStackEntry<'env>>>,
+}
+
+struct TemplateStackEntry<'env> {
+    name: &'env str,
+    parent: Option<std::sync::Arc<TemplateStackEntry<'env>>>,
+}
+
+impl<'env> TemplateStack<'env> {
+    #[cfg(any(feature = "macros", feature = "multi_template"))]
+    pub fn push(&mut self, name: &'env str) {
+        let parent = self.top.take();
+        self.top = Some(std::sync::Arc::new(TemplateStackEntry { name, parent }));
+    }
+
+    #[cfg(feature = "multi_template")]
+    #[track_caller]
+    pub fn pop(&mut self) {
+        self.top = self.top.take().unwrap().parent.clone();
+    }
+
+    pub fn names(&self) -> Vec<&'env str> {
+        let mut rv = Vec::new();
+        let mut entry = self.top.as_deref();
+        while let Some(item) = entry {
+            rv.push(item.name);
+            entry = item.parent.as_deref();
+        }
+        rv.reverse();
+        rv
+    }
+}
@@ -0,0 +1,136 @@
+use std::time::Duration;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::time::Instant;
+
+use crate::compiler::instructions::Instructions;
+use crate::vm::state::State;
+
+/// The kind of operation a [`TraceEvent`] was emitted for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TraceEventKind {
+    /// A filter was applied (`value|filter`).
+    Filter,
+    /// A test was performed (`value is test`).
+    Test,
+    /// A function was called by name (`func()`).
+    Function,
+    /// A method was called on a value (`value.method()`).
+    Method,
+    /// A block was rendered.
+    Block,
+    /// A macro was invoked.
+    Macro,
+}
+
+/// Describes a traced operation.
+///
+/// These events are passed to the callback registered with
+/// [`Environment::set_trace_callback`](crate::Environment::set_trace_callback).
+/// An event is emitted once the operation finished (even if it failed) so
+/// that nested operations are reported before the operations that contain
+/// them.
+#[derive(Debug, Clone, Copy)]
+pub struct TraceEvent<'a> {
+    kind: TraceEventKind,
+    name: &'a str,
+    template_name: &'a str,
+    line: Option<usize>,
+    duration: Duration,
+}
+
+impl<'a> TraceEvent<'a> {
+    /// Returns the kind of operation.
+    pub fn kind(&self) -> TraceEventKind {
+        self.kind
+    }
+
+    /// Returns the name of the filter, test, function, method, block or macro.
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// Returns the name of the template the operation was invoked from.
+    pub fn template_name(&self) -> &'a str {
+        self.template_name
+    }
+
+    /// Returns the line in the template if it is known.
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+
+    /// Returns how long the operation took.
+    ///
+    /// On `wasm32-unknown-unknown` there is no clock available and the
+    /// duration is always zero.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+}
+
+/// Marks the point in time at which a traced operation started.
+///
+/// `Instant::now` panics on `wasm32-unknown-unknown` so nothing is measured
+/// on that target.
+#[derive(Clone, Copy)]
+pub(crate) struct TraceStart {
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    instant: Instant,
+}
+
+impl TraceStart {
+    #[inline(always)]
+    fn now() -> TraceStart {
+        TraceStart {
+            #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+            instant: Instant::now(),
+        }
+    }
+
+    #[inline(always)]
+    fn elapsed(&self) -> Duration {
+        #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+        {
+            self.instant.elapsed()
+        }
+        #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+        {
+            Duration::ZERO
+        }
+    }
+}
+
+/// Starts timing an operation if a trace callback is registered.
+#[inline(always)]
+pub(crate) fn trace_start(state: &State) -> Option<TraceStart> {
+    state
+        .env()
+        .trace_callback
+        .as_ref()
+        .map(|_| TraceStart::now())
+}
+
+/// Emits a trace event for an operation started with [`trace_start`].
+///
+/// The location is given as instructions and program counter so that the
+/// line is only looked up if a callback is registered.
+#[inline(always)]
+pub(crate) fn trace_end(
+    state: &State,
+    start: Option<TraceStart>,
+    kind: TraceEventKind,
+    name: &str,
+    instructions: &Instructions<'_>,
+    pc: usize,
+) {
+    if let (Some(start), Some(callback)) = (start, state.env().trace_callback.as_ref()) {
+        callback(TraceEvent {
+            kind,
+            name,
+            template_name: instructions.name(),
+            line: instructions.get_line(pc),
+            duration: start.elapsed(),
+        });
+    }
+}
@@ -0,0 +1,70 @@
+use std::fmt;
+use std::sync::Mutex;
+
+/// A non fatal warning emitted while rendering.
+///
+/// Warnings are issued by filters, tests and functions through
+/// [`State::warn`](crate::State::warn) and collected by
+/// [`Template::render_with_warnings`](crate::Template::render_with_warnings).
+/// They do not abort rendering and are intended for logging or linting, for
+/// instance to find out which templates still use deprecated features.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    message: String,
+    template_name: String,
+    block: Option<String>,
+}
+
+impl Warning {
+    /// Returns the warning message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the name of the template that was rendered when the warning was issued.
+    pub fn template_name(&self) -> &str {
+        &self.template_name
+    }
+
+    /// Returns the name of the block that was rendered when the warning was issued.
+    pub fn block(&self) -> Option<&str> {
+        self.block.as_deref()
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        ok!(write!(f, "{} (in {}", self.message, self.template_name));
+        if let Some(ref block) = self.block {
+            ok!(write!(f, ", block {}", block));
+        }
+        write!(f, ")")
+    }
+}
+
+/// Collects warnings across all states of a single render.
+#[derive(Default)]
+pub(crate) struct WarningCollector {
+    warnings: Mutex<Vec<Warning>>,
+}
+
+impl WarningCollector {
+    /// Records a new warning.
+    pub fn push(&self, message: String, template_name: &str, block: Option<&str>) {
+        self.warnings.lock().unwrap().push(Warning {
+            message,
+            template_name: template_name.to_string(),
+            block: block.map(|x| x.to_string()),
+        });
+    }
+
+    /// Returns all warnings recorded so far.
+    pub fn warnings(&self) -> Vec<Warning> {
+        self.warnings.lock().unwrap().clone()
+    }
+
+    /// Removes and returns all warnings recorded so far.
+    pub fn take(&self) -> Vec<Warning> {
+        std::mem::take(&mut *self.warnings.lock().unwrap())
+    }
+}
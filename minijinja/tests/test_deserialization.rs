@@ -140,3 +140,60 @@ fn test_invalid() {
         "cannot deserialize: could not serialize to value: meh"
     );
 }
+
+#[test]
+fn test_via_kwargs() {
+    use minijinja::value::{from_args, Kwargs, ViaKwargs};
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Options {
+        #[serde(default)]
+        limit: Option<usize>,
+        #[serde(default)]
+        reverse: bool,
+    }
+
+    let args = [
+        Value::from("foo"),
+        Value::from(Kwargs::from_iter([
+            ("limit", Value::from(2)),
+            ("reverse", Value::from(true)),
+        ])),
+    ];
+    let (name, opts): (&str, ViaKwargs<Options>) = from_args(&args).unwrap();
+    assert_eq!(name, "foo");
+    assert_eq!(
+        opts.0,
+        Options {
+            limit: Some(2),
+            reverse: true
+        }
+    );
+
+    let (name, opts): (&str, ViaKwargs<Options>) = from_args(&args[..1]).unwrap();
+    assert_eq!(name, "foo");
+    assert_eq!(
+        opts.0,
+        Options {
+            limit: None,
+            reverse: false
+        }
+    );
+
+    let args = [
+        Value::from("foo"),
+        Value::from(Kwargs::from_iter([
+            ("limit", Value::from(2)),
+            ("bogus", Value::from(true)),
+        ])),
+    ];
+    let err = from_args::<(&str, ViaKwargs<Options>)>(&args).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "too many arguments: unknown keyword argument 'bogus'"
+    );
+
+    let args = [Value::from("foo"), Value::from(42)];
+    let err = from_args::<(&str, ViaKwargs<Options>)>(&args).unwrap_err();
+    assert_eq!(err.kind(), minijinja::ErrorKind::TooManyArguments);
+}
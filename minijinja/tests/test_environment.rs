@@ -28,6 +28,45 @@ fn test_expression() {
     assert_eq!(expr.eval(&ctx).unwrap(), Value::from(65));
 }
 
+#[test]
+fn test_expression_filters_and_tests() {
+    let mut env = Environment::new();
+    env.add_filter("double", |x: i64| x * 2);
+    env.add_test("big", |x: i64| x > 100);
+    env.add_function("half", |x: i64| x / 2);
+    env.add_global("factor", 3);
+
+    let expr = env.compile_expression("x|double").unwrap();
+    assert_eq!(
+        expr.eval(minijinja::context!(x => 21)).unwrap(),
+        Value::from(42)
+    );
+    let expr = env.compile_expression("value|round(2)").unwrap();
+    assert_eq!(
+        expr.eval(minijinja::context!(value => 1.23456)).unwrap(),
+        Value::from(1.23)
+    );
+    let expr = env
+        .compile_expression("[name is defined, missing is defined, x is big]")
+        .unwrap();
+    assert_eq!(
+        expr.eval(minijinja::context!(name => "x", x => 200))
+            .unwrap(),
+        Value::from(vec![true, false, true])
+    );
+    let expr = env.compile_expression("half(x|double) * factor").unwrap();
+    assert_eq!(
+        expr.eval(minijinja::context!(x => 5)).unwrap(),
+        Value::from(15)
+    );
+    let err = env
+        .compile_expression("x|missing_filter")
+        .unwrap()
+        .eval(minijinja::context!(x => 1))
+        .unwrap_err();
+    assert_eq!(err.kind(), minijinja::ErrorKind::UnknownFilter);
+}
+
 #[test]
 #[cfg(feature = "loader")]
 fn test_expression_owned() {
@@ -89,6 +128,25 @@ fn test_clone() {
     assert_eq!(env.get_template("test").unwrap().render(()).unwrap(), "a");
 }
 
+#[test]
+fn test_clone_copy_on_write() {
+    let mut env = Environment::new();
+    env.add_filter("double", |x: i64| x * 2);
+    env.add_global("x", 21);
+    let mut env2 = env.clone();
+    env2.add_filter("triple", |x: i64| x * 3);
+    env2.remove_filter("double");
+    env2.add_global("x", 42);
+    env2.add_test("big", |x: i64| x > 100);
+
+    assert!(env.has_filter("double"));
+    assert!(!env.has_filter("triple"));
+    assert!(!env.has_test("big"));
+    assert_eq!(env.render_str("{{ x|double }}", ()).unwrap(), "42");
+    assert_eq!(env2.render_str("{{ x|triple }}", ()).unwrap(), "126");
+    assert!(env2.render_str("{{ x|double }}", ()).is_err());
+}
+
 #[test]
 fn test_globals() {
     let mut env = Environment::new();
@@ -129,6 +187,61 @@ fn test_path_join() {
     assert_eq!(t.render(()).unwrap(), "bar.txt");
 }
 
+#[test]
+#[cfg(feature = "multi_template")]
+fn test_relative_includes() {
+    let mut env = Environment::new();
+    env.set_relative_includes(true);
+    env.add_template("site/base.html", "[{% block body %}{% endblock %}]")
+        .unwrap();
+    env.add_template(
+        "site/blog/post.html",
+        "{% extends '../base.html' %}{% block body %}{% include './parts/meta.html' %}|{% include 'header.html' %}{% endblock %}",
+    )
+    .unwrap();
+    env.add_template("site/blog/parts/meta.html", "meta")
+        .unwrap();
+    env.add_template("header.html", "header").unwrap();
+    env.add_template("outside.html", "outside").unwrap();
+    env.add_template("top.html", "{% include '../../outside.html' %}")
+        .unwrap();
+
+    let t = env.get_template("site/blog/post.html").unwrap();
+    assert_eq!(t.render(()).unwrap(), "[meta|header]");
+
+    // walking past the root just stays at the root
+    let t = env.get_template("top.html").unwrap();
+    assert_eq!(t.render(()).unwrap(), "outside");
+
+    env.set_relative_includes(false);
+    let t = env.get_template("site/blog/post.html").unwrap();
+    assert_eq!(
+        t.render(()).unwrap_err().kind(),
+        minijinja::ErrorKind::TemplateNotFound
+    );
+}
+
+#[test]
+fn test_keep_comments() {
+    let mut env = Environment::new();
+    assert!(!env.keep_comments());
+    env.add_template("strip.txt", "a{# x #}b").unwrap();
+    env.set_keep_comments(true);
+    assert!(env.keep_comments());
+    assert_eq!(
+        env.get_template("strip.txt").unwrap().render(()).unwrap(),
+        "ab"
+    );
+    assert_eq!(
+        env.render_str("/* {# license #} */\n{{ 1 }}", ()).unwrap(),
+        "/* {# license #} */\n1"
+    );
+    assert_eq!(
+        env.render_str("a  {#- trimmed -#}  b", ()).unwrap(),
+        "a{#- trimmed -#}b"
+    );
+}
+
 #[test]
 fn test_keep_trailing_newlines() {
     let mut env = Environment::new();
@@ -188,3 +301,244 @@ fn test_iter() {
     assert!(renders.contains(&("hello", "Hello World!".into())));
     assert!(renders.contains(&("goodbye", "Goodbye World!".into())));
 }
+
+#[test]
+fn test_formatter_output_auto_escape() {
+    use minijinja::AutoEscape;
+
+    let mut env = Environment::new();
+    env.set_formatter(|out, state, value| {
+        assert_eq!(out.auto_escape(), state.auto_escape());
+        let tag = match out.auto_escape() {
+            AutoEscape::Html => "html",
+            AutoEscape::None => "none",
+            _ => "other",
+        };
+        write!(out, "{}:{}", tag, value)?;
+        Ok(())
+    });
+    env.add_template(
+        "test.html",
+        "[{{ 1 }}]{% autoescape false %}[{{ 2 }}]{% endautoescape %}",
+    )
+    .unwrap();
+    let rv = env.get_template("test.html").unwrap().render(()).unwrap();
+    assert_eq!(rv, "[html:1][none:2]");
+}
+
+#[test]
+fn test_aliases() {
+    let mut env = Environment::new();
+    env.add_filter("double", |x: i64| x * 2);
+    env.add_test("big", |x: i64| x > 100);
+    env.add_function("answer", || 42);
+    env.add_filter_alias("twice", "double").unwrap();
+    env.add_test_alias("large", "big").unwrap();
+    env.add_function_alias("the_answer", "answer").unwrap();
+
+    let expr = env
+        .compile_expression("[21|twice, 200 is large, the_answer()]")
+        .unwrap();
+    assert_eq!(expr.eval(()).unwrap().to_string(), "[42, true, 42]");
+
+    let err = env.add_filter_alias("nope", "missing").unwrap_err();
+    assert_eq!(err.kind(), minijinja::ErrorKind::UnknownFilter);
+    assert_eq!(
+        err.to_string(),
+        "unknown filter: cannot alias unknown filter 'missing'"
+    );
+    let err = env.add_test_alias("nope", "missing").unwrap_err();
+    assert_eq!(err.kind(), minijinja::ErrorKind::UnknownTest);
+    let err = env.add_function_alias("nope", "missing").unwrap_err();
+    assert_eq!(err.kind(), minijinja::ErrorKind::UnknownFunction);
+}
+
+#[test]
+fn test_registry_introspection() {
+    let mut env = Environment::empty();
+    env.add_filter("double", |x: i64| x * 2);
+    env.add_test("big", |x: i64| x > 100);
+    env.add_function("answer", || 42);
+    env.add_global("VERSION", "1.0");
+
+    assert_eq!(env.filters().collect::<Vec<_>>(), ["double"]);
+    assert_eq!(env.tests().collect::<Vec<_>>(), ["big"]);
+    assert_eq!(
+        env.globals().map(|(name, _)| name).collect::<Vec<_>>(),
+        ["VERSION", "answer"]
+    );
+    assert!(env.has_filter("double"));
+    assert!(!env.has_filter("big"));
+    assert!(env.has_test("big"));
+    assert!(env.has_global("answer"));
+
+    env.remove_filter("double");
+    assert!(!env.has_filter("double"));
+}
+
+#[test]
+fn test_attribute_access_policy() {
+    let mut env = Environment::new();
+    env.set_attribute_access_policy(|value, name| {
+        !(name.starts_with('_')
+            || (value.kind() == minijinja::value::ValueKind::String && name == "upper"))
+    });
+    let ctx = minijinja::context! {
+        user => minijinja::context! { name => "John", _secret => "s3cr3t" }
+    };
+
+    assert_eq!(env.render_str("{{ user.name }}", &ctx).unwrap(), "John");
+    assert_eq!(env.render_str("{{ user['name'] }}", &ctx).unwrap(), "John");
+
+    for source in [
+        "{{ user._secret }}",
+        "{{ user['_secret'] }}",
+        "{{ user.name.upper() }}",
+    ] {
+        let err = env.render_str(source, &ctx).unwrap_err();
+        assert_eq!(err.kind(), minijinja::ErrorKind::SecurityError);
+    }
+    let err = env.render_str("{{ user._secret }}", &ctx).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "security error: access to '_secret' on map is not permitted (in <string>:1)"
+    );
+}
+
+#[test]
+fn test_add_and_get_template() {
+    let mut env = Environment::new();
+    let tmpl = env
+        .add_and_get_template("hello.txt", "Hello {{ name }}!")
+        .unwrap();
+    assert_eq!(tmpl.name(), "hello.txt");
+    assert_eq!(
+        tmpl.render(minijinja::context!(name => "World")).unwrap(),
+        "Hello World!"
+    );
+    assert!(env.get_template("hello.txt").is_ok());
+
+    let err = env
+        .add_and_get_template("broken.txt", "{% if %}")
+        .unwrap_err();
+    assert_eq!(err.kind(), minijinja::ErrorKind::SyntaxError);
+}
+
+#[test]
+fn test_custom_escaper() {
+    let mut env = Environment::new();
+    env.set_auto_escape_callback(|name| {
+        if name.ends_with(".csv") {
+            minijinja::AutoEscape::Custom("csv")
+        } else {
+            minijinja::AutoEscape::None
+        }
+    });
+    env.add_escaper("csv", |out, value| {
+        if value.contains([',', '"', '\n']) {
+            write!(out, "\"{}\"", value.replace('"', "\"\""))
+        } else {
+            out.write_str(value)
+        }
+    });
+    env.add_template(
+        "row.csv",
+        "{{ a }},{{ b }},{{ c|safe }},{{ d|escape }},{{ 42 }}",
+    )
+    .unwrap();
+    let tmpl = env.get_template("row.csv").unwrap();
+    let rv = tmpl
+        .render(minijinja::context! {
+            a => "plain",
+            b => "with, comma",
+            c => "raw, \"value\"",
+            d => "say \"hi\"",
+        })
+        .unwrap();
+    assert_eq!(rv, r#"plain,"with, comma",raw, "value","say ""hi""",42"#);
+
+    env.remove_escaper("csv");
+    let err = env.get_template("row.csv").unwrap().render(()).unwrap_err();
+    assert_eq!(
+        err.detail(),
+        Some("no escaper registered for custom auto escape format 'csv'")
+    );
+}
+
+#[test]
+fn test_trace_callback() {
+    use minijinja::TraceEventKind;
+    use std::sync::{Arc, Mutex};
+
+    let mut env = Environment::new();
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let events_ref = events.clone();
+    env.set_trace_callback(move |event| {
+        events_ref.lock().unwrap().push((
+            event.kind(),
+            event.name().to_string(),
+            event.template_name().to_string(),
+            event.line(),
+        ));
+    });
+    env.add_template(
+        "trace.html",
+        "{% macro m(x) %}{{ x|upper }}{% endmacro %}\n{{ m('a') }}\n{{ range(2)|list is sequence }}\n{% for _ in [1] %}{{ loop.cycle('a') }}{% endfor %}\n{% block b %}{{ 1 is odd }}{% endblock %}",
+    )
+    .unwrap();
+    env.get_template("trace.html").unwrap().render(()).unwrap();
+
+    let events = events.lock().unwrap();
+    let summary = events
+        .iter()
+        .map(|(kind, name, tmpl, line)| format!("{kind:?} {name} {tmpl}:{}", line.unwrap_or(0)))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        summary,
+        vec![
+            "Filter upper trace.html:1",
+            "Macro m trace.html:1",
+            "Function m trace.html:2",
+            "Function range trace.html:3",
+            "Filter list trace.html:3",
+            "Test sequence trace.html:3",
+            "Method cycle trace.html:4",
+            "Test odd trace.html:5",
+            "Block b trace.html:5",
+        ]
+    );
+    assert_eq!(events[0].0, TraceEventKind::Filter);
+}
+
+#[test]
+fn test_output_whitespace() {
+    use minijinja::WhitespacePolicy;
+
+    let source = "<div>\n    <p>  {{ text }}  </p>\t\n  <pre>  a\n   b</pre>\n<textarea> x  </textarea>  </div>  ";
+    let ctx = minijinja::context!(text => "hello    world");
+
+    let mut env = Environment::new();
+    assert_eq!(env.output_whitespace(), WhitespacePolicy::Preserve);
+    assert_eq!(
+        env.render_str(source, &ctx).unwrap(),
+        source.replace("{{ text }}", "hello    world")
+    );
+
+    env.set_output_whitespace(WhitespacePolicy::Collapse);
+    assert_eq!(
+        env.render_str(source, &ctx).unwrap(),
+        "<div>\n<p> hello world </p>\n<pre> a\nb</pre>\n<textarea> x </textarea> </div> "
+    );
+
+    env.set_output_whitespace(WhitespacePolicy::CollapseHtml);
+    assert_eq!(
+        env.render_str(source, &ctx).unwrap(),
+        "<div>\n<p> hello world </p>\n<pre>  a\n   b</pre>\n<textarea> x  </textarea> </div> "
+    );
+
+    let tmpl = env.template_from_str("a  {{ x }}\n\n b").unwrap();
+    let mut out = Vec::<u8>::new();
+    tmpl.render_to_write(minijinja::context!(x => "  y  "), &mut out)
+        .unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "a y\nb");
+}
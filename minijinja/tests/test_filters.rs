@@ -1,6 +1,8 @@
 #![cfg(feature = "builtins")]
-use minijinja::value::Value;
-use minijinja::{args, Environment};
+use std::sync::Arc;
+
+use minijinja::value::{Enumerator, Object, Value};
+use minijinja::{args, context, Environment};
 use similar_asserts::assert_eq;
 
 use minijinja::filters::{abs, indent};
@@ -86,3 +88,287 @@ fn test_abs_overflow() {
     let err = abs(Value::from(i128::MIN)).unwrap_err();
     assert_eq!(err.to_string(), "invalid operation: overflow on abs");
 }
+
+#[test]
+fn test_pprint_dynamic_object() {
+    #[derive(Debug)]
+    struct Point;
+
+    impl Object for Point {
+        fn get_value(self: &Arc<Self>, key: &Value) -> Option<Value> {
+            match key.as_str()? {
+                "x" => Some(Value::from(1)),
+                "y" => Some(Value::from("<2>")),
+                _ => None,
+            }
+        }
+
+        fn enumerate(self: &Arc<Self>) -> Enumerator {
+            Enumerator::Str(&["x", "y"])
+        }
+    }
+
+    let mut env = Environment::new();
+    env.add_template("test.html", "<pre>{{ point|pprint }}</pre>")
+        .unwrap();
+    let rv = env
+        .get_template("test.html")
+        .unwrap()
+        .render(context!(point => Value::from_object(Point)))
+        .unwrap();
+    assert_eq!(
+        rv,
+        "<pre>{\n    &quot;x&quot;: 1,\n    &quot;y&quot;: &quot;&lt;2&gt;&quot;,\n}</pre>"
+    );
+}
+
+#[test]
+fn test_forceescape_in_html() {
+    let mut env = Environment::new();
+    env.add_template(
+        "test.html",
+        "{{ trusted|safe|forceescape }}|{{ trusted|forceescape }}",
+    )
+    .unwrap();
+    let rv = env
+        .get_template("test.html")
+        .unwrap()
+        .render(context!(trusted => "<b>&</b>"))
+        .unwrap();
+    assert_eq!(
+        rv,
+        "&lt;b&gt;&amp;&lt;&#x2f;b&gt;|&lt;b&gt;&amp;&lt;&#x2f;b&gt;"
+    );
+}
+
+#[test]
+fn test_items_dynamic_object() {
+    #[derive(Debug)]
+    struct Point;
+
+    impl Object for Point {
+        fn get_value(self: &Arc<Self>, key: &Value) -> Option<Value> {
+            match key.as_str()? {
+                "x" => Some(Value::from(1)),
+                "y" => Some(Value::from(2)),
+                _ => None,
+            }
+        }
+
+        fn enumerate(self: &Arc<Self>) -> Enumerator {
+            Enumerator::Str(&["x", "y"])
+        }
+    }
+
+    let env = Environment::new();
+    let rv = env
+        .render_str(
+            "{% for k, v in point|items %}{{ k }}={{ v }};{% endfor %}",
+            context!(point => Value::from_object(Point)),
+        )
+        .unwrap();
+    assert_eq!(rv, "x=1;y=2;");
+
+    let err = env.render_str("{{ [1, 2]|items }}", ()).unwrap_err();
+    assert_eq!(err.detail(), Some("cannot convert sequence into pairs"));
+}
+
+#[test]
+#[cfg(feature = "regex")]
+fn test_replace_regex() {
+    let env = Environment::new();
+    let rv = env
+        .render_str(
+            r#"{{ "2024-01-15"|replace("(\\d+)-(\\d+)-(\\d+)", "$3.$2.$1", regex=true) }}"#,
+            (),
+        )
+        .unwrap();
+    assert_eq!(rv, "15.01.2024");
+
+    let rv = env
+        .render_str(
+            r##"{{ "a1b22c333"|replace("\\d+", "#", 2, regex=true) }}"##,
+            (),
+        )
+        .unwrap();
+    assert_eq!(rv, "a#b#c333");
+
+    let rv = env
+        .render_str(r#"{{ "a.b"|replace(".", "-") }}"#, ())
+        .unwrap();
+    assert_eq!(rv, "a-b");
+
+    let err = env
+        .render_str(r#"{{ "x"|replace("(", "y", regex=true) }}"#, ())
+        .unwrap_err();
+    assert_eq!(err.kind(), minijinja::ErrorKind::InvalidOperation);
+}
+
+#[test]
+fn test_filter_with_data() {
+    struct Lookup {
+        prefix: String,
+        names: Vec<&'static str>,
+    }
+
+    let lookup = Arc::new(Lookup {
+        prefix: "#".into(),
+        names: vec!["zero", "one", "two"],
+    });
+
+    let mut env = Environment::new();
+    env.add_filter_with_data(
+        "name",
+        lookup.clone(),
+        |lookup: &Lookup, idx: usize, fallback: Option<&str>| match lookup.names.get(idx) {
+            Some(name) => name.to_string(),
+            None => format!("{}{}", lookup.prefix, fallback.unwrap_or("?")),
+        },
+    );
+    env.add_filter_with_data(
+        "prefixed",
+        lookup,
+        |lookup: &Lookup, state: &minijinja::State, value: String| {
+            format!("{}{}:{}", lookup.prefix, state.name(), value)
+        },
+    );
+
+    let rv = env
+        .render_str("{{ 1|name }} {{ 5|name }} {{ 5|name('x') }}", ())
+        .unwrap();
+    assert_eq!(rv, "one #? #x");
+    let rv = env
+        .render_named_str("tmpl.txt", "{{ 'x'|prefixed }}", ())
+        .unwrap();
+    assert_eq!(rv, "#tmpl.txt:x");
+}
+
+#[test]
+#[cfg(feature = "json")]
+fn test_tojson_dynamic_object() {
+    #[derive(Debug)]
+    struct Point;
+
+    impl Object for Point {
+        fn get_value(self: &Arc<Self>, key: &Value) -> Option<Value> {
+            match key.as_str()? {
+                "x" => Some(Value::from(1)),
+                "y" => Some(Value::from(vec![2, 3])),
+                _ => None,
+            }
+        }
+
+        fn enumerate(self: &Arc<Self>) -> Enumerator {
+            Enumerator::Str(&["x", "y"])
+        }
+    }
+
+    let env = Environment::new();
+    let point = Value::from_object(Point);
+    let rv = env
+        .render_str(
+            "{{ point|tojson }} {{ [point, point]|tojson }}",
+            context!(point),
+        )
+        .unwrap();
+    assert_eq!(
+        rv,
+        r#"{"x":1,"y":[2,3]} [{"x":1,"y":[2,3]},{"x":1,"y":[2,3]}]"#
+    );
+
+    let err = env
+        .render_str(
+            "{% set ns = namespace(a=1) %}{% set ns.me = ns %}{{ ns|tojson }}",
+            (),
+        )
+        .unwrap_err();
+    assert_eq!(err.detail(), Some("cannot serialize to JSON"));
+    assert_eq!(
+        std::error::Error::source(&err).unwrap().to_string(),
+        "cannot serialize object that contains itself"
+    );
+}
+
+#[test]
+fn test_groupby_result_shape() {
+    let env = Environment::new();
+    let ctx = context! {
+        users => vec![
+            context! { name => "a", city => "Vienna" },
+            context! { name => "b", city => "London" },
+            context! { name => "c", city => "Vienna" },
+        ]
+    };
+    let rv = env
+        .render_str(
+            "{% for group in users|groupby('city') %}\
+             {{ group.grouper }}={{ group[0] }}:{{ group.list|length }}:\
+             {{ group.list[0].name }}-{{ group[1][-1].name }}:{{ group|length }};\
+             {% endfor %}",
+            ctx.clone(),
+        )
+        .unwrap();
+    assert_eq!(rv, "London=London:1:b-b:2;Vienna=Vienna:2:a-c:2;");
+
+    let rv = env
+        .render_str(
+            "{% for city, items in users|groupby('city') %}\
+             {{ city }}:{{ items|map(attribute='name')|join(',') }};\
+             {% endfor %}",
+            ctx,
+        )
+        .unwrap();
+    assert_eq!(rv, "London:b;Vienna:a,c;");
+}
+
+#[test]
+fn test_wordwrap() {
+    let env = Environment::new();
+    let text = "This is a long piece of text that should be wrapped at a specific width.";
+    let rv = env
+        .render_str("{{ text|wordwrap(width=20) }}", context! { text })
+        .unwrap();
+    assert_eq!(
+        rv,
+        "This is a long piece\nof text that should\nbe wrapped at a\nspecific width."
+    );
+
+    let rv = env
+        .render_str(
+            "{{ 'ThisIsAVeryLongWord short'|wordwrap(width=10) }}|\
+             {{ 'ThisIsAVeryLongWord short'|wordwrap(width=10, break_long_words=false) }}",
+            context! {},
+        )
+        .unwrap();
+    assert_eq!(
+        rv,
+        "ThisIsAVer\nyLongWord\nshort|ThisIsAVeryLongWord\nshort"
+    );
+
+    let rv = env
+        .render_str(
+            "{{ 'This-is-a-hyphenated-word'|wordwrap(width=10) }}|\
+             {{ 'well-known fact'|wordwrap(width=6, break_on_hyphens=false, break_long_words=false) }}",
+            context! {},
+        )
+        .unwrap();
+    assert_eq!(rv, "This-is-a-\nhyphenated\n-word|well-known\nfact");
+
+    let rv = env
+        .render_str(
+            "{{ 'one two three\n\nfour five'|wordwrap(width=8, wrapstring='<br>') }}|{{ '  '|wordwrap }}|",
+            context! {},
+        )
+        .unwrap();
+    assert_eq!(rv, "one two<br>three<br><br>four<br>five||");
+}
+
+#[test]
+#[cfg(feature = "json")]
+fn test_tojson_escape_html_false_is_not_safe() {
+    let mut env = Environment::new();
+    env.add_template("test.html", r#"{{ "<script>"|tojson(escape_html=false) }}"#)
+        .unwrap();
+    let rv = env.get_template("test.html").unwrap().render(()).unwrap();
+    assert_eq!(rv, "&quot;&lt;script&gt;&quot;");
+}
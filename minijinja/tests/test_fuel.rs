@@ -48,3 +48,49 @@ fn test_macro_fuel() {
     let err = t.render(context!(macros => 5)).unwrap_err();
     assert_eq!(err.kind(), ErrorKind::OutOfFuel);
 }
+
+#[test]
+fn test_fuel_remaining() {
+    use minijinja::State;
+
+    let mut env = Environment::new();
+    env.add_function("remaining", |state: &State| state.fuel_remaining());
+    assert_eq!(env.render_str("{{ remaining() }}", ()).unwrap(), "none");
+
+    env.set_fuel(Some(100));
+    let rv = env
+        .render_str(
+            "{{ remaining() }}|{% for x in range(10) %}{% endfor %}{{ remaining() }}",
+            (),
+        )
+        .unwrap();
+    let (before, after) = rv.split_once('|').unwrap();
+    let before: u64 = before.parse().unwrap();
+    let after: u64 = after.parse().unwrap();
+    assert!(before < 100);
+    assert!(after < before);
+}
+
+#[test]
+fn test_eval_expr_fuel() {
+    let mut env = Environment::new();
+    env.add_function(
+        "measure",
+        |state: &minijinja::State, expr: &str| -> Result<u64, minijinja::Error> {
+            let before = state.fuel_levels().unwrap().0;
+            state.eval_expr(expr)?;
+            Ok(state.fuel_levels().unwrap().0 - before)
+        },
+    );
+    env.set_fuel(Some(20));
+    let rv = env
+        .render_str("{{ measure('1 + 2') }}", context! {})
+        .unwrap();
+    assert!(rv.parse::<u64>().unwrap() > 0);
+
+    let expr = vec!["1"; 30].join(" + ");
+    let err = env
+        .render_str("{{ measure(expr) }}", context! { expr })
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::OutOfFuel);
+}
@@ -56,3 +56,197 @@ fn test_source_replace_dynamic() {
     let rv = env.get_template("a").unwrap().render(()).unwrap();
     assert_eq!(rv, "2");
 }
+
+#[test]
+fn test_loader_compiles_once() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let mut env = Environment::new();
+    env.set_loader({
+        let calls = calls.clone();
+        move |name| {
+            calls.fetch_add(1, Ordering::Relaxed);
+            Ok(match name {
+                "layout" => Some("[{% block body %}{% endblock %}]".into()),
+                "page" => Some("{% extends 'layout' %}{% block body %}page{% endblock %}".into()),
+                _ => None,
+            })
+        }
+    });
+
+    for _ in 0..3 {
+        let rv = env.get_template("page").unwrap().render(()).unwrap();
+        assert_eq!(rv, "[page]");
+    }
+    assert_eq!(calls.load(Ordering::Relaxed), 2);
+
+    env.remove_template("layout");
+    assert_eq!(
+        env.get_template("page").unwrap().render(()).unwrap(),
+        "[page]"
+    );
+    assert_eq!(calls.load(Ordering::Relaxed), 3);
+
+    env.clear_templates();
+    assert_eq!(
+        env.get_template("page").unwrap().render(()).unwrap(),
+        "[page]"
+    );
+    assert_eq!(calls.load(Ordering::Relaxed), 5);
+}
+
+#[test]
+fn test_inline_template_cache() {
+    use minijinja::AutoEscape;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    // the auto escape callback is invoked once per compiled template
+    let compiles = Arc::new(AtomicUsize::new(0));
+    let mut env = Environment::new();
+    env.set_auto_escape_callback({
+        let compiles = compiles.clone();
+        move |_| {
+            compiles.fetch_add(1, Ordering::Relaxed);
+            AutoEscape::None
+        }
+    });
+    assert_eq!(env.inline_template_cache_size(), 0);
+    env.set_inline_template_cache_size(2);
+    assert_eq!(env.inline_template_cache_size(), 2);
+
+    for _ in 0..3 {
+        assert_eq!(env.render_str("{{ 1 + 1 }}", ()).unwrap(), "2");
+    }
+    assert_eq!(compiles.load(Ordering::Relaxed), 1);
+
+    // the name is part of the key
+    env.render_named_str("other", "{{ 1 + 1 }}", ()).unwrap();
+    assert_eq!(compiles.load(Ordering::Relaxed), 2);
+
+    // evicts the least recently used template
+    env.render_str("{{ 1 + 1 }}", ()).unwrap();
+    env.render_str("{{ 2 + 2 }}", ()).unwrap();
+    assert_eq!(compiles.load(Ordering::Relaxed), 3);
+    env.render_str("{{ 1 + 1 }}", ()).unwrap();
+    assert_eq!(compiles.load(Ordering::Relaxed), 3);
+    env.render_named_str("other", "{{ 1 + 1 }}", ()).unwrap();
+    assert_eq!(compiles.load(Ordering::Relaxed), 4);
+
+    // changing settings that affect compilation does not reuse templates
+    assert_eq!(
+        env.render_str("{% if true %}\nx{% endif %}", ()).unwrap(),
+        "\nx"
+    );
+    env.set_trim_blocks(true);
+    assert_eq!(
+        env.render_str("{% if true %}\nx{% endif %}", ()).unwrap(),
+        "x"
+    );
+    assert_eq!(compiles.load(Ordering::Relaxed), 6);
+
+    // failed compilations are not cached and still report errors
+    assert!(env.render_str("{{ oops", ()).is_err());
+    assert!(env.render_str("{{ oops", ()).is_err());
+}
+
+#[test]
+#[cfg(all(feature = "bytecode", feature = "multi_template", feature = "macros"))]
+fn test_compiled_templates() {
+    use minijinja::{context, ErrorKind};
+
+    let mut env = Environment::new();
+    env.add_template(
+        "layout.html",
+        "<title>{% block title %}{% endblock %}</title>{% block body required %}{% endblock %}",
+    )
+    .unwrap();
+    env.add_template(
+        "page.html",
+        "{% extends 'layout.html' %}\
+         {% macro item(x) %}<li>{{ x|upper }}</li>{% endmacro %}\
+         {% block title %}{{ title }}{% endblock %}\
+         {% block body %}<ul>{% for x in [1.5, 'a', none, {'b': [true]}] %}{{ item(x) }}{% endfor %}</ul>{% endblock %}",
+    )
+    .unwrap();
+    let expected = env
+        .get_template("page.html")
+        .unwrap()
+        .render(context!(title => "<Hi>"))
+        .unwrap();
+
+    let mut other_env = Environment::new();
+    for name in ["layout.html", "page.html"] {
+        let data = env.get_template(name).unwrap().to_compiled_bytes().unwrap();
+        other_env.add_compiled_template(name, &data).unwrap();
+    }
+    let tmpl = other_env.get_template("page.html").unwrap();
+    assert_eq!(
+        tmpl.source(),
+        env.get_template("page.html").unwrap().source()
+    );
+    assert_eq!(tmpl.render(context!(title => "<Hi>")).unwrap(), expected);
+    assert_eq!(
+        tmpl.complexity(),
+        env.get_template("page.html").unwrap().complexity()
+    );
+
+    // errors still point to the right line
+    other_env
+        .add_compiled_template(
+            "broken.txt",
+            &env.template_from_str("\n{{ 1 + [] }}")
+                .unwrap()
+                .to_compiled_bytes()
+                .unwrap(),
+        )
+        .unwrap();
+    let err = other_env
+        .get_template("broken.txt")
+        .unwrap()
+        .render(())
+        .unwrap_err();
+    assert_eq!(err.line(), Some(2));
+
+    // corrupted data is rejected
+    let data = env
+        .get_template("page.html")
+        .unwrap()
+        .to_compiled_bytes()
+        .unwrap();
+    let err = other_env
+        .add_compiled_template("x", &data[..data.len() - 5])
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidBytecode);
+    let err = other_env
+        .add_compiled_template("x", b"Hello World!")
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidBytecode);
+
+    // jumps outside of the instructions are rejected
+    let source = "{% if x %}{% endif %}";
+    let mut data = env
+        .template_from_str(source)
+        .unwrap()
+        .to_compiled_bytes()
+        .unwrap();
+    let code_start = data
+        .windows(source.len())
+        .position(|x| x == source.as_bytes())
+        .unwrap()
+        + source.len();
+    // `Lookup("x")` (opcode 2, offset 6, length 1) is followed by
+    // `JumpIfFalse` (opcode 40) with the target as varint
+    let jump = code_start
+        + data[code_start..]
+            .windows(4)
+            .position(|x| x == [2, 6, 1, 40])
+            .unwrap()
+        + 3;
+    data[jump + 1] = 0x7f;
+    let err = other_env.add_compiled_template("x", &data).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidBytecode);
+    assert!(err.to_string().contains("jump target out of bounds"));
+}
@@ -6,7 +6,7 @@ use insta::assert_snapshot;
 use similar_asserts::assert_eq;
 
 use minijinja::value::{Kwargs, Object, Value};
-use minijinja::{args, context, render, Environment, ErrorKind};
+use minijinja::{args, context, render, try_render, Environment, ErrorKind};
 
 #[test]
 fn test_context() {
@@ -30,6 +30,38 @@ fn test_context_merge() {
     assert_eq!(ctx.get_attr("b").unwrap(), Value::from(2));
 }
 
+#[test]
+fn test_context_merge_leading() {
+    let base = context!(a => 1, b => 2);
+    let ctx = context! { ..base, b => 42, c => 3 };
+    assert_eq!(ctx.get_attr("a").unwrap(), Value::from(1));
+    assert_eq!(ctx.get_attr("b").unwrap(), Value::from(42));
+    assert_eq!(ctx.get_attr("c").unwrap(), Value::from(3));
+
+    let c = 23;
+    let one = context!(a => 1);
+    let two = context!(a => 2, d => 4);
+    let ctx = context! { ..one, ..two, c, };
+    assert_eq!(ctx.get_attr("a").unwrap(), Value::from(1));
+    assert_eq!(ctx.get_attr("c").unwrap(), Value::from(23));
+    assert_eq!(ctx.get_attr("d").unwrap(), Value::from(4));
+}
+
+#[test]
+fn test_context_merge_non_map() {
+    let env = Environment::new();
+    let ctx = context! { ..vec![1, 2, 3], a => 1 };
+    let err = env.render_str("{{ a }}", ctx).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidOperation);
+    assert_eq!(
+        err.detail(),
+        Some("cannot merge value of type sequence into context")
+    );
+
+    let ctx = context! { ..context!(a => 1), ..42 };
+    assert!(env.render_str("{{ a }}", ctx).is_err());
+}
+
 #[test]
 fn test_context_merge_custom() {
     #[derive(Debug, Clone)]
@@ -65,6 +97,23 @@ fn test_render() {
     assert_eq!(rv, "Hello World!");
 }
 
+#[test]
+fn test_try_render() {
+    let env = Environment::new();
+    let rv = try_render!(in env, "Hello {{ name }}!", name => "World").unwrap();
+    assert_eq!(rv, "Hello World!");
+
+    let name = "World";
+    let rv = try_render!("Hello {{ name }}!", name).unwrap();
+    assert_eq!(rv, "Hello World!");
+
+    let err = try_render!("Hello {{ name", name).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::SyntaxError);
+
+    let err = try_render!(in env, "{{ 1 + x }}", x => "foo").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidOperation);
+}
+
 #[test]
 fn test_args() {
     fn type_name_of_val<T: ?Sized>(_val: &T) -> &str {
@@ -11,3 +11,139 @@ fn test_state_lookup_global() {
     let rv = env.render_str("[{{ lookup_global() }}]", ()).unwrap();
     assert_eq!(rv, "[true]");
 }
+
+#[test]
+#[cfg(all(feature = "multi_template", feature = "macros"))]
+fn test_state_template_stack() {
+    let mut env = Environment::new();
+    env.add_function("stack", |state: &State| state.template_stack().join(">"));
+    env.add_template("layout", "[{{ stack() }}]{% block body %}{% endblock %}")
+        .unwrap();
+    env.add_template(
+        "page",
+        "{% extends 'layout' %}{% block body %}[{{ stack() }}]{% include 'nav' %}{% endblock %}",
+    )
+    .unwrap();
+    env.add_template(
+        "nav",
+        "{% from 'macros' import item %}[{{ stack() }}]{{ item() }}",
+    )
+    .unwrap();
+    env.add_template("macros", "{% macro item() %}[{{ stack() }}]{% endmacro %}")
+        .unwrap();
+    let rv = env.get_template("page").unwrap().render(()).unwrap();
+    assert_eq!(
+        rv,
+        "[page>layout][page>layout>page][page>layout>page>nav][page>layout>page>nav>macros]"
+    );
+}
+
+#[test]
+#[cfg(feature = "multi_template")]
+fn test_state_template_stack_include_extends() {
+    let mut env = Environment::new();
+    env.add_function("stack", |state: &State| state.template_stack().join(">"));
+    env.add_template("base", "{% block body %}{% endblock %}")
+        .unwrap();
+    env.add_template(
+        "child",
+        "{% extends 'base' %}{% block body %}[{{ stack() }}]{% endblock %}",
+    )
+    .unwrap();
+    env.add_template("main", "{% include 'child' %}[{{ stack() }}]")
+        .unwrap();
+    let rv = env.get_template("main").unwrap().render(()).unwrap();
+    assert_eq!(rv, "[main>child>base>child][main]");
+}
+
+#[test]
+#[cfg(feature = "multi_template")]
+fn test_state_eval_expr_template_stack() {
+    use minijinja::Error;
+
+    let mut env = Environment::new();
+    env.add_function("stack", |state: &State| {
+        format!("{}:{}", state.name(), state.template_stack().join(">"))
+    });
+    env.add_function(
+        "lookup",
+        |state: &State, expr: &str| -> Result<Value, Error> { state.eval_expr(expr) },
+    );
+    env.add_template("nav", "[{{ stack() }}][{{ lookup('stack()') }}]")
+        .unwrap();
+    env.add_template("main", "{% include 'nav' %}").unwrap();
+    let rv = env.get_template("main").unwrap().render(()).unwrap();
+    assert_eq!(rv, "[nav:main>nav][nav:main>nav]");
+}
+
+#[test]
+#[cfg(all(feature = "multi_template", feature = "macros"))]
+fn test_state_warnings() {
+    let mut env = Environment::new();
+    env.add_function("legacy", |state: &State, name: String| {
+        state.warn(format!("{} is deprecated", name));
+        ""
+    });
+    env.add_template(
+        "page",
+        "{% block body %}{{ legacy('a') }}{% include 'nav' %}{% endblock %}",
+    )
+    .unwrap();
+    env.add_template(
+        "nav",
+        "{% macro item() %}{{ legacy('c') }}{% endmacro %}{{ legacy('b') }}{{ item() }}",
+    )
+    .unwrap();
+    let (rv, warnings) = env
+        .get_template("page")
+        .unwrap()
+        .render_with_warnings(())
+        .unwrap();
+    assert_eq!(rv, "");
+    let warnings = warnings.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+    assert_eq!(
+        warnings,
+        [
+            "a is deprecated (in page, block body)",
+            "b is deprecated (in nav, block body)",
+            "c is deprecated (in nav)",
+        ]
+    );
+}
+
+#[test]
+#[cfg(feature = "builtins")]
+fn test_state_eval_expr() {
+    use minijinja::{context, Error, ErrorKind, UndefinedBehavior};
+
+    let mut env = Environment::new();
+    env.add_function(
+        "lookup",
+        |state: &State, expr: &str| -> Result<Value, Error> { state.eval_expr(expr) },
+    );
+    env.add_global("site", "example.com");
+    let rv = env
+        .render_str(
+            "{% for item in items %}{{ lookup('item.name|upper ~ \"@\" ~ site') }}|\
+             {{ lookup('loop.index * factor') }}|{{ lookup('missing') }};{% endfor %}",
+            context! {
+                items => vec![context!(name => "a"), context!(name => "b")],
+                factor => 10,
+            },
+        )
+        .unwrap();
+    assert_eq!(rv, "A@example.com|10|;B@example.com|20|;");
+
+    let err = env.render_str("{{ lookup('1 +') }}", ()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::SyntaxError);
+
+    let rv = env
+        .render_str("{{ lookup('\"yes\" if missing else \"no\"') }}", ())
+        .unwrap();
+    assert_eq!(rv, "no");
+    env.set_undefined_behavior(UndefinedBehavior::Strict);
+    let err = env
+        .render_str("{{ lookup('\"yes\" if missing else \"no\"') }}", ())
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UndefinedError);
+}
@@ -312,6 +312,19 @@ fn test_auto_escaping() {
     insta::assert_snapshot!(rv, @r###"foo"bar'baz"###);
 }
 
+#[test]
+fn test_set_block_filter_auto_escaping() {
+    let mut env = Environment::new();
+    env.add_template(
+        "nav.html",
+        "{% set nav | trim %}\n  <b>{{ var }}</b>\n{% endset %}[{{ nav }}]",
+    )
+    .unwrap();
+    let tmpl = env.get_template("nav.html").unwrap();
+    let rv = tmpl.render(context!(var => "<script>")).unwrap();
+    assert_eq!(rv, "[<b>&lt;script&gt;</b>]");
+}
+
 #[test]
 fn test_loop_changed() {
     let rv = minijinja::render!(
@@ -325,6 +338,29 @@ fn test_loop_changed() {
     assert_eq!(rv, "12345");
 }
 
+#[test]
+#[cfg(feature = "adjacent_loop_items")]
+fn test_loop_adjacent_items_lazy() {
+    let tmpl = "{% for i in items %}[{{ loop.previtem }}|{{ i }}|{{ loop.nextitem }}|{{ loop.last }}]{% endfor %}";
+    let expected = "[|0|1|false][0|1|2|false][1|2||true]";
+    assert_eq!(
+        minijinja::render!(tmpl, items => Value::make_iterable(|| 0..3)),
+        expected
+    );
+    assert_eq!(
+        minijinja::render!(tmpl, items => Value::make_one_shot_iterator(0..3)),
+        expected
+    );
+    assert_eq!(
+        minijinja::render!(tmpl, items => Value::make_iterable(|| 0..0)),
+        ""
+    );
+    assert_eq!(
+        minijinja::render!(tmpl, items => Value::make_one_shot_iterator(0..1)),
+        "[|0||true]"
+    );
+}
+
 // ideally this would work, but unfortunately the way serde flatten works makes it
 // impossible for us to support with the internal optimizations in the value model.
 // see https://github.com/mitsuhiko/minijinja/issues/222
@@ -394,7 +430,7 @@ fn test_flattening_sub_item_bad_attr() {
     assert_eq!(err.kind(), ErrorKind::BadSerialization);
     assert_eq!(
         err.detail(),
-        Some("can only flatten structs and maps (got a tuple struct)")
+        Some("can only flatten structs and maps (got a tuple struct) (at bad)")
     );
 }
 
@@ -412,7 +448,7 @@ fn test_flattening_sub_item_shielded_print() {
     let value = env.render_str("{{ good }}", ctx).unwrap();
     assert_eq!(
         value,
-        r#"{"bad": <invalid value: could not serialize to value: can only flatten structs and maps (got a tuple struct)>}"#
+        r#"{"bad": <invalid value: could not serialize to value: can only flatten structs and maps (got a tuple struct) (at bad)>}"#
     );
 }
 
@@ -434,6 +470,87 @@ fn test_custom_syntax() {
         .render_str("{for x in range(3)}${x}{endfor}{* nothing *}", ())
         .unwrap();
     assert_eq!(value, r"012");
+    assert_eq!(env.syntax().block_delimiters(), ("{", "}"));
+    assert_eq!(env.syntax().variable_delimiters(), ("${", "}"));
+    assert_eq!(env.syntax().comment_delimiters(), ("{*", "*}"));
+    assert_eq!(env.syntax().line_statement_prefix(), None);
+}
+
+#[test]
+#[cfg(feature = "custom_syntax")]
+fn test_custom_syntax_whitespace_control() {
+    let mut env = Environment::new();
+    env.set_syntax(
+        minijinja::syntax::SyntaxConfig::builder()
+            .block_delimiters("<%", "%>")
+            .variable_delimiters("${", "}")
+            .comment_delimiters("<#", "#>")
+            .build()
+            .unwrap(),
+    );
+    env.set_trim_blocks(true);
+    env.set_lstrip_blocks(true);
+
+    let render = |source: &str| env.render_str(source, context!(seq => [1, 2])).unwrap();
+    assert_eq!(
+        render("<ul>\n  <% for x in seq %>\n    <li>${ x }</li>\n  <% endfor %>\n</ul>"),
+        "<ul>\n    <li>1</li>\n    <li>2</li>\n</ul>"
+    );
+    assert_eq!(render("\t<% if true %>\nx\n\t<% endif %>\n"), "x\n");
+    assert_eq!(render("  <%+ if true %>x<% endif %>"), "  x");
+    assert_eq!(render("a  \n  <%- if true %>b<% endif %>"), "ab");
+    assert_eq!(render("a\n  <# comment #>\nb"), "a\nb");
+    assert_eq!(
+        render("  <% raw %>\n  ${ x }\n  <% endraw %>\nafter"),
+        "  ${ x }\nafter"
+    );
+}
+
+#[test]
+#[cfg(feature = "custom_syntax")]
+fn test_custom_syntax_validation() {
+    use minijinja::syntax::SyntaxConfig;
+
+    let err = SyntaxConfig::builder()
+        .block_delimiters("{{", "}}")
+        .build()
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidDelimiter);
+    assert_eq!(
+        err.detail(),
+        Some("block start delimiter '{{' is the same as the variable start delimiter")
+    );
+
+    let err = SyntaxConfig::builder()
+        .variable_delimiters("", "}}")
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        err.detail(),
+        Some("variable start delimiter must not be empty or whitespace")
+    );
+
+    let err = SyntaxConfig::builder()
+        .comment_delimiters("{#", " ")
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        err.detail(),
+        Some("comment end delimiter must not be empty or whitespace")
+    );
+
+    assert!(SyntaxConfig::builder()
+        .line_statement_prefix("#")
+        .build()
+        .is_ok());
+    let err = SyntaxConfig::builder()
+        .line_statement_prefix("  ")
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        err.detail(),
+        Some("line statement prefix must not be empty or whitespace")
+    );
 }
 
 #[test]
@@ -563,6 +680,20 @@ fn test_render_to_write_state() {
     assert_eq!(state.call_macro("bar", &[]).ok().as_deref(), Some("x"));
 }
 
+#[test]
+fn test_render_value() {
+    let env = Environment::new();
+    let tmpl = env
+        .template_from_str("{% set foo = 42 %}Hello {{ name }}!")
+        .unwrap();
+    let ctx = context!(name => "World");
+    assert_eq!(tmpl.render_value(ctx.clone()).unwrap(), "Hello World!");
+    let mut out = Vec::<u8>::new();
+    let state = tmpl.render_value_to_write(ctx, &mut out).unwrap();
+    assert_eq!(String::from_utf8_lossy(&out), "Hello World!");
+    assert_eq!(state.lookup("foo"), Some(Value::from(42)));
+}
+
 #[test]
 fn test_functions() {
     assert_snapshot!(
@@ -661,3 +792,252 @@ fn test_test_caching() {
     let rv = env.get_template("child.txt").unwrap().render(()).unwrap();
     assert_eq!(rv, "false");
 }
+
+#[test]
+fn test_template_blocks() {
+    let mut env = Environment::new();
+    env.add_template(
+        "layout.txt",
+        "{% block title %}{% endblock %}{% block body %}{% block nav %}{% endblock %}{% endblock %}",
+    )
+    .unwrap();
+    env.add_template(
+        "page.txt",
+        "{% extends 'layout.txt' %}{% block body %}{% block content %}{% endblock %}{% endblock %}",
+    )
+    .unwrap();
+    let tmpl = env.get_template("page.txt").unwrap();
+    assert_eq!(tmpl.blocks(), vec!["body", "content"]);
+    let state = tmpl.eval_to_state(()).unwrap();
+    assert_eq!(state.blocks(), vec!["body", "content", "nav", "title"]);
+}
+
+#[test]
+fn test_error_line_range() {
+    let env = Environment::new();
+    let tmpl = env
+        .template_from_str("first line\r\n{{ 1 }}\n  {{ foo + 1 }}\n")
+        .unwrap();
+    assert_eq!(&tmpl.source()[tmpl.line_range(1).unwrap()], "first line");
+    assert_eq!(&tmpl.source()[tmpl.line_range(2).unwrap()], "{{ 1 }}");
+    assert_eq!(tmpl.line_range(0), None);
+    assert_eq!(tmpl.line_range(4), None);
+
+    let err = tmpl.render(context!(foo => "x")).unwrap_err();
+    assert_eq!(err.line(), Some(3));
+    assert_eq!(
+        &tmpl.source()[tmpl.line_range(3).unwrap()],
+        "  {{ foo + 1 }}"
+    );
+    assert_eq!(&tmpl.source()[err.range().unwrap()], "foo + 1");
+}
+
+#[test]
+fn test_front_matter() {
+    let mut env = Environment::new();
+    env.add_template("before.txt", "---\ntitle: x\n---\nbody")
+        .unwrap();
+    env.set_front_matter_parser(|front_matter| {
+        serde_json::from_str::<serde_json::Value>(front_matter)
+            .map(Value::from_serialize)
+            .map_err(|err| {
+                Error::new(ErrorKind::BadSerialization, "invalid front matter").with_source(err)
+            })
+    });
+
+    // templates compiled before the parser was set are unaffected
+    let tmpl = env.get_template("before.txt").unwrap();
+    assert_eq!(tmpl.front_matter(), None);
+
+    env.add_template(
+        "post.txt",
+        "---\r\n{\"title\": \"Hello\", \"tags\": [1, 2]}\r\n---\r\n{{ title }}\n{{ missing.attr }}",
+    )
+    .unwrap();
+    let tmpl = env.get_template("post.txt").unwrap();
+    let front_matter = tmpl.front_matter().unwrap();
+    assert_eq!(
+        front_matter.get_attr("title").unwrap().as_str(),
+        Some("Hello")
+    );
+    assert_eq!(
+        tmpl.render(context!(title => "x")).unwrap_err().line(),
+        Some(5)
+    );
+    assert_eq!(tmpl.undeclared_variables(false).len(), 2);
+
+    env.add_template("plain.txt", "--- not front matter\n---\n{{ 42 }}")
+        .unwrap();
+    let tmpl = env.get_template("plain.txt").unwrap();
+    assert_eq!(tmpl.front_matter(), None);
+    assert_eq!(tmpl.render(()).unwrap(), "--- not front matter\n---\n42");
+
+    let err = env
+        .add_template("bad.txt", "---\nnot json\n---\nbody")
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::BadSerialization);
+    assert_eq!(err.name(), Some("bad.txt"));
+    assert_eq!(err.line(), Some(1));
+
+    env.set_front_matter_delimiter("+++");
+    env.add_template("toml.txt", "+++\n\"x\"\n+++\nbody")
+        .unwrap();
+    let tmpl = env.get_template("toml.txt").unwrap();
+    assert_eq!(tmpl.front_matter(), Some(Value::from("x")));
+    assert_eq!(tmpl.render(()).unwrap(), "body");
+}
+
+#[test]
+fn test_debug_dynamic_objects() {
+    #[derive(Debug)]
+    struct Point;
+
+    impl Object for Point {
+        fn get_value(self: &Arc<Self>, key: &Value) -> Option<Value> {
+            match key.as_str()? {
+                "x" => Some(Value::from(1)),
+                "y" => Some(Value::from(2)),
+                _ => None,
+            }
+        }
+
+        fn enumerate(self: &Arc<Self>) -> Enumerator {
+            Enumerator::Str(&["x", "y"])
+        }
+
+        fn render(self: &Arc<Self>, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("<point>")
+        }
+    }
+
+    #[derive(Debug)]
+    struct Opaque;
+
+    impl Object for Opaque {
+        fn get_value(self: &Arc<Self>, _key: &Value) -> Option<Value> {
+            Some(Value::from(true))
+        }
+
+        fn enumerate(self: &Arc<Self>) -> Enumerator {
+            Enumerator::NonEnumerable
+        }
+    }
+
+    let env = Environment::new();
+    let ctx = context!(point => Value::from_object(Point), opaque => Value::from_object(Opaque));
+    assert_snapshot!(env.render_str("{{ debug(point, opaque) }}", ctx.clone()).unwrap(), @r###"
+    [
+        {
+            "x": 1,
+            "y": 2,
+        },
+        <non-enumerable map Opaque>,
+    ]
+    "###);
+
+    let rv = env.render_str("{{ debug() }}", ctx).unwrap();
+    assert!(rv.contains("\"point\": {\n"));
+    assert!(rv.contains("\"opaque\": <non-enumerable map Opaque>,"));
+
+    let rv = env.render_str("{{ debug(range(1000)) }}", ()).unwrap();
+    assert_eq!(rv.lines().count(), 103);
+    assert!(rv.ends_with("    99,\n    ...,\n]"));
+}
+
+#[test]
+fn test_template_complexity() {
+    let mut env = Environment::new();
+    env.add_template("simple.txt", "Hello {{ name }}!").unwrap();
+    env.add_template(
+        "nested.txt",
+        "{% for a in b %}{% with x = a %}{% for c in x %}{{ c }}{% endfor %}{% endwith %}{% endfor %}\
+         {% block body %}{% for d in e %}{{ d }}{% endfor %}{% endblock %}",
+    )
+    .unwrap();
+
+    let simple = env.get_template("simple.txt").unwrap().complexity();
+    assert_eq!(simple.loop_count(), 0);
+    assert_eq!(simple.max_nesting(), 0);
+    assert!(simple.instruction_count() > 0);
+
+    let nested = env.get_template("nested.txt").unwrap().complexity();
+    assert_eq!(nested.loop_count(), 3);
+    assert_eq!(nested.max_nesting(), 3);
+    assert!(nested.instruction_count() > simple.instruction_count());
+
+    env.add_template(
+        "filtered.txt",
+        "{% for a in b if a %}{% for c in a if c %}{{ c }}{% endfor %}{% endfor %}",
+    )
+    .unwrap();
+    let filtered = env.get_template("filtered.txt").unwrap().complexity();
+    assert_eq!(filtered.loop_count(), 2);
+    assert_eq!(filtered.max_nesting(), 2);
+}
+
+#[test]
+fn test_template_complexity_imports() {
+    let mut env = Environment::new();
+    env.add_template("macros.txt", "{% macro m() %}{% endmacro %}")
+        .unwrap();
+    env.add_template(
+        "imports.txt",
+        "{% import 'macros.txt' as macros %}{% from 'macros.txt' import m %}\
+         {% for a in b %}{% import 'macros.txt' as inner %}{% endfor %}",
+    )
+    .unwrap();
+    let complexity = env.get_template("imports.txt").unwrap().complexity();
+    assert_eq!(complexity.loop_count(), 1);
+    assert_eq!(complexity.max_nesting(), 1);
+}
+
+#[test]
+#[cfg(feature = "debug")]
+fn test_error_render_report() {
+    use minijinja::ReportOptions;
+
+    let mut env = Environment::new();
+    env.set_debug(true);
+    env.add_function("fail", || -> Result<Value, Error> {
+        Err(Error::new(ErrorKind::InvalidOperation, "outer")
+            .with_source(std::io::Error::new(std::io::ErrorKind::Other, "inner")))
+    });
+    let tmpl = env
+        .template_from_named_str("report.txt", "{% set x = 1 %}\n{{ fail() }}")
+        .unwrap();
+    let err = tmpl.render(()).unwrap_err();
+
+    let mut full = String::new();
+    err.render_report(&mut full, ReportOptions::default())
+        .unwrap();
+    assert_eq!(full, format!("{err:#}\n\ncaused by: inner"));
+
+    let mut report = String::new();
+    err.render_report(
+        &mut report,
+        ReportOptions::default()
+            .with_line_numbers(false)
+            .with_referenced_variables(false)
+            .with_causes(false),
+    )
+    .unwrap();
+    insta::assert_snapshot!(report, @r###"
+    invalid operation: outer (in report.txt:2)
+    --------------------------------- report.txt ----------------------------------
+    | {% set x = 1 %}
+    > {{ fail() }}
+    i    ^^^^^^ invalid operation
+    ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+    "###);
+
+    let mut short = String::new();
+    err.render_report(
+        &mut short,
+        ReportOptions::default()
+            .with_source_snippet(false)
+            .with_referenced_variables(false)
+            .with_causes(false),
+    )
+    .unwrap();
+    assert_eq!(short, err.to_string());
+}
@@ -14,3 +14,134 @@ fn test_basics() {
     let state = env.empty_state();
     assert!(state.perform_test("test", args!(23, 23)).unwrap());
 }
+
+#[test]
+#[cfg(feature = "builtins")]
+fn test_sameas_identity() {
+    use std::sync::Arc;
+
+    use minijinja::context;
+    use minijinja::value::{Object, Value};
+
+    #[derive(Debug)]
+    struct Page(&'static str);
+
+    impl Object for Page {
+        fn get_value(self: &Arc<Self>, key: &Value) -> Option<Value> {
+            match key.as_str()? {
+                "title" => Some(Value::from(self.0)),
+                _ => None,
+            }
+        }
+    }
+
+    let pages = vec![
+        Value::from_object(Page("Index")),
+        Value::from_object(Page("Docs")),
+        Value::from_object(Page("Docs")),
+    ];
+    let env = Environment::new();
+    let rv = env
+        .render_str(
+            "{% for page in pages %}{{ page.title }}{% if page is sameas current %}*{% endif %} {% endfor %}",
+            context! { current => pages[2].clone(), pages },
+        )
+        .unwrap();
+    assert_eq!(rv, "Index Docs Docs* ");
+}
+
+#[test]
+#[cfg(feature = "builtins")]
+fn test_type_tests_dynamic_objects() {
+    use std::sync::Arc;
+
+    use minijinja::context;
+    use minijinja::value::{Enumerator, Object, ObjectRepr, Value};
+
+    #[derive(Debug)]
+    struct DynSeq;
+
+    impl Object for DynSeq {
+        fn repr(self: &Arc<Self>) -> ObjectRepr {
+            ObjectRepr::Seq
+        }
+
+        fn get_value(self: &Arc<Self>, key: &Value) -> Option<Value> {
+            key.as_usize().filter(|&idx| idx < 3).map(Value::from)
+        }
+
+        fn enumerate(self: &Arc<Self>) -> Enumerator {
+            Enumerator::Seq(3)
+        }
+    }
+
+    #[derive(Debug)]
+    struct DynMap;
+
+    impl Object for DynMap {
+        fn get_value(self: &Arc<Self>, key: &Value) -> Option<Value> {
+            match key.as_str()? {
+                "a" => Some(Value::from(1)),
+                _ => None,
+            }
+        }
+
+        fn enumerate(self: &Arc<Self>) -> Enumerator {
+            Enumerator::Str(&["a"])
+        }
+    }
+
+    #[derive(Debug)]
+    struct DynIterable;
+
+    impl Object for DynIterable {
+        fn repr(self: &Arc<Self>) -> ObjectRepr {
+            ObjectRepr::Iterable
+        }
+
+        fn enumerate(self: &Arc<Self>) -> Enumerator {
+            Enumerator::Iter(Box::new((0..3).map(Value::from)))
+        }
+    }
+
+    #[derive(Debug)]
+    struct Plain;
+
+    impl Object for Plain {
+        fn repr(self: &Arc<Self>) -> ObjectRepr {
+            ObjectRepr::Plain
+        }
+    }
+
+    let env = Environment::new();
+    let rv = env
+        .render_str(
+            "{% for name in ['seq', 'map', 'iter', 'plain', 'native_seq', 'native_map', 'string', 'none'] %}\
+             {%- set v = values[name] %}\
+             {{ name }}: {{ [v is sequence, v is mapping, v is iterable] }}\n\
+             {% endfor %}",
+            context! {
+                values => context! {
+                    seq => Value::from_object(DynSeq),
+                    map => Value::from_object(DynMap),
+                    iter => Value::from_object(DynIterable),
+                    plain => Value::from_object(Plain),
+                    native_seq => vec![1, 2],
+                    native_map => context! { a => 1 },
+                    string => "abc",
+                    none => (),
+                }
+            },
+        )
+        .unwrap();
+    insta::assert_snapshot!(rv, @r###"
+    seq: [true, false, true]
+    map: [false, true, true]
+    iter: [false, false, true]
+    plain: [false, false, false]
+    native_seq: [true, false, true]
+    native_map: [false, true, true]
+    string: [false, false, true]
+    none: [false, false, true]
+    "###);
+}
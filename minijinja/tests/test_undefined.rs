@@ -38,6 +38,34 @@ fn test_lenient_undefined() {
     assert_eq!(render!(in env, "{{ 42 in undefined }}"), "false");
 }
 
+#[test]
+fn test_call_non_callable() {
+    let env = Environment::new();
+    let ctx = context! { x => 42, items => vec![1, 2] };
+
+    let err = env.render_str("{{ x() }}", &ctx).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidOperation);
+    assert_eq!(
+        err.detail(),
+        Some("x is not callable (value of type number)")
+    );
+
+    let err = env.render_str("{{ items() }}", &ctx).unwrap_err();
+    assert_eq!(
+        err.detail(),
+        Some("items is not callable (value of type sequence)")
+    );
+
+    let err = env.render_str("{{ items[0]() }}", &ctx).unwrap_err();
+    assert_eq!(err.detail(), Some("value of type number is not callable"));
+
+    let err = env.render_str("{{ missing() }}", &ctx).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnknownFunction);
+
+    let err = env.render_str("{{ undefined.foo() }}", &ctx).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnknownMethod);
+}
+
 #[test]
 fn test_strict_undefined() {
     let mut env = Environment::new();
@@ -99,6 +127,42 @@ fn test_strict_undefined() {
     );
 }
 
+#[test]
+fn test_strict_output_undefined() {
+    let mut env = Environment::new();
+    env.set_undefined_behavior(UndefinedBehavior::StrictOutput);
+
+    assert_eq!(
+        env.render_str("<{{ undefined }}>", ()).unwrap_err().kind(),
+        ErrorKind::UndefinedError
+    );
+    assert_eq!(
+        env.render_str("{{ true.missing_attribute }}", ())
+            .unwrap_err()
+            .kind(),
+        ErrorKind::UndefinedError
+    );
+    assert_eq!(
+        env.render_str("{{ undefined.missing_attribute }}", ())
+            .unwrap_err()
+            .kind(),
+        ErrorKind::UndefinedError
+    );
+    assert_eq!(
+        render!(in env, "<{% if undefined %}yes{% else %}no{% endif %}>"),
+        "<no>"
+    );
+    assert_eq!(render!(in env, "{{ 'yes' if undefined else 'no' }}"), "no");
+    assert_eq!(render!(in env, "{{ undefined or 'fallback' }}"), "fallback");
+    assert_eq!(
+        render!(in env, "<{% for x in undefined %}...{% endfor %}>"),
+        "<>"
+    );
+    assert_eq!(render!(in env, "{{ undefined is undefined }}"), "true");
+    assert_eq!(render!(in env, "{{ undefined|default('x') }}"), "x");
+    assert_eq!(render!(in env, "{{ undefined|list }}"), "[]");
+}
+
 #[test]
 fn test_chainable_undefined() {
     let mut env = Environment::new();
@@ -125,4 +189,46 @@ fn test_chainable_undefined() {
     assert_eq!(render!(in env, "{{ undefined|list }}"), "[]");
     assert_eq!(render!(in env, "<{{ undefined|test }}>"), "<>");
     assert_eq!(render!(in env, "{{ 42 in undefined }}"), "false");
+    assert_eq!(render!(in env, "<{{ missing_function() }}>"), "<>");
+    assert_eq!(render!(in env, "<{{ undefined.foo.bar(1, 2) }}>"), "<>");
+    assert_eq!(render!(in env, "<{{ undefined[0]() }}>"), "<>");
+    assert_eq!(
+        render!(in env, "{{ missing_function() is undefined }}"),
+        "true"
+    );
+}
+
+#[test]
+fn test_template_undefined_behavior_override() {
+    let mut env = Environment::new();
+    env.add_template("inc", "[{{ missing }}]").unwrap();
+    env.add_template("main", "{{ x }}{% include 'inc' %}{{ x|upper }}")
+        .unwrap();
+    env.add_function("behavior", |state: &State| {
+        format!("{:?}", state.undefined_behavior())
+    });
+
+    let tmpl = env.get_template("main").unwrap();
+    assert_eq!(tmpl.undefined_behavior(), UndefinedBehavior::Lenient);
+    assert_eq!(tmpl.render(()).unwrap(), "[]");
+
+    // the override also applies to included templates
+    let strict = tmpl
+        .clone()
+        .with_undefined_behavior(UndefinedBehavior::Strict);
+    let err = strict.render(context!(x => "a")).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::BadInclude);
+    let source = std::error::Error::source(&err).unwrap();
+    assert_eq!(
+        source.downcast_ref::<minijinja::Error>().unwrap().kind(),
+        ErrorKind::UndefinedError
+    );
+    assert_eq!(tmpl.render(context!(x => "a")).unwrap(), "a[]A");
+
+    let tmpl = env
+        .template_from_str("{{ behavior() }}")
+        .unwrap()
+        .with_undefined_behavior(UndefinedBehavior::Chainable);
+    assert_eq!(tmpl.render(()).unwrap(), "Chainable");
+    assert_eq!(env.undefined_behavior(), UndefinedBehavior::Lenient);
 }
@@ -165,6 +165,22 @@ fn test_value_by_index() {
     assert!(val.get_item_by_index(4).unwrap().is_undefined());
 }
 
+#[test]
+fn test_value_get_path() {
+    let val = context! {
+        a => context! {
+            b => vec![context! { c => 42 }],
+        },
+        d => "x",
+    };
+    assert_eq!(val.get_path("a.b.0.c"), Value::from(42));
+    assert_eq!(val.get_path("d"), Value::from("x"));
+    assert!(val.get_path("a.b.1.c").is_undefined());
+    assert!(val.get_path("a.missing.c").is_undefined());
+    assert!(val.get_path("d.0.e").is_undefined());
+    assert!(Value::UNDEFINED.get_path("a").is_undefined());
+}
+
 #[test]
 fn test_map_object_iteration_and_indexing() {
     #[derive(Debug, Clone)]
@@ -353,6 +369,66 @@ fn test_kwargs_error() {
     assert_eq!(bar.detail(), Some("missing keyword argument 'bar'"));
 }
 
+#[test]
+fn test_kwargs_order() {
+    let kwargs = Kwargs::from_iter([
+        ("zebra", Value::from(1)),
+        ("apple", Value::from(2)),
+        ("mango", Value::from(3)),
+    ]);
+    assert_eq!(
+        kwargs.args().collect::<Vec<_>>(),
+        vec!["zebra", "apple", "mango"]
+    );
+
+    let mut env = Environment::new();
+    env.add_function("keys", |kwargs: Kwargs| {
+        kwargs.args().collect::<Vec<_>>().join(",")
+    });
+    let rv = env
+        .render_str(
+            "{{ keys(zebra=1, apple=x, mango=3) }}|{{ keys(b=1, **{'c': 2}, a=x) }}",
+            context! { x => 42 },
+        )
+        .unwrap();
+    assert_eq!(rv, "zebra,apple,mango|b,c,a");
+}
+
+#[test]
+fn test_call_with_empty_state() {
+    #[derive(Debug)]
+    struct Greeter;
+
+    impl Object for Greeter {
+        fn call_method(
+            self: &Arc<Self>,
+            _state: &minijinja::State,
+            method: &str,
+            args: &[Value],
+        ) -> Result<Value, Error> {
+            match method {
+                "greet" => {
+                    let (name,): (&str,) = minijinja::value::from_args(args)?;
+                    Ok(Value::from(format!("Hello {name}!")))
+                }
+                _ => Err(Error::from(ErrorKind::UnknownMethod)),
+            }
+        }
+    }
+
+    let mut env = Environment::new();
+    env.add_function("double", |x: i64| x * 2);
+    let state = env.empty_state();
+    let double = state.lookup("double").unwrap();
+    assert_eq!(double.call(&state, args!(21)).unwrap(), Value::from(42));
+
+    let greeter = Value::from_object(Greeter);
+    let rv = greeter
+        .call_method(&state, "greet", args!("World"))
+        .unwrap();
+    assert_eq!(rv.as_str(), Some("Hello World!"));
+}
+
 #[test]
 fn test_return_none() {
     let env = Environment::empty();
@@ -506,6 +582,24 @@ fn test_one_shot_iterator() {
     assert_snapshot!(rv, @r###"abcde"###);
 }
 
+#[test]
+fn test_from_iterator() {
+    let value = Value::from_iterator("abc".chars().map(|x| x.to_string()));
+    assert_eq!(value.kind(), ValueKind::Seq);
+    let rv = render!(
+        "{{ iter|length }}: {% for item in iter %}{{ item }}{% endfor %}{% for item in iter %}{{ item }}{% endfor %}",
+        iter => value
+    );
+    assert_snapshot!(rv, @"3: abcabc");
+
+    // a one-shot iterator comes up empty on the second pass
+    let rv = render!(
+        "{{ iter|list|length }}: {% for item in iter %}{{ item }}{% endfor %}",
+        iter => Value::make_one_shot_iterator('a'..'d')
+    );
+    assert_snapshot!(rv, @"3: ");
+}
+
 #[test]
 fn test_make_iterable() {
     let value = Value::make_iterable(|| 0..10);
@@ -1216,3 +1310,271 @@ fn test_bytes() {
         "b'\\'foo\"'"
     );
 }
+
+#[test]
+fn test_merge() {
+    let defaults = context! { a => 1, nested => context! { x => 1, y => 2 } };
+    let overrides = context! { b => 2, nested => context! { y => 3 } };
+
+    let merged = defaults.merge(&overrides, false).unwrap();
+    assert_eq!(merged.get_attr("a").unwrap(), Value::from(1));
+    assert_eq!(merged.get_attr("b").unwrap(), Value::from(2));
+    let nested = merged.get_attr("nested").unwrap();
+    assert!(nested.get_attr("x").unwrap().is_undefined());
+    assert_eq!(nested.get_attr("y").unwrap(), Value::from(3));
+
+    let merged = defaults.merge(&overrides, true).unwrap();
+    let nested = merged.get_attr("nested").unwrap();
+    assert_eq!(nested.get_attr("x").unwrap(), Value::from(1));
+    assert_eq!(nested.get_attr("y").unwrap(), Value::from(3));
+
+    let err = defaults.merge(&Value::from(42), false).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidOperation);
+    assert_eq!(
+        err.to_string(),
+        "invalid operation: cannot merge value of type number"
+    );
+}
+
+#[test]
+fn test_typed_accessors() {
+    assert_eq!(Value::from(42).as_f64(), Some(42.0));
+    assert_eq!(Value::from(-1.5).as_f64(), Some(-1.5));
+    assert_eq!(Value::from(9007199254740993i64).as_f64(), None);
+    assert_eq!(Value::from(true).as_f64(), None);
+    assert_eq!(Value::from("1.0").as_f64(), None);
+
+    assert_eq!(Value::from(i128::MIN).as_i128(), Some(i128::MIN));
+    assert_eq!(Value::from(23u8).as_i128(), Some(23));
+    assert_eq!(Value::from(u128::MAX).as_i128(), None);
+    assert_eq!(Value::from(()).as_i128(), None);
+    assert_eq!(Value::from(true).as_i128(), None);
+
+    assert_eq!(Value::from(true).as_bool(), Some(true));
+    assert_eq!(Value::from(1).as_bool(), None);
+    assert_eq!(Value::UNDEFINED.as_bool(), None);
+
+    assert_eq!(
+        Value::from_bytes(vec![1, 2]).as_bytes(),
+        Some(&[1u8, 2][..])
+    );
+    assert_eq!(Value::from(42).as_bytes(), None);
+}
+
+#[test]
+fn test_value_kind_names() {
+    #[derive(Debug)]
+    struct Plain;
+
+    impl Object for Plain {
+        fn repr(self: &Arc<Self>) -> ObjectRepr {
+            ObjectRepr::Plain
+        }
+    }
+
+    let env = Environment::new();
+    for (value, name, test) in [
+        (Value::UNDEFINED, "undefined", Some("undefined")),
+        (Value::from(()), "none", Some("none")),
+        (Value::from(true), "bool", Some("boolean")),
+        (Value::from(42), "number", Some("number")),
+        (Value::from("x"), "string", Some("string")),
+        (Value::from_bytes(vec![1]), "bytes", None),
+        (Value::from(vec![1]), "sequence", Some("sequence")),
+        (context! { a => 1 }, "map", Some("mapping")),
+        (Value::make_iterable(|| 0..3), "iterable", None),
+        (Value::from_object(Plain), "plain", None),
+    ] {
+        assert_eq!(value.kind().name(), name);
+        if let Some(test) = test {
+            let source = format!("v is {}", test);
+            let expr = env.compile_expression(&source).unwrap();
+            assert!(expr.eval(context! { v => value }).unwrap().is_true());
+        }
+    }
+    assert_eq!(ValueKind::Invalid.name(), "invalid");
+}
+
+#[test]
+fn test_serialization_error_path() {
+    #[derive(serde::Serialize)]
+    struct Bad {
+        a: i32,
+        #[serde(flatten)]
+        b: i32,
+    }
+
+    #[derive(serde::Serialize)]
+    struct Outer {
+        items: Vec<BTreeMap<&'static str, Bad>>,
+    }
+
+    let value = Value::from_serialize(Outer {
+        items: vec![BTreeMap::new(), BTreeMap::from([("x", Bad { a: 1, b: 2 })])],
+    });
+    let err = value.get_path("items.1.x").to_string();
+    assert!(err.contains("can only flatten structs and maps"));
+    let env = Environment::new();
+    let err = env.render_str("{{ items[1].x }}", &value).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::BadSerialization);
+    assert_eq!(
+        err.detail(),
+        Some("can only flatten structs and maps (got an integer) (at items.1.x)")
+    );
+
+    let err = env
+        .render_str("{{ bad }}", context! { bad => Bad { a: 1, b: 2 } })
+        .unwrap_err();
+    assert_eq!(
+        err.detail(),
+        Some("can only flatten structs and maps (got an integer) (at bad)")
+    );
+}
+
+#[test]
+fn test_serialize_enums() {
+    #[derive(serde::Serialize)]
+    struct Bad {
+        #[serde(flatten)]
+        b: i32,
+    }
+
+    #[derive(serde::Serialize)]
+    enum Shape {
+        Empty,
+        Square(u32),
+        Rect(u32, u32),
+        Circle { r: u32 },
+        Broken { inner: Bad },
+        Pair(u32, Bad),
+    }
+
+    let shapes = Value::from_serialize(vec![
+        Shape::Empty,
+        Shape::Square(2),
+        Shape::Rect(2, 3),
+        Shape::Circle { r: 4 },
+    ]);
+    assert_eq!(shapes.get_item_by_index(0).unwrap(), Value::from("Empty"));
+    for (idx, variant) in [(1, "Square"), (2, "Rect"), (3, "Circle")] {
+        let shape = shapes.get_item_by_index(idx).unwrap();
+        assert_eq!(shape.kind(), ValueKind::Map);
+        assert_eq!(shape.len(), Some(1));
+        assert!(!shape.get_attr(variant).unwrap().is_undefined());
+    }
+    assert_eq!(
+        shapes
+            .get_item_by_index(2)
+            .unwrap()
+            .get_attr("Rect")
+            .unwrap(),
+        Value::from(vec![2, 3])
+    );
+    assert_eq!(
+        shapes.get_item_by_index(3).unwrap().to_string(),
+        r#"{"Circle": {"r": 4}}"#
+    );
+
+    let rv = render!(
+        "{% for shape in shapes %}\
+         {%- if 'Square' in shape %}square {{ shape.Square }}\
+         {%- elif 'Rect' in shape %}rect {{ shape.Rect[0] }}x{{ shape.Rect[1] }}\
+         {%- elif 'Circle' in shape %}circle {{ shape.Circle.r }}\
+         {%- else %}{{ shape }}\
+         {%- endif %};{% endfor %}",
+        shapes
+    );
+    assert_eq!(rv, "Empty;square 2;rect 2x3;circle 4;");
+
+    let env = Environment::new();
+    let err = env
+        .render_str(
+            "{{ shape.Broken.inner }}",
+            context! { shape => Shape::Broken { inner: Bad { b: 1 } } },
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.detail(),
+        Some("can only flatten structs and maps (got an integer) (at shape.Broken.inner)")
+    );
+    let err = env
+        .render_str(
+            "{{ shape.Pair[1] }}",
+            context! { shape => Shape::Pair(1, Bad { b: 1 }) },
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.detail(),
+        Some("can only flatten structs and maps (got an integer) (at shape.Pair.1)")
+    );
+}
+
+#[test]
+fn test_object_id() {
+    let obj = Value::from_object(vec![Value::from(1), Value::from(2)]);
+    let ctx = context! { a => obj.clone(), b => obj.clone(), c => vec![1, 2] };
+    assert_eq!(obj.object_id(), ctx.get_attr("a").unwrap().object_id());
+    assert_eq!(
+        ctx.get_attr("a").unwrap().object_id(),
+        ctx.get_attr("b").unwrap().object_id()
+    );
+    assert_ne!(obj.object_id(), ctx.get_attr("c").unwrap().object_id());
+    assert_eq!(Value::from("x").object_id(), None);
+    assert_eq!(Value::UNDEFINED.object_id(), None);
+
+    let seen = Arc::new(std::sync::Mutex::new(BTreeSet::new()));
+    let mut env = Environment::new();
+    env.add_filter("seen", {
+        let seen = seen.clone();
+        move |value: Value| seen.lock().unwrap().insert(value.object_id())
+    });
+    let rv = env
+        .render_str("{{ a|seen }} {{ b|seen }} {{ c|seen }}", ctx)
+        .unwrap();
+    assert_eq!(rv, "true false true");
+}
+
+#[test]
+fn test_function_signature() {
+    use minijinja::functions::FunctionSignature;
+
+    let func = Value::from_function_with_signature(
+        |a: i32, kwargs: Kwargs| -> Result<i32, Error> {
+            let b: Option<i32> = kwargs.get("b")?;
+            kwargs.assert_all_used()?;
+            Ok(a + b.unwrap_or(0))
+        },
+        FunctionSignature::new(["a"]).with_kwargs(true),
+    );
+    let sig = func.function_signature().unwrap();
+    assert_eq!(sig.args().collect::<Vec<_>>(), ["a"]);
+    assert!(sig.takes_kwargs());
+    assert_eq!(sig.to_string(), "(a, **kwargs)");
+
+    let rv = minijinja::render!("{{ f(1, b=2) }}", f => func);
+    assert_eq!(rv, "3");
+
+    assert!(Value::from_function(|| 42).function_signature().is_none());
+    assert!(Value::from(42).function_signature().is_none());
+}
+
+#[test]
+fn test_owned_small_strings() {
+    let short = Value::from(String::from("class"));
+    assert_eq!(short, Value::from("class"));
+    assert_eq!(short.as_str(), Some("class"));
+    assert!(!short.is_safe());
+    let long = Value::from("x".repeat(100));
+    assert_eq!(long.as_str().map(|x| x.len()), Some(100));
+    let mut set = HashSet::new();
+    set.insert(Value::from(String::from("class")));
+    assert!(set.contains(&Value::from("class")));
+    assert_eq!(
+        render!(
+            "{{ a }}|{{ a|escape }}|{{ b }}",
+            a => String::from("<class>"),
+            b => "x".repeat(30)
+        ),
+        format!("<class>|&lt;class&gt;|{}", "x".repeat(30))
+    );
+}
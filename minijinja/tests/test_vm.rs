@@ -3,7 +3,7 @@ use std::collections::BTreeMap;
 
 use minijinja::machinery::{make_string_output, CodeGenerator, Instruction, Instructions, Vm};
 use minijinja::value::Value;
-use minijinja::{AutoEscape, Environment, Error};
+use minijinja::{AutoEscape, Environment, Error, ErrorKind};
 
 use similar_asserts::assert_eq;
 
@@ -23,6 +23,7 @@ pub fn simple_eval<S: serde::Serialize>(
         &empty_blocks,
         &mut output,
         AutoEscape::None,
+        env.undefined_behavior(),
     )?;
     Ok(rv)
 }
@@ -343,3 +344,15 @@ fn test_deep_recursion() {
         .collect::<Vec<_>>();
     assert_eq!(pieces, (0..limit).collect::<Vec<_>>());
 }
+
+#[test]
+fn test_recursion_limit_error_kind() {
+    let mut env = Environment::new();
+    env.set_recursion_limit(20);
+    assert_eq!(env.recursion_limit(), 20);
+    let tmpl = env
+        .template_from_str("{% macro foo(i) %}{{ foo(i + 1) }}{% endmacro %}{{ foo(0) }}")
+        .unwrap();
+    let err = tmpl.render(()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::RecursionLimitExceeded);
+}